@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::db::{ImportError, JobRepository};
+use crate::middleware::Caller;
+use crate::models::{Job, JobResponse, JobStatus, JobType, ResourceLimits};
+use crate::podman::{ContainerInfo, ContainerState};
+use crate::AppState;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new().route("/import", axum::routing::post(import_container))
+}
+
+/// Request body for `POST /admin/import`
+#[derive(Debug, Deserialize)]
+pub struct ImportContainerRequest {
+    /// ID or name of the container to import
+    pub container: String,
+    /// Owner of the new job (default: the calling admin)
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// `worker` (default) or `agent`; parsed by the handler so a bad value
+    /// gets `invalid_enum_value`
+    #[serde(rename = "type", default)]
+    pub job_type: Option<String>,
+    #[serde(default = "default_import_timeout")]
+    pub timeout_minutes: i32,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn default_import_timeout() -> i32 {
+    120
+}
+
+/// Job status for an imported container: running (or paused) ones are
+/// running, finished ones completed or failed by `job`'s success exit codes.
+/// A finished container without an exit code failed. Containers that were
+/// never started can't be imported.
+pub fn imported_status(info: &ContainerInfo, job: &Job) -> Option<JobStatus> {
+    match info.state {
+        ContainerState::Running | ContainerState::Paused => Some(JobStatus::Running),
+        ContainerState::Exited | ContainerState::Stopped => Some(
+            info.exit_code
+                .map_or(JobStatus::Failed, |exit_code| job.status_for_exit(exit_code)),
+        ),
+        ContainerState::Created | ContainerState::Unknown => None,
+    }
+}
+
+/// POST /admin/import - admin only; start managing a container flashpods
+/// didn't create, e.g. while migrating onto it
+async fn import_container(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<ImportContainerRequest>,
+) -> Result<(StatusCode, Json<JobResponse>), (StatusCode, Json<serde_json::Value>)> {
+    caller.require_admin("Only admins can import containers")?;
+    let job_type = match req.job_type.as_deref() {
+        Some(job_type) => job_type.parse().map_err(crate::jobs::invalid_enum_value)?,
+        None => JobType::Worker,
+    };
+    if req.container.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_container",
+                "message": "container must be a container ID or name"
+            })),
+        ));
+    }
+    if let Err((code, message)) =
+        crate::jobs::check_metadata_limits("labels", &req.labels, &state.job_config)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": code,
+                "message": message
+            })),
+        ));
+    }
+
+    let podman = state.podman.clone();
+    let container = req.container.clone();
+    let inspected = match tokio::task::spawn_blocking(move || podman.inspect_container(&container)).await {
+        Ok(inspected) => inspected.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let details = match inspected {
+        Ok(Some(details)) => details,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "container_not_found",
+                    "message": format!("No container {:?}", req.container)
+                })),
+            ));
+        }
+        Err(e) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "podman_error",
+                    "message": e
+                })),
+            ));
+        }
+    };
+
+    let already_managed = |job_id: String| {
+        (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "already_managed",
+                "message": format!("Container {} is already managed as job {}", req.container, job_id),
+                "job_id": job_id
+            })),
+        )
+    };
+    // Containers flashpods started carry their job's id; imported ones are
+    // found by theirs when the job is created
+    if let Some(job_id) = details.info.labels.get("flashpods-job-id") {
+        return Err(already_managed(job_id.clone()));
+    }
+
+    let (_, _, timeout_minutes) =
+        ResourceLimits::for_job_type(job_type).clamp(1, 1, req.timeout_minutes);
+    let now = Utc::now();
+    let mut job = Job {
+        id: JobRepository::generate_id(),
+        user_id: req.user_id.clone().unwrap_or_else(|| caller.user_id.clone()),
+        job_type,
+        status: JobStatus::Running,
+        command: details.command,
+        commands: None,
+        task: None,
+        context: None,
+        git_branch: None,
+        files_id: None,
+        input_from_job: None,
+        image: details.image,
+        // Resource accounting uses what the container really has, not the
+        // per-type submission limits
+        cpus: details.cpus.unwrap_or(1),
+        memory_gb: details.memory_gb.unwrap_or(1),
+        timeout_minutes,
+        restartable: false,
+        start_after: None,
+        secret_refs: Vec::new(),
+        max_total_runtime_minutes: None,
+        node_selector: Default::default(),
+        artifact_paths: Vec::new(),
+        ephemeral: false,
+        success_exit_codes: vec![0],
+        shm_size_mb: None,
+        promote_log_to_artifact: false,
+        runtime_seconds: 0,
+        cost_units: None,
+        failure_category: None,
+        log_created_at: None,
+        logs_deleted_at: None,
+        submission: Default::default(),
+        container_id: Some(details.info.id.clone()),
+        exit_code: None,
+        error: None,
+        created_at: now,
+        started_at: Some(details.started_at.unwrap_or(now)),
+        completed_at: None,
+        pending_reason: None,
+        labels: req.labels.clone(),
+        annotations: Default::default(),
+    };
+
+    let Some(status) = imported_status(&details.info, &job) else {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "container_not_started",
+                "message": format!(
+                    "Container {} is {}; only started containers can be imported",
+                    req.container, details.info.state
+                )
+            })),
+        ));
+    };
+    if status.is_terminal() {
+        job.exit_code = details.info.exit_code;
+        job.completed_at = Some(details.finished_at.unwrap_or(now));
+    }
+    job.status = status;
+
+    let job = match state.job_repo.create_imported(&job).await {
+        Ok(job) => job,
+        Err(ImportError::AlreadyManaged(job_id)) => return Err(already_managed(job_id)),
+        Err(ImportError::Database(e)) => {
+            return Err(crate::db::database_error_response(&e, e.to_string()))
+        }
+    };
+    tracing::warn!(
+        "Container {} imported as job {} for {} by {}",
+        req.container,
+        job.id,
+        job.user_id,
+        caller.user_id
+    );
+    Ok((StatusCode::CREATED, Json(job.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const INSPECT: &str = r#"[ "$1" = inspect ] || exit 0
+case "$4" in
+  legacy) cat <<'JSON'
+[{"Id": "ctr_legacy", "Name": "legacy", "State": {"Status": "running", "StartedAt": "2026-01-21T10:00:00Z"},
+  "Config": {"Image": "ubuntu:22.04", "Cmd": ["sleep", "infinity"], "Labels": {"team": "ml"}},
+  "HostConfig": {"NanoCpus": 2000000000, "Memory": 4294967296}}]
+JSON
+  ;;
+  crashed) cat <<'JSON'
+[{"Id": "ctr_crashed", "Name": "crashed",
+  "State": {"Status": "exited", "ExitCode": 2, "StartedAt": "2026-01-21T10:00:00Z", "FinishedAt": "2026-01-21T10:05:00Z"},
+  "Config": {"Image": "ubuntu:22.04", "Cmd": ["make"]}}]
+JSON
+  ;;
+  fresh) echo '[{"Id": "ctr_fresh", "Name": "fresh", "State": {"Status": "created"}}]' ;;
+  ours) echo '[{"Id": "ctr_ours", "State": {"Status": "running"}, "Config": {"Labels": {"flashpods-job-id": "job_ours"}}}]' ;;
+  *) echo "Error: no such container $4" >&2; exit 125 ;;
+esac"#;
+
+    async fn import(state: AppState, is_admin: bool, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/import")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        request.extensions_mut().insert(Caller {
+            user_id: "ops".to_string(),
+            is_admin,
+        });
+        let response = routes().with_state(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_import_running_container_as_managed_job() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), INSPECT)),
+            ..crate::test_state().await
+        };
+
+        let (status, body) = import(
+            state.clone(),
+            true,
+            serde_json::json!({"container": "legacy", "user_id": "alice", "labels": {"migrated": "yes"}}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let job_id = body["id"].as_str().unwrap().to_string();
+
+        let job = state.job_repo.get(&job_id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert_eq!(job.user_id, "alice");
+        assert_eq!(job.container_id.as_deref(), Some("ctr_legacy"));
+        assert_eq!(job.image, "ubuntu:22.04");
+        assert_eq!(job.command.as_deref(), Some("sleep infinity"));
+        assert_eq!((job.cpus, job.memory_gb), (2, 4));
+        assert_eq!(job.started_at.unwrap().to_rfc3339(), "2026-01-21T10:00:00+00:00");
+        assert_eq!(job.labels.get("migrated").map(String::as_str), Some("yes"));
+        // It now counts towards the host's usage like any running job
+        assert_eq!(state.job_repo.get_active_jobs().await.unwrap().len(), 1);
+
+        // A second import of the same container is refused
+        let (status, body) = import(state.clone(), true, serde_json::json!({"container": "legacy"})).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "already_managed");
+        assert_eq!(body["job_id"], job_id.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_import_finished_container_keeps_its_outcome() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), INSPECT)),
+            ..crate::test_state().await
+        };
+
+        let (status, body) = import(state.clone(), true, serde_json::json!({"container": "crashed"})).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let job = state.job_repo.get(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.exit_code, Some(2));
+        assert_eq!(job.user_id, "ops");
+        assert_eq!(job.completed_at.unwrap().to_rfc3339(), "2026-01-21T10:05:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_imports_create_one_job() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), INSPECT)),
+            ..crate::test_state().await
+        };
+
+        let body = serde_json::json!({"container": "legacy"});
+        let (first, second) = tokio::join!(
+            import(state.clone(), true, body.clone()),
+            import(state.clone(), true, body)
+        );
+        let mut statuses = [first.0, second.0];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+        assert_eq!(state.job_repo.list(None, 10).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_imported_status_follows_success_exit_codes() {
+        let info = |state: ContainerState, exit_code: Option<i32>| ContainerInfo {
+            id: "ctr_imported".to_string(),
+            name: "imported".to_string(),
+            state,
+            exit_code,
+            labels: Default::default(),
+        };
+        let job = Job {
+            success_exit_codes: vec![0, 3],
+            ..Job::sample("job_imported", "ops", JobStatus::Running)
+        };
+
+        let status = |state, exit_code| imported_status(&info(state, exit_code), &job);
+        assert_eq!(status(ContainerState::Running, None), Some(JobStatus::Running));
+        assert_eq!(status(ContainerState::Exited, Some(3)), Some(JobStatus::Completed));
+        assert_eq!(status(ContainerState::Exited, Some(2)), Some(JobStatus::Failed));
+        // podman lost the exit code; the outcome is unknown
+        assert_eq!(status(ContainerState::Stopped, None), Some(JobStatus::Failed));
+        assert_eq!(status(ContainerState::Created, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejections() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), INSPECT)),
+            ..crate::test_state().await
+        };
+
+        for (container, is_admin, status, error) in [
+            ("legacy", false, StatusCode::FORBIDDEN, "admin_required"),
+            ("", true, StatusCode::BAD_REQUEST, "invalid_container"),
+            ("missing", true, StatusCode::NOT_FOUND, "container_not_found"),
+            ("fresh", true, StatusCode::CONFLICT, "container_not_started"),
+            ("ours", true, StatusCode::CONFLICT, "already_managed"),
+        ] {
+            let (got, body) = import(state.clone(), is_admin, serde_json::json!({"container": container})).await;
+            assert_eq!((got, body["error"].as_str()), (status, Some(error)), "{}", container);
+        }
+
+        let (status, body) =
+            import(state.clone(), true, serde_json::json!({"container": "legacy", "type": "workr"})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_enum_value");
+        assert_eq!(body["field"], "type");
+        assert!(state.job_repo.list(None, 10).await.unwrap().is_empty());
+    }
+}
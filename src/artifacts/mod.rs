@@ -1,3 +1,8 @@
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
 use crate::AppState;
 
 pub fn routes() -> axum::Router<AppState> {
@@ -18,3 +23,331 @@ async fn list_artifacts() -> impl axum::response::IntoResponse {
 async fn download_artifact() -> impl axum::response::IntoResponse {
     axum::http::StatusCode::NOT_FOUND
 }
+
+/// Validate an artifact name. Names are filenames, never paths.
+pub fn validate_artifact_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("empty name");
+    }
+    if name.len() > 255 {
+        return Err("name too long (max 255)");
+    }
+    if name.contains('/') || name.contains('\\') {
+        return Err("contains path separator");
+    }
+    if name.contains("..") {
+        return Err("contains parent traversal");
+    }
+    if name.contains('\0') {
+        return Err("contains NUL byte");
+    }
+    if name.trim() != name {
+        return Err("leading/trailing whitespace");
+    }
+    if name.starts_with('.') {
+        tracing::warn!("Hidden artifact: {}", name);
+    }
+    Ok(())
+}
+
+/// Hard ceiling on artifact names, matching `validate_artifact_name`
+pub const MAX_ARTIFACT_NAME_LEN: usize = 255;
+
+/// Turn a file's path relative to `/artifacts` into an artifact name.
+///
+/// Path separators become `_` and anything outside a conservative character
+/// set is replaced, so the result is a single filename that is safe in a
+/// `Content-Disposition` header. Names longer than `max_len` are cut short
+/// and given a suffix derived from the full path, keeping them unique and
+/// stable across runs; the extension is preserved where it fits.
+pub fn sanitize_artifact_name(relative_path: &str, max_len: usize) -> String {
+    let max_len = max_len.clamp(16, MAX_ARTIFACT_NAME_LEN);
+    let mut name: String = relative_path
+        .trim_matches('/')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | '+' | '@' | '=' | ',' => c,
+            _ => '_',
+        })
+        .collect();
+    // `..` anywhere is rejected as traversal, even inside a filename
+    while name.contains("..") {
+        name = name.replace("..", ".");
+    }
+    if name.is_empty() || name == "." {
+        name = "artifact".to_string();
+    }
+    if name.len() <= max_len {
+        return name;
+    }
+    with_path_suffix(&name, relative_path, max_len)
+}
+
+/// `name` with a `~xxxxxxxx` suffix derived from `relative_path` inserted
+/// before its extension, shortened to fit `max_len`. The stem gives way
+/// first, then the extension; the suffix is always kept whole.
+pub fn with_path_suffix(name: &str, relative_path: &str, max_len: usize) -> String {
+    let digest = format!("{:x}", Sha256::digest(relative_path.as_bytes()));
+    let suffix = format!("~{}", &digest[..8]);
+    let (stem, extension) = match name.rfind('.') {
+        Some(i) if i > 0 && name.len() - i <= 16 => name.split_at(i),
+        _ => (name, ""),
+    };
+    let budget = max_len.saturating_sub(suffix.len());
+    let extension = truncate_to(extension, budget);
+    let stem = truncate_to(stem, budget - extension.len());
+    format!("{}{}{}", stem, suffix, extension)
+}
+
+/// The longest prefix of `s` of at most `max_len` bytes
+fn truncate_to(s: &str, max_len: usize) -> &str {
+    let mut end = s.len().min(max_len);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Most entries a job's `artifact_paths` may have
+pub const MAX_ARTIFACT_PATHS: usize = 32;
+
+/// Normalize an `artifact_paths` entry to a path relative to `/artifacts`.
+/// Both `output/logs` and `/artifacts/output/logs` are accepted.
+pub fn normalize_artifact_path(path: &str) -> Result<String, &'static str> {
+    let relative = match path.strip_prefix("/artifacts") {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => path,
+    };
+    let relative = relative.trim_matches('/');
+    if relative.is_empty() {
+        return Err("empty path");
+    }
+    if relative.contains('\0') {
+        return Err("contains NUL byte");
+    }
+    if relative
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err("contains empty, '.' or '..' components");
+    }
+    Ok(relative.to_string())
+}
+
+/// Whether a file at `relative_path` under `/artifacts` lies in one of the
+/// `allowed` subtrees; an empty allowlist allows everything
+pub fn is_allowed_artifact(relative_path: &str, allowed: &[String]) -> bool {
+    allowed.is_empty()
+        || allowed.iter().any(|prefix| {
+            relative_path == prefix
+                || relative_path
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
+/// A file found in a job's artifacts directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectedArtifact {
+    /// Sanitized, unique name served by the API
+    pub name: String,
+    /// Path relative to `/artifacts` as the job wrote it
+    pub original_path: String,
+    /// Location on the host
+    pub host_path: PathBuf,
+    pub size_bytes: u64,
+}
+
+/// Find every regular file under `dir` within the `allowed` subpaths (all
+/// of them if empty) and name it for the API. Symlinks are skipped. Names
+/// that collide after sanitizing are told apart by a suffix derived from the
+/// original path.
+pub fn collect_artifacts(
+    dir: &Path,
+    max_name_len: usize,
+    allowed: &[String],
+) -> std::io::Result<Vec<CollectedArtifact>> {
+    let mut files = Vec::new();
+    walk_files(dir, dir, &mut files)?;
+    files.retain(|(relative, _, _)| is_allowed_artifact(relative, allowed));
+    files.sort();
+
+    let max_name_len = max_name_len.clamp(16, MAX_ARTIFACT_NAME_LEN);
+    let mut taken = BTreeSet::new();
+    let mut artifacts = Vec::new();
+    for (original_path, host_path, size_bytes) in files {
+        let mut name = sanitize_artifact_name(&original_path, max_name_len);
+        if taken.contains(&name) {
+            name = with_path_suffix(&name, &original_path, max_name_len);
+        }
+        taken.insert(name.clone());
+        artifacts.push(CollectedArtifact {
+            name,
+            original_path,
+            host_path,
+            size_bytes,
+        });
+    }
+    Ok(artifacts)
+}
+
+fn walk_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf, u64)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            walk_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .into_owned();
+            files.push((relative, path, entry.metadata()?.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Compute the hex-encoded SHA-256 of a file without loading it into memory
+pub fn sha256_file(path: &std::path::Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_artifact_name() {
+        assert!(validate_artifact_name("myapp").is_ok());
+        assert!(validate_artifact_name("build.log").is_ok());
+        assert!(validate_artifact_name("").is_err());
+        assert!(validate_artifact_name("../../../etc/passwd").is_err());
+        assert!(validate_artifact_name("foo/bar.txt").is_err());
+        assert!(validate_artifact_name("  ").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_artifact_name() {
+        assert_eq!(sanitize_artifact_name("myapp", 255), "myapp");
+        assert_eq!(sanitize_artifact_name("dist/app.tar.gz", 255), "dist_app.tar.gz");
+        assert_eq!(
+            sanitize_artifact_name("report \"final\";\r\n.pdf", 255),
+            "report__final____.pdf"
+        );
+        assert_eq!(sanitize_artifact_name("résumé.txt", 255), "r_sum_.txt");
+        assert_eq!(sanitize_artifact_name("a/../../b", 255), "a_._._b");
+        assert_eq!(sanitize_artifact_name("...", 255), "artifact");
+
+        for name in ["dist/app.tar.gz", "report \"final\";.pdf", "a/../../b", "..."] {
+            assert!(validate_artifact_name(&sanitize_artifact_name(name, 255)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_with_path_suffix_fits_short_limits() {
+        // A 16-byte extension leaves no room for the stem at the minimum length
+        let name = with_path_suffix("report.abcdefghijklmno", "a/report.abcdefghijklmno", 16);
+        assert_eq!(name.len(), 16);
+        assert!(name.starts_with('~'));
+        assert!(name.ends_with(".abcdef"));
+
+        let name = with_path_suffix("job.log", "x", 14);
+        assert!(name.starts_with("j~") && name.ends_with(".log"));
+        assert_eq!(name.len(), 14);
+        // Even a limit below the suffix's own length doesn't underflow
+        assert_eq!(with_path_suffix("job.log", "x", 4).len(), 9);
+        // Multi-byte characters are never split
+        let name = with_path_suffix("résumé", "x", 11);
+        assert!(name.starts_with("r~"));
+        assert_eq!(name.len(), 10);
+    }
+
+    #[test]
+    fn test_long_artifact_names_are_shortened_deterministically() {
+        let long = format!("{}/output.json", "nested".repeat(60));
+        let name = sanitize_artifact_name(&long, 64);
+        assert_eq!(name.len(), 64);
+        assert!(name.ends_with(".json"));
+        assert!(name.contains('~'));
+        assert_eq!(name, sanitize_artifact_name(&long, 64));
+
+        // Paths sharing a long prefix still get distinct names
+        let other = format!("{}/output2.json", "nested".repeat(60));
+        assert_ne!(name, sanitize_artifact_name(&other, 64));
+    }
+
+    #[test]
+    fn test_collect_artifacts_keeps_names_unique() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        std::fs::write(dir.path().join("a/b"), "1").unwrap();
+        std::fs::write(dir.path().join("a_b"), "22").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", dir.path().join("link")).unwrap();
+
+        let artifacts = collect_artifacts(dir.path(), 255, &[]).unwrap();
+        let paths: Vec<&str> = artifacts.iter().map(|a| a.original_path.as_str()).collect();
+        assert_eq!(paths, vec!["a/b", "a_b"]);
+        assert_eq!(artifacts[0].name, "a_b");
+        assert!(artifacts[1].name.starts_with("a_b~"));
+        assert_eq!(artifacts[1].size_bytes, 2);
+    }
+
+    #[test]
+    fn test_normalize_artifact_path() {
+        assert_eq!(normalize_artifact_path("output").as_deref(), Ok("output"));
+        assert_eq!(normalize_artifact_path("/artifacts/output/").as_deref(), Ok("output"));
+        assert_eq!(normalize_artifact_path("reports/html").as_deref(), Ok("reports/html"));
+        // Only a whole `/artifacts` component is stripped
+        assert_eq!(normalize_artifact_path("/artifactsx").as_deref(), Ok("artifactsx"));
+        for bad in ["", "/", "/artifacts", "a/../b", "./a", "a//b"] {
+            assert!(normalize_artifact_path(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_collect_only_allowed_subpaths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for path in ["output/app", "output/nested/report.html", "outputs/other", "scratch/big.bin", "top.txt"] {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "x").unwrap();
+        }
+
+        let allowed = vec!["output".to_string(), "top.txt".to_string()];
+        let artifacts = collect_artifacts(dir.path(), 255, &allowed).unwrap();
+        let paths: Vec<&str> = artifacts.iter().map(|a| a.original_path.as_str()).collect();
+        // `outputs/` merely shares a prefix with `output`
+        assert_eq!(paths, vec!["output/app", "output/nested/report.html", "top.txt"]);
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("hello.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::models::Artifact;
+use super::jobs::parse_datetime;
+use super::retry_on_lock;
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tracing::info;
+
+pub struct ArtifactRepository {
+    pool: SqlitePool,
+}
+
+impl ArtifactRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Get an artifact by job and name
+    pub async fn get(&self, job_id: &str, name: &str) -> Result<Option<Artifact>, sqlx::Error> {
+        let row = sqlx::query_as::<_, ArtifactRow>(
+            "SELECT id, job_id, name, path, original_path, size_bytes, created_at
+             FROM artifacts WHERE job_id = ? AND name = ?",
+        )
+        .bind(job_id)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| r.into_artifact()))
+    }
+
+    /// List a job's artifacts by name
+    pub async fn list(&self, job_id: &str) -> Result<Vec<Artifact>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, ArtifactRow>(
+            "SELECT id, job_id, name, path, original_path, size_bytes, created_at
+             FROM artifacts WHERE job_id = ? ORDER BY name",
+        )
+        .bind(job_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_artifact()).collect())
+    }
+
+    /// Record all of a job's artifacts in one transaction and return its full
+    /// list, so a listing never sees part of them. Names the job already
+    /// has keep their existing row, so concurrent recorders of the same
+    /// files agree.
+    pub async fn create_all(
+        &self,
+        job_id: &str,
+        artifacts: &[NewArtifact<'_>],
+    ) -> Result<Vec<Artifact>, sqlx::Error> {
+        retry_on_lock(|| async {
+            let mut tx = self.pool.begin().await?;
+            let created_at = Utc::now().to_rfc3339();
+            for artifact in artifacts {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO artifacts (job_id, name, path, original_path, size_bytes, created_at)
+                     VALUES (?, ?, ?, ?, ?, ?)",
+                )
+                .bind(job_id)
+                .bind(artifact.name)
+                .bind(artifact.path)
+                .bind(artifact.original_path)
+                .bind(artifact.size_bytes)
+                .bind(&created_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        })
+        .await?;
+
+        info!("Recorded {} artifacts for job {}", artifacts.len(), job_id);
+        self.list(job_id).await
+    }
+
+    /// Record an artifact copied out of a job's container. Recording a name
+    /// the job already has keeps the existing row.
+    pub async fn create(
+        &self,
+        job_id: &str,
+        name: &str,
+        path: &str,
+        original_path: Option<&str>,
+        size_bytes: i64,
+    ) -> Result<Artifact, sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query(
+                "INSERT OR IGNORE INTO artifacts (job_id, name, path, original_path, size_bytes, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(job_id)
+            .bind(name)
+            .bind(path)
+            .bind(original_path)
+            .bind(size_bytes)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+        })
+        .await?;
+
+        info!("Recorded artifact {} for job {} ({} bytes)", name, job_id, size_bytes);
+        self.get(job_id, name).await?.ok_or(sqlx::Error::RowNotFound)
+    }
+}
+
+/// An artifact to record with `create_all`
+pub struct NewArtifact<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub original_path: Option<&'a str>,
+    pub size_bytes: i64,
+}
+
+/// Raw database row for artifacts
+#[derive(sqlx::FromRow)]
+struct ArtifactRow {
+    id: i64,
+    job_id: String,
+    name: String,
+    path: String,
+    original_path: Option<String>,
+    size_bytes: i64,
+    created_at: String,
+}
+
+impl ArtifactRow {
+    fn into_artifact(self) -> Artifact {
+        Artifact {
+            id: self.id,
+            job_id: self.job_id,
+            name: self.name,
+            path: self.path,
+            original_path: self.original_path,
+            size_bytes: self.size_bytes,
+            created_at: parse_datetime(&self.created_at),
+        }
+    }
+}
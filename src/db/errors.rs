@@ -0,0 +1,112 @@
+use axum::{http::StatusCode, Json};
+
+/// SQLite primary result codes for "database is locked" by another
+/// connection or within this one
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+/// SQLite primary result code for "database or disk is full"
+const SQLITE_FULL: i32 = 13;
+/// errno for "no space left on device"
+const ENOSPC: i32 = 28;
+
+/// Coarse category of a database failure, used to pick the API response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DbErrorKind {
+    /// The filesystem holding the database is out of space
+    StorageFull,
+    /// Another connection holds the lock; retrying shortly may succeed
+    Locked,
+    Other,
+}
+
+/// Classify a sqlx error by its SQLite result code or OS error
+pub fn classify_error(e: &sqlx::Error) -> DbErrorKind {
+    let full = match e {
+        // Extended result codes keep the primary code in the low byte
+        sqlx::Error::Database(db) => db
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| code & 0xff == SQLITE_FULL),
+        sqlx::Error::Io(io) => {
+            io.kind() == std::io::ErrorKind::StorageFull || io.raw_os_error() == Some(ENOSPC)
+        }
+        _ => false,
+    };
+    if full {
+        DbErrorKind::StorageFull
+    } else if is_locked(e) {
+        DbErrorKind::Locked
+    } else {
+        DbErrorKind::Other
+    }
+}
+
+/// Whether SQLite reported the database busy or locked
+fn is_locked(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Database(db) => db
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED)),
+        _ => false,
+    }
+}
+
+/// HTTP status and API error code for a failed database operation: 507
+/// `storage_full` when the disk is full, otherwise 500 `database_error`
+pub fn database_error_status(e: &sqlx::Error) -> (StatusCode, &'static str) {
+    match classify_error(e) {
+        DbErrorKind::StorageFull => (StatusCode::INSUFFICIENT_STORAGE, "storage_full"),
+        DbErrorKind::Locked | DbErrorKind::Other => (StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
+    }
+}
+
+/// API error response for a failed database operation, see
+/// `database_error_status`
+pub fn database_error_response(
+    e: &sqlx::Error,
+    message: String,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let (status, code) = database_error_status(e);
+    (
+        status,
+        Json(serde_json::json!({
+            "error": code,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_classify_full_database() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        // Cap the database at a handful of pages so the insert can't fit
+        sqlx::query("PRAGMA max_page_count = 4").execute(&pool).await.unwrap();
+        sqlx::query("CREATE TABLE blobs (data BLOB)").execute(&pool).await.unwrap();
+        let err = sqlx::query("INSERT INTO blobs VALUES (zeroblob(1000000))")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+
+        assert_eq!(classify_error(&err), DbErrorKind::StorageFull);
+        let (status, Json(body)) = database_error_response(&err, err.to_string());
+        assert_eq!(status, StatusCode::INSUFFICIENT_STORAGE);
+        assert_eq!(body["error"], "storage_full");
+    }
+
+    #[test]
+    fn test_classify_other_errors() {
+        let enospc = sqlx::Error::Io(std::io::Error::from_raw_os_error(ENOSPC));
+        assert_eq!(classify_error(&enospc), DbErrorKind::StorageFull);
+
+        assert_eq!(classify_error(&sqlx::Error::RowNotFound), DbErrorKind::Other);
+        let (status, Json(body)) =
+            database_error_response(&sqlx::Error::RowNotFound, "missing".to_string());
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"], "database_error");
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::Job;
+
+/// Bounded, short-lived cache of jobs by id, for hot read paths such as
+/// clients polling `GET /jobs/:id`.
+///
+/// Entries expire after `ttl`, or after `terminal_ttl` once the job has
+/// finished and no longer changes on its own. When full, the least recently
+/// used entry is evicted. `JobRepository` invalidates a job on every write
+/// to it. A capacity of 0 disables the cache.
+pub struct JobCache {
+    capacity: usize,
+    ttl: Duration,
+    terminal_ttl: Duration,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    /// Bumped on every invalidation, so a read that raced a write doesn't
+    /// cache the row it read before the write
+    generation: u64,
+    /// Logical clock ordering entries by last use
+    clock: u64,
+}
+
+struct CacheEntry {
+    job: Job,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+impl JobCache {
+    pub fn new(capacity: usize, ttl: Duration, terminal_ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            terminal_ttl,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// A cached job that hasn't expired
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.get_at(id, Instant::now())
+    }
+
+    fn get_at(&self, id: &str, now: Instant) -> Option<Job> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        match inner.entries.get_mut(id) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used = clock;
+                Some(entry.job.clone())
+            }
+            Some(_) => {
+                inner.entries.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Current invalidation generation; take it before reading the job from
+    /// the database and pass it to `insert`
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Cache a job read from the database, unless something was invalidated
+    /// since `generation` was taken
+    pub fn insert(&self, job: Job, generation: u64) {
+        self.insert_at(job, generation, Instant::now());
+    }
+
+    fn insert_at(&self, job: Job, generation: u64, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+
+        if inner.entries.len() >= self.capacity && !inner.entries.contains_key(&job.id) {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+
+        let ttl = if job.status.is_terminal() {
+            self.terminal_ttl
+        } else {
+            self.ttl
+        };
+        inner.clock += 1;
+        let entry = CacheEntry {
+            expires_at: now + ttl,
+            last_used: inner.clock,
+            job,
+        };
+        inner.entries.insert(entry.job.id.clone(), entry);
+    }
+
+    /// Drop a job after it was written to
+    pub fn invalidate(&self, id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        inner.entries.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::JobStatus;
+
+    fn job(id: &str, status: JobStatus) -> Job {
+        Job::sample(id, "default", status)
+    }
+
+    #[test]
+    fn test_terminal_jobs_are_cached_longer() {
+        let cache = JobCache::new(8, Duration::from_secs(2), Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert_at(job("job_run", JobStatus::Running), 0, now);
+        cache.insert_at(job("job_done", JobStatus::Completed), 0, now);
+
+        let later = now + Duration::from_secs(10);
+        assert!(cache.get_at("job_run", later).is_none());
+        assert!(cache.get_at("job_done", later).is_some());
+        assert!(cache.get_at("job_done", now + Duration::from_secs(61)).is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = JobCache::new(2, Duration::from_secs(60), Duration::from_secs(60));
+        cache.insert(job("job_a", JobStatus::Running), 0);
+        cache.insert(job("job_b", JobStatus::Running), 0);
+        // Touch a so b is the oldest
+        assert!(cache.get("job_a").is_some());
+        cache.insert(job("job_c", JobStatus::Running), 0);
+
+        assert!(cache.get("job_a").is_some());
+        assert!(cache.get("job_b").is_none());
+        assert!(cache.get("job_c").is_some());
+    }
+
+    #[test]
+    fn test_reads_racing_a_write_are_not_cached() {
+        let cache = JobCache::new(8, Duration::from_secs(60), Duration::from_secs(60));
+        let generation = cache.generation();
+        cache.invalidate("job_a");
+        cache.insert(job("job_a", JobStatus::Running), generation);
+        assert!(cache.get("job_a").is_none());
+
+        assert!(JobCache::disabled().get("job_a").is_none());
+    }
+}
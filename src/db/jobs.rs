@@ -1,16 +1,37 @@
-use crate::models::{Job, JobStatus, JobType};
+use crate::models::{EventSource, FailureCategory, Job, JobEvent, JobStatus, JobType, PendingReason, SubmissionSource};
+use super::{retry_on_lock, JobCache, INVALID_TIMESTAMP};
 use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
-use tracing::info;
+use std::collections::BTreeMap;
+use tracing::{error, info};
 use uuid::Uuid;
 
+/// Columns selected into a `JobRow`, shared by every job query
+const JOB_COLUMNS: &str = "jobs.id, user_id, job_type, status, command, commands, task, context,
+     git_branch, files_id, input_from_job, image, cpus, memory_gb, timeout_minutes, restartable,
+     container_id, exit_code, error, created_at, started_at, completed_at, pending_reason, labels,
+     annotations, secret_refs, client_ip, user_agent, request_id, start_after,
+     max_total_runtime_minutes, runtime_seconds, node_selector, log_created_at, logs_deleted_at,
+     artifact_paths, ephemeral, cost_units, success_exit_codes, failure_category, shm_size_mb,
+     promote_log_to_artifact";
+
 pub struct JobRepository {
     pool: SqlitePool,
+    cache: JobCache,
 }
 
 impl JobRepository {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            cache: JobCache::disabled(),
+        }
+    }
+
+    /// Serve `get_cached` from `cache`
+    pub fn with_cache(mut self, cache: JobCache) -> Self {
+        self.cache = cache;
+        self
     }
 
     /// Generate a new job ID
@@ -20,12 +41,10 @@ impl JobRepository {
 
     /// Get a job by ID
     pub async fn get(&self, id: &str) -> Result<Option<Job>, sqlx::Error> {
-        let row = sqlx::query_as::<_, JobRow>(
-            "SELECT id, user_id, job_type, status, command, task, context, git_branch,
-                    files_id, image, cpus, memory_gb, timeout_minutes, container_id,
-                    exit_code, error, created_at, started_at, completed_at
-             FROM jobs WHERE id = ?",
-        )
+        let row = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE id = ?",
+            JOB_COLUMNS
+        ))
         .bind(id)
         .fetch_optional(&self.pool)
         .await?;
@@ -33,16 +52,28 @@ impl JobRepository {
         Ok(row.map(|r| r.into_job()))
     }
 
+    /// Get a job by ID, from the cache if it holds a fresh copy. For hot
+    /// read-only paths; anything that acts on the job should use `get`.
+    pub async fn get_cached(&self, id: &str) -> Result<Option<Job>, sqlx::Error> {
+        if let Some(job) = self.cache.get(id) {
+            return Ok(Some(job));
+        }
+        let generation = self.cache.generation();
+        let job = self.get(id).await?;
+        if let Some(job) = &job {
+            self.cache.insert(job.clone(), generation);
+        }
+        Ok(job)
+    }
+
     /// Get a job by client job ID (idempotency key)
     pub async fn get_by_client_id(&self, client_job_id: &str) -> Result<Option<Job>, sqlx::Error> {
-        let row = sqlx::query_as::<_, JobRow>(
-            "SELECT j.id, j.user_id, j.job_type, j.status, j.command, j.task, j.context,
-                    j.git_branch, j.files_id, j.image, j.cpus, j.memory_gb, j.timeout_minutes,
-                    j.container_id, j.exit_code, j.error, j.created_at, j.started_at, j.completed_at
-             FROM jobs j
-             JOIN idempotency_keys ik ON j.id = ik.job_id
+        let row = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs
+             JOIN idempotency_keys ik ON jobs.id = ik.job_id
              WHERE ik.client_job_id = ? AND ik.active = 1",
-        )
+            JOB_COLUMNS
+        ))
         .bind(client_job_id)
         .fetch_optional(&self.pool)
         .await?;
@@ -50,49 +81,115 @@ impl JobRepository {
         Ok(row.map(|r| r.into_job()))
     }
 
+    /// The job managing a container, by its full container ID
+    pub async fn get_by_container_id(&self, container_id: &str) -> Result<Option<Job>, sqlx::Error> {
+        let row = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE container_id = ?",
+            JOB_COLUMNS
+        ))
+        .bind(container_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| r.into_job()))
+    }
+
+    /// Create a job for a container that's already running or has run, with
+    /// the container, exit code and times `job` carries. Fails with
+    /// `AlreadyManaged` if another job already has the container; the check
+    /// and the insert are one transaction, so concurrent imports can't both
+    /// succeed.
+    pub async fn create_imported(&self, job: &Job) -> Result<Job, ImportError> {
+        let existing = retry_on_lock(|| async {
+            let mut tx = self.pool.begin().await?;
+            let existing: Option<(String,)> = sqlx::query_as("SELECT id FROM jobs WHERE container_id = ?")
+                .bind(&job.container_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if existing.is_some() {
+                return Ok(existing);
+            }
+            let insert = sqlx::query(
+                "INSERT INTO jobs (id, user_id, job_type, status, command, commands, task, context,
+                                   git_branch, files_id, input_from_job, image, cpus, memory_gb,
+                                   timeout_minutes, restartable, created_at, labels, annotations,
+                                   secret_refs, client_ip, user_agent, request_id, start_after,
+                                   max_total_runtime_minutes, node_selector, artifact_paths,
+                                   ephemeral, success_exit_codes, shm_size_mb, promote_log_to_artifact,
+                                   container_id, exit_code, started_at, completed_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                         ?, ?, ?, ?)",
+            );
+            bind_new_job(insert, job)
+                .bind(&job.container_id)
+                .bind(job.exit_code)
+                .bind(job.started_at.map(|t| t.to_rfc3339()))
+                .bind(job.completed_at.map(|t| t.to_rfc3339()))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            Ok(None)
+        })
+        .await?;
+        if let Some((job_id,)) = existing {
+            return Err(ImportError::AlreadyManaged(job_id));
+        }
+
+        self.record_event(&job.id, job.created_at, &job.status.to_string()).await;
+        info!("Imported container {:?} as job {}", job.container_id, job.id);
+        Ok(self.get(&job.id).await?.ok_or(sqlx::Error::RowNotFound)?)
+    }
+
     /// Create a new job
     pub async fn create(&self, job: &Job, client_job_id: Option<&str>) -> Result<Job, sqlx::Error> {
-        sqlx::query(
-            "INSERT INTO jobs (id, user_id, job_type, status, command, task, context, git_branch,
-                               files_id, image, cpus, memory_gb, timeout_minutes, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-        )
-        .bind(&job.id)
-        .bind(&job.user_id)
-        .bind(job.job_type.to_string())
-        .bind(job.status.to_string())
-        .bind(&job.command)
-        .bind(&job.task)
-        .bind(&job.context)
-        .bind(&job.git_branch)
-        .bind(&job.files_id)
-        .bind(&job.image)
-        .bind(job.cpus)
-        .bind(job.memory_gb)
-        .bind(job.timeout_minutes)
-        .bind(job.created_at.to_rfc3339())
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            let insert = sqlx::query(
+                "INSERT INTO jobs (id, user_id, job_type, status, command, commands, task, context,
+                                   git_branch, files_id, input_from_job, image, cpus, memory_gb,
+                                   timeout_minutes, restartable, created_at, labels, annotations,
+                                   secret_refs, client_ip, user_agent, request_id, start_after,
+                                   max_total_runtime_minutes, node_selector, artifact_paths,
+                                   ephemeral, success_exit_codes, shm_size_mb, promote_log_to_artifact)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            );
+            bind_new_job(insert, job).execute(&self.pool)
+        })
         .await?;
 
         // Create idempotency key if provided
         if let Some(cid) = client_job_id {
-            sqlx::query(
-                "INSERT INTO idempotency_keys (client_job_id, job_id, active) VALUES (?, ?, 1)",
-            )
-            .bind(cid)
-            .bind(&job.id)
-            .execute(&self.pool)
+            retry_on_lock(|| {
+                sqlx::query(
+                    "INSERT INTO idempotency_keys (client_job_id, job_id, active) VALUES (?, ?, 1)",
+                )
+                .bind(cid)
+                .bind(&job.id)
+                .execute(&self.pool)
+            })
             .await?;
         }
 
+        self.record_event(&job.id, job.created_at, &job.status.to_string()).await;
         info!("Created job {} (type: {:?})", job.id, job.job_type);
         self.get(&job.id).await?.ok_or(sqlx::Error::RowNotFound)
     }
 
+    /// Forget the job's `client_job_id`, so it can be used for a new job
+    pub async fn release_client_id(&self, job_id: &str) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query("DELETE FROM idempotency_keys WHERE job_id = ?")
+                .bind(job_id)
+                .execute(&self.pool)
+        })
+        .await?;
+        Ok(())
+    }
+
     /// Update job status
     pub async fn update_status(&self, id: &str, status: JobStatus) -> Result<(), sqlx::Error> {
         let now = Utc::now();
 
+        // A job leaving `Pending` no longer has a reason to be pending
         let (started_at, completed_at) = match status {
             JobStatus::Running => (Some(now.to_rfc3339()), None),
             JobStatus::Completed | JobStatus::Failed | JobStatus::TimedOut | JobStatus::Cancelled => {
@@ -102,81 +199,415 @@ impl JobRepository {
         };
 
         if let Some(started) = started_at {
-            sqlx::query(
-                "UPDATE jobs SET status = ?, started_at = ? WHERE id = ?",
-            )
-            .bind(status.to_string())
-            .bind(started)
-            .bind(id)
-            .execute(&self.pool)
+            retry_on_lock(|| {
+                sqlx::query(
+                    "UPDATE jobs SET status = ?, started_at = ?, pending_reason = NULL WHERE id = ?",
+                )
+                .bind(status.to_string())
+                .bind(&started)
+                .bind(id)
+                .execute(&self.pool)
+            })
             .await?;
         } else if let Some(completed) = completed_at {
-            sqlx::query(
-                "UPDATE jobs SET status = ?, completed_at = ? WHERE id = ?",
-            )
-            .bind(status.to_string())
-            .bind(completed)
-            .bind(id)
-            .execute(&self.pool)
+            retry_on_lock(|| {
+                sqlx::query(
+                    "UPDATE jobs SET status = ?, completed_at = ?, pending_reason = NULL WHERE id = ?",
+                )
+                .bind(status.to_string())
+                .bind(&completed)
+                .bind(id)
+                .execute(&self.pool)
+            })
             .await?;
         } else {
-            sqlx::query(
-                "UPDATE jobs SET status = ? WHERE id = ?",
-            )
-            .bind(status.to_string())
-            .bind(id)
-            .execute(&self.pool)
+            let sql = format!("UPDATE jobs SET status = ?{} WHERE id = ?", pending_reason_update(&status));
+            retry_on_lock(|| {
+                sqlx::query(&sql)
+                    .bind(status.to_string())
+                    .bind(id)
+                    .execute(&self.pool)
+            })
             .await?;
         }
 
+        self.cache.invalidate(id);
+        self.record_event(id, now, &status.to_string()).await;
         info!("Updated job {} status to {:?}", id, status);
         Ok(())
     }
 
+    /// Update job status only if it is currently in one of the `from` states.
+    ///
+    /// Returns `false` when the job had already moved elsewhere (e.g. it was
+    /// cancelled while its container was starting).
+    pub async fn transition_status(
+        &self,
+        id: &str,
+        from: &[JobStatus],
+        status: JobStatus,
+    ) -> Result<bool, sqlx::Error> {
+        let timestamp_column = match status {
+            JobStatus::Running => Some("started_at"),
+            JobStatus::Completed | JobStatus::Failed | JobStatus::TimedOut | JobStatus::Cancelled => {
+                Some("completed_at")
+            }
+            _ => None,
+        };
+
+        let placeholders = vec!["?"; from.len()].join(", ");
+        let reason = pending_reason_update(&status);
+        let sql = match timestamp_column {
+            Some(column) => format!(
+                "UPDATE jobs SET status = ?, {} = ?{} WHERE id = ? AND status IN ({})",
+                column, reason, placeholders
+            ),
+            None => format!(
+                "UPDATE jobs SET status = ?{} WHERE id = ? AND status IN ({})",
+                reason, placeholders
+            ),
+        };
+
+        let result = retry_on_lock(|| {
+            let mut query = sqlx::query(&sql).bind(status.to_string());
+            if timestamp_column.is_some() {
+                query = query.bind(Utc::now().to_rfc3339());
+            }
+            query = query.bind(id);
+            for s in from {
+                query = query.bind(s.to_string());
+            }
+            query.execute(&self.pool)
+        })
+        .await?;
+        let moved = result.rows_affected() > 0;
+        self.cache.invalidate(id);
+        if moved {
+            self.record_event(id, Utc::now(), &status.to_string()).await;
+            info!("Updated job {} status to {:?}", id, status);
+        }
+        Ok(moved)
+    }
+
+    /// Append to the job's event log. Best-effort: the status change it
+    /// describes has already been made.
+    async fn record_event(&self, id: &str, at: DateTime<Utc>, event: &str) {
+        let result = retry_on_lock(|| {
+            sqlx::query("INSERT INTO job_events (job_id, at, event) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(at.to_rfc3339())
+                .bind(event)
+                .execute(&self.pool)
+        })
+        .await;
+        if let Err(e) = result {
+            error!("Failed to record event {} for job {}: {}", event, id, e);
+        }
+    }
+
+    /// The job's status transitions, oldest first
+    pub async fn events(&self, id: &str) -> Result<Vec<JobEvent>, sqlx::Error> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT at, event FROM job_events WHERE job_id = ? ORDER BY id")
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(at, event)| JobEvent {
+                at: parse_datetime(&at),
+                source: EventSource::Job,
+                event,
+                detail: None,
+            })
+            .collect())
+    }
+
     /// Set container ID for a job
     pub async fn set_container_id(&self, id: &str, container_id: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "UPDATE jobs SET container_id = ? WHERE id = ?",
-        )
-        .bind(container_id)
-        .bind(id)
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE jobs SET container_id = ? WHERE id = ?",
+            )
+            .bind(container_id)
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
+        self.cache.invalidate(id);
         Ok(())
     }
 
     /// Set exit code for a job
     pub async fn set_exit_code(&self, id: &str, exit_code: i32) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "UPDATE jobs SET exit_code = ? WHERE id = ?",
-        )
-        .bind(exit_code)
-        .bind(id)
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE jobs SET exit_code = ? WHERE id = ?",
+            )
+            .bind(exit_code)
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
+        self.cache.invalidate(id);
         Ok(())
     }
 
+    /// Add a finished attempt's runtime to the job's total, returning the
+    /// new total
+    pub async fn add_runtime(&self, id: &str, seconds: i64) -> Result<i64, sqlx::Error> {
+        let total: (i64,) = retry_on_lock(|| {
+            sqlx::query_as(
+                "UPDATE jobs SET runtime_seconds = runtime_seconds + ? WHERE id = ?
+                 RETURNING runtime_seconds",
+            )
+            .bind(seconds.max(0))
+            .bind(id)
+            .fetch_one(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+
+        Ok(total.0)
+    }
+
     /// Set error message for a job
     pub async fn set_error(&self, id: &str, error: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "UPDATE jobs SET error = ? WHERE id = ?",
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE jobs SET error = ? WHERE id = ?",
+            )
+            .bind(error)
+            .bind(id)
+            .execute(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    /// Record why a job failed, alongside its `error`
+    pub async fn set_failure_category(&self, id: &str, category: FailureCategory) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query("UPDATE jobs SET failure_category = ? WHERE id = ?")
+                .bind(category.to_string())
+                .bind(id)
+                .execute(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    /// Record why the scheduler left a job pending, or clear it with `None`
+    pub async fn set_pending_reason(
+        &self,
+        id: &str,
+        reason: Option<PendingReason>,
+    ) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE jobs SET pending_reason = ? WHERE id = ?",
+            )
+            .bind(reason.map(|r| r.to_string()))
+            .bind(id)
+            .execute(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    /// Pending jobs whose `start_after` has passed, earliest first
+    pub async fn get_due_scheduled(&self, now: DateTime<Utc>) -> Result<Vec<Job>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs
+             WHERE status = 'pending' AND start_after IS NOT NULL AND start_after <= ?
+             ORDER BY start_after",
+            JOB_COLUMNS
+        ))
+        .bind(now.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
+    /// Finished jobs that completed before `before` and haven't been cleaned,
+    /// including those an interrupted cleanup left in `cleaning`
+    pub async fn get_cleanable(&self, before: DateTime<Utc>) -> Result<Vec<Job>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs
+             WHERE status IN ('completed', 'failed', 'timed_out', 'cancelled', 'cleaning')
+               AND completed_at IS NOT NULL AND completed_at < ?",
+            JOB_COLUMNS
+        ))
+        .bind(before.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
+    /// Finished (or cleaned) jobs whose captured log was created before
+    /// `before` and hasn't been deleted yet
+    pub async fn get_expired_logs(&self, before: DateTime<Utc>) -> Result<Vec<Job>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs
+             WHERE status IN ('completed', 'failed', 'timed_out', 'cancelled', 'cleaning', 'cleaned')
+               AND log_created_at IS NOT NULL AND log_created_at < ?
+               AND logs_deleted_at IS NULL",
+            JOB_COLUMNS
+        ))
+        .bind(before.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
+    /// Jobs that have finished but have no cost recorded yet
+    pub async fn get_uncosted(&self) -> Result<Vec<Job>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE completed_at IS NOT NULL AND cost_units IS NULL",
+            JOB_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
+    /// Record a finished job's cost
+    pub async fn set_cost_units(&self, id: &str, cost_units: f64) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query("UPDATE jobs SET cost_units = ? WHERE id = ?")
+                .bind(cost_units)
+                .bind(id)
+                .execute(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    /// Recorded costs of jobs that completed since `since`, summed per user
+    /// and optionally limited to one user
+    pub async fn costs_since(
+        &self,
+        since: DateTime<Utc>,
+        user_id: Option<&str>,
+    ) -> Result<Vec<UserCost>, sqlx::Error> {
+        let rows: Vec<(String, i64, f64)> = sqlx::query_as(
+            "SELECT user_id, COUNT(*), SUM(cost_units) FROM jobs
+             WHERE cost_units IS NOT NULL AND completed_at >= ?
+               AND (? IS NULL OR user_id = ?)
+             GROUP BY user_id ORDER BY SUM(cost_units) DESC, user_id",
         )
-        .bind(error)
-        .bind(id)
-        .execute(&self.pool)
+        .bind(since.to_rfc3339())
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(user_id, jobs, cost_units)| UserCost {
+                user_id,
+                jobs,
+                cost_units,
+            })
+            .collect())
+    }
+
+    /// Number of a user's jobs in each status
+    pub async fn status_counts_for_user(&self, user_id: &str) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT status, COUNT(*) FROM jobs WHERE user_id = ? GROUP BY status",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// CPU-hours a user's jobs have used: `cpus` times runtime over all
+    /// attempts, with running attempts counted up to `now`
+    pub async fn cpu_hours_for_user(&self, user_id: &str, now: DateTime<Utc>) -> Result<f64, sqlx::Error> {
+        let (cpu_seconds,): (f64,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(cpus * (runtime_seconds + CASE WHEN started_at IS NULL THEN 0
+                 ELSE MAX(0, (julianday(COALESCE(completed_at, ?)) - julianday(started_at)) * 86400) END)), 0.0)
+             FROM jobs WHERE user_id = ?",
+        )
+        .bind(now.to_rfc3339())
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(cpu_seconds / 3600.0)
+    }
+
+    /// Record when output capture created the job's log file
+    pub async fn set_log_created_at(&self, id: &str, at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query("UPDATE jobs SET log_created_at = ? WHERE id = ?")
+                .bind(at.to_rfc3339())
+                .bind(id)
+                .execute(&self.pool)
+        })
+        .await?;
+        self.cache.invalidate(id);
+        Ok(())
+    }
+
+    /// Record that the job's log file was deleted by retention
+    pub async fn mark_logs_deleted(&self, id: &str) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query("UPDATE jobs SET logs_deleted_at = ? WHERE id = ?")
+                .bind(Utc::now().to_rfc3339())
+                .bind(id)
+                .execute(&self.pool)
+        })
         .await?;
+        self.cache.invalidate(id);
         Ok(())
     }
 
+    /// Whether any job other than `except_job_id` that hasn't finished yet
+    /// uses `upload_id` as its files
+    pub async fn upload_in_use(&self, upload_id: &str, except_job_id: &str) -> Result<bool, sqlx::Error> {
+        let row: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM jobs
+             WHERE files_id = ? AND id != ? AND status IN ('pending', 'starting', 'running')",
+        )
+        .bind(upload_id)
+        .bind(except_job_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.0 > 0)
+    }
+
+    /// Jobs that haven't finished yet and use `upload_id` as their files
+    pub async fn get_active_by_upload(&self, upload_id: &str) -> Result<Vec<Job>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE files_id = ? AND status IN ('pending', 'starting', 'running')",
+            JOB_COLUMNS
+        ))
+        .bind(upload_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
     /// Get all jobs in starting or running state (for reconciliation)
     pub async fn get_active_jobs(&self) -> Result<Vec<Job>, sqlx::Error> {
-        let rows = sqlx::query_as::<_, JobRow>(
-            "SELECT id, user_id, job_type, status, command, task, context, git_branch,
-                    files_id, image, cpus, memory_gb, timeout_minutes, container_id,
-                    exit_code, error, created_at, started_at, completed_at
-             FROM jobs WHERE status IN ('starting', 'running')",
-        )
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE status IN ('starting', 'running')",
+            JOB_COLUMNS
+        ))
         .fetch_all(&self.pool)
         .await?;
 
@@ -198,28 +629,53 @@ impl JobRepository {
         })
     }
 
+    /// Count jobs of one type in starting or running state
+    pub async fn count_active_by_type(&self, job_type: JobType) -> Result<i64, sqlx::Error> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM jobs WHERE job_type = ? AND status IN ('starting', 'running')",
+        )
+        .bind(job_type.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count)
+    }
+
     /// List jobs with optional filters
+    /// Failed jobs whose `failure_category` is permanent, newest first
+    pub async fn list_dead_letter(&self, limit: i32) -> Result<Vec<Job>, sqlx::Error> {
+        let categories = FailureCategory::PERMANENT
+            .iter()
+            .map(|category| format!("'{}'", category))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rows = sqlx::query_as::<_, JobRow>(&format!(
+            "SELECT {} FROM jobs WHERE status = 'failed' AND failure_category IN ({})
+             ORDER BY created_at DESC LIMIT ?",
+            JOB_COLUMNS, categories
+        ))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into_job()).collect())
+    }
+
     pub async fn list(&self, status_filter: Option<&str>, limit: i32) -> Result<Vec<Job>, sqlx::Error> {
         let rows = if let Some(filter) = status_filter {
-            sqlx::query_as::<_, JobRow>(
-                "SELECT id, user_id, job_type, status, command, task, context, git_branch,
-                        files_id, image, cpus, memory_gb, timeout_minutes, container_id,
-                        exit_code, error, created_at, started_at, completed_at
-                 FROM jobs WHERE status = ?
+            sqlx::query_as::<_, JobRow>(&format!(
+                "SELECT {} FROM jobs WHERE status = ?
                  ORDER BY created_at DESC LIMIT ?",
-            )
+                JOB_COLUMNS
+            ))
             .bind(filter)
             .bind(limit as i64)
             .fetch_all(&self.pool)
             .await?
         } else {
-            sqlx::query_as::<_, JobRow>(
-                "SELECT id, user_id, job_type, status, command, task, context, git_branch,
-                        files_id, image, cpus, memory_gb, timeout_minutes, container_id,
-                        exit_code, error, created_at, started_at, completed_at
-                 FROM jobs
-                 ORDER BY created_at DESC LIMIT ?",
-            )
+            sqlx::query_as::<_, JobRow>(&format!(
+                "SELECT {} FROM jobs ORDER BY created_at DESC LIMIT ?",
+                JOB_COLUMNS
+            ))
             .bind(limit as i64)
             .fetch_all(&self.pool)
             .await?
@@ -246,6 +702,62 @@ pub struct ResourceUsage {
     pub running_jobs: i32,
 }
 
+/// One user's summed job costs
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct UserCost {
+    pub user_id: String,
+    pub jobs: i64,
+    pub cost_units: f64,
+}
+
+/// Bind the columns every new job is inserted with, in the order `create`
+/// lists them
+fn bind_new_job<'q>(
+    query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    job: &'q Job,
+) -> sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>> {
+    query
+        .bind(&job.id)
+        .bind(&job.user_id)
+        .bind(job.job_type.to_string())
+        .bind(job.status.to_string())
+        .bind(&job.command)
+        .bind(job.commands.as_ref().map(|c| serde_json::to_string(c).unwrap_or_default()))
+        .bind(&job.task)
+        .bind(&job.context)
+        .bind(&job.git_branch)
+        .bind(&job.files_id)
+        .bind(&job.input_from_job)
+        .bind(&job.image)
+        .bind(job.cpus)
+        .bind(job.memory_gb)
+        .bind(job.timeout_minutes)
+        .bind(job.restartable)
+        .bind(job.created_at.to_rfc3339())
+        .bind(encode_map(&job.labels))
+        .bind(encode_map(&job.annotations))
+        .bind(serde_json::to_string(&job.secret_refs).unwrap_or_else(|_| "[]".to_string()))
+        .bind(&job.submission.client_ip)
+        .bind(&job.submission.user_agent)
+        .bind(&job.submission.request_id)
+        .bind(job.start_after.map(|t| t.to_rfc3339()))
+        .bind(job.max_total_runtime_minutes)
+        .bind(encode_map(&job.node_selector))
+        .bind(serde_json::to_string(&job.artifact_paths).unwrap_or_else(|_| "[]".to_string()))
+        .bind(job.ephemeral)
+        .bind(serde_json::to_string(&job.success_exit_codes).unwrap_or_else(|_| "[0]".to_string()))
+        .bind(job.shm_size_mb)
+        .bind(job.promote_log_to_artifact)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Container is already managed as job {0}")]
+    AlreadyManaged(String),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
 /// Raw database row for jobs
 #[derive(sqlx::FromRow)]
 struct JobRow {
@@ -254,20 +766,43 @@ struct JobRow {
     job_type: String,
     status: String,
     command: Option<String>,
+    commands: Option<String>,
     task: Option<String>,
     context: Option<String>,
     git_branch: Option<String>,
     files_id: Option<String>,
+    input_from_job: Option<String>,
     image: String,
     cpus: i32,
     memory_gb: i32,
     timeout_minutes: i32,
+    restartable: bool,
     container_id: Option<String>,
     exit_code: Option<i32>,
     error: Option<String>,
     created_at: String,
     started_at: Option<String>,
     completed_at: Option<String>,
+    pending_reason: Option<String>,
+    labels: String,
+    annotations: String,
+    secret_refs: String,
+    client_ip: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+    start_after: Option<String>,
+    max_total_runtime_minutes: Option<i32>,
+    runtime_seconds: i64,
+    node_selector: String,
+    log_created_at: Option<String>,
+    logs_deleted_at: Option<String>,
+    artifact_paths: String,
+    ephemeral: bool,
+    cost_units: Option<f64>,
+    success_exit_codes: String,
+    failure_category: Option<String>,
+    shm_size_mb: Option<i32>,
+    promote_log_to_artifact: bool,
 }
 
 impl JobRow {
@@ -278,28 +813,86 @@ impl JobRow {
             job_type: self.job_type.parse().unwrap_or(JobType::Worker),
             status: self.status.parse().unwrap_or(JobStatus::Pending),
             command: self.command,
+            commands: self.commands.and_then(|s| serde_json::from_str(&s).ok()),
             task: self.task,
             context: self.context,
             git_branch: self.git_branch,
             files_id: self.files_id,
+            input_from_job: self.input_from_job,
             image: self.image,
             cpus: self.cpus,
             memory_gb: self.memory_gb,
             timeout_minutes: self.timeout_minutes,
+            restartable: self.restartable,
+            start_after: self.start_after.and_then(|s| parse_datetime_opt(&s)),
+            max_total_runtime_minutes: self.max_total_runtime_minutes,
+            node_selector: decode_map(&self.node_selector),
+            artifact_paths: serde_json::from_str(&self.artifact_paths).unwrap_or_else(|e| {
+                error!("Malformed stored artifact paths {:?}: {}", self.artifact_paths, e);
+                Vec::new()
+            }),
+            ephemeral: self.ephemeral,
+            success_exit_codes: serde_json::from_str(&self.success_exit_codes).unwrap_or_else(|e| {
+                error!("Malformed stored success exit codes {:?}: {}", self.success_exit_codes, e);
+                vec![0]
+            }),
+            shm_size_mb: self.shm_size_mb,
+            promote_log_to_artifact: self.promote_log_to_artifact,
             container_id: self.container_id,
             exit_code: self.exit_code,
             error: self.error,
+            failure_category: self.failure_category.and_then(|s| s.parse().ok()),
+            runtime_seconds: self.runtime_seconds,
+            cost_units: self.cost_units,
+            log_created_at: self.log_created_at.and_then(|s| parse_datetime_opt(&s)),
+            logs_deleted_at: self.logs_deleted_at.and_then(|s| parse_datetime_opt(&s)),
             created_at: parse_datetime(&self.created_at),
             started_at: self.started_at.and_then(|s| parse_datetime_opt(&s)),
             completed_at: self.completed_at.and_then(|s| parse_datetime_opt(&s)),
+            pending_reason: self.pending_reason.and_then(|s| s.parse().ok()),
+            labels: decode_map(&self.labels),
+            annotations: decode_map(&self.annotations),
+            secret_refs: serde_json::from_str(&self.secret_refs).unwrap_or_else(|e| {
+                error!("Malformed stored secret refs {:?}: {}", self.secret_refs, e);
+                Vec::new()
+            }),
+            submission: SubmissionSource {
+                client_ip: self.client_ip,
+                user_agent: self.user_agent,
+                request_id: self.request_id,
+            },
         }
     }
 }
 
-fn parse_datetime(s: &str) -> DateTime<Utc> {
+/// Extra `SET` clause for a status change that clears the pending reason
+/// of a job leaving `Pending`
+fn pending_reason_update(status: &JobStatus) -> &'static str {
+    if *status == JobStatus::Pending {
+        ""
+    } else {
+        ", pending_reason = NULL"
+    }
+}
+
+fn encode_map(map: &BTreeMap<String, String>) -> String {
+    serde_json::to_string(map).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn decode_map(s: &str) -> BTreeMap<String, String> {
+    serde_json::from_str(s).unwrap_or_else(|e| {
+        error!("Malformed stored map {:?}: {}", s, e);
+        BTreeMap::new()
+    })
+}
+
+pub(super) fn parse_datetime(s: &str) -> DateTime<Utc> {
     DateTime::parse_from_rfc3339(s)
         .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now())
+        .unwrap_or_else(|e| {
+            error!("Malformed stored timestamp {:?}: {}", s, e);
+            INVALID_TIMESTAMP
+        })
 }
 
 fn parse_datetime_opt(s: &str) -> Option<DateTime<Utc>> {
@@ -313,51 +906,16 @@ mod tests {
     use super::*;
 
     async fn create_test_pool() -> SqlitePool {
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE TABLE jobs (
-                id TEXT PRIMARY KEY,
-                user_id TEXT NOT NULL DEFAULT 'default',
-                job_type TEXT NOT NULL CHECK (job_type IN ('worker', 'agent')),
-                status TEXT NOT NULL CHECK (status IN ('pending', 'starting', 'running', 'completed', 'failed', 'timed_out', 'cancelled', 'cleaning', 'cleaned')),
-                command TEXT,
-                task TEXT,
-                context TEXT,
-                git_branch TEXT,
-                files_id TEXT,
-                image TEXT NOT NULL,
-                cpus INTEGER NOT NULL DEFAULT 2,
-                memory_gb INTEGER NOT NULL DEFAULT 4,
-                timeout_minutes INTEGER NOT NULL DEFAULT 30,
-                container_id TEXT,
-                exit_code INTEGER,
-                error TEXT,
-                created_at TEXT NOT NULL,
-                started_at TEXT,
-                completed_at TEXT
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE TABLE idempotency_keys (
-                client_job_id TEXT PRIMARY KEY,
-                job_id TEXT NOT NULL REFERENCES jobs(id),
-                active INTEGER NOT NULL DEFAULT 1
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
+        crate::db::init_db(":memory:").await.unwrap().inner().clone()
+    }
 
-        pool
+    fn sample_job() -> Job {
+        Job {
+            command: Some("echo test".to_string()),
+            cpus: 2,
+            memory_gb: 4,
+            ..Job::sample(&JobRepository::generate_id(), "default", JobStatus::Pending)
+        }
     }
 
     #[tokio::test]
@@ -366,25 +924,9 @@ mod tests {
         let repo = JobRepository::new(pool);
 
         let job = Job {
-            id: JobRepository::generate_id(),
             user_id: "user1".to_string(),
-            job_type: JobType::Worker,
-            status: JobStatus::Pending,
             command: Some("echo hello".to_string()),
-            task: None,
-            context: None,
-            git_branch: None,
-            files_id: None,
-            image: "ubuntu:22.04".to_string(),
-            cpus: 2,
-            memory_gb: 4,
-            timeout_minutes: 30,
-            container_id: None,
-            exit_code: None,
-            error: None,
-            created_at: Utc::now(),
-            started_at: None,
-            completed_at: None,
+            ..sample_job()
         };
 
         let created = repo.create(&job, None).await.unwrap();
@@ -396,31 +938,73 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_update_status() {
+    async fn test_labels_and_annotations_round_trip() {
         let pool = create_test_pool().await;
         let repo = JobRepository::new(pool);
 
         let job = Job {
-            id: JobRepository::generate_id(),
-            user_id: "default".to_string(),
-            job_type: JobType::Worker,
-            status: JobStatus::Pending,
-            command: Some("echo test".to_string()),
-            task: None,
-            context: None,
-            git_branch: None,
-            files_id: None,
-            image: "ubuntu:22.04".to_string(),
-            cpus: 2,
-            memory_gb: 4,
-            timeout_minutes: 30,
-            container_id: None,
-            exit_code: None,
-            error: None,
-            created_at: Utc::now(),
-            started_at: None,
-            completed_at: None,
+            labels: BTreeMap::from([("team".to_string(), "ml".to_string())]),
+            annotations: BTreeMap::from([("note".to_string(), "nightly run".to_string())]),
+            ..sample_job()
         };
+        repo.create(&job, None).await.unwrap();
+
+        let fetched = repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(fetched.labels, job.labels);
+        assert_eq!(fetched.annotations, job.annotations);
+        // A job without any keeps empty maps
+        let plain = sample_job();
+        repo.create(&plain, None).await.unwrap();
+        assert!(repo.get(&plain.id).await.unwrap().unwrap().labels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commands_round_trip() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool);
+
+        let commands = vec!["make".to_string(), "make test".to_string()];
+        let job = Job {
+            command: None,
+            commands: Some(commands.clone()),
+            ..sample_job()
+        };
+        repo.create(&job, None).await.unwrap();
+
+        let fetched = repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(fetched.command, None);
+        assert_eq!(fetched.commands, Some(commands));
+    }
+
+    #[tokio::test]
+    async fn test_count_active_by_type() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool);
+
+        for (job_type, status) in [
+            (JobType::Agent, JobStatus::Running),
+            (JobType::Agent, JobStatus::Starting),
+            (JobType::Agent, JobStatus::Completed),
+            (JobType::Worker, JobStatus::Running),
+        ] {
+            let job = Job {
+                job_type,
+                ..sample_job()
+            };
+            repo.create(&job, None).await.unwrap();
+            repo.update_status(&job.id, status).await.unwrap();
+        }
+
+        assert_eq!(repo.count_active_by_type(JobType::Agent).await.unwrap(), 2);
+        assert_eq!(repo.count_active_by_type(JobType::Worker).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_status() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool);
+
+        let job = sample_job();
 
         repo.create(&job, None).await.unwrap();
 
@@ -444,27 +1028,7 @@ mod tests {
 
         let client_job_id = "test-client-id-123";
 
-        let job = Job {
-            id: JobRepository::generate_id(),
-            user_id: "default".to_string(),
-            job_type: JobType::Worker,
-            status: JobStatus::Pending,
-            command: Some("echo test".to_string()),
-            task: None,
-            context: None,
-            git_branch: None,
-            files_id: None,
-            image: "ubuntu:22.04".to_string(),
-            cpus: 2,
-            memory_gb: 4,
-            timeout_minutes: 30,
-            container_id: None,
-            exit_code: None,
-            error: None,
-            created_at: Utc::now(),
-            started_at: None,
-            completed_at: None,
-        };
+        let job = sample_job();
 
         repo.create(&job, Some(client_job_id)).await.unwrap();
 
@@ -473,6 +1037,35 @@ mod tests {
         assert_eq!(found.id, job.id);
     }
 
+    #[tokio::test]
+    async fn test_get_cached_skips_the_database_until_invalidated() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool.clone()).with_cache(JobCache::new(
+            16,
+            std::time::Duration::from_secs(60),
+            std::time::Duration::from_secs(60),
+        ));
+        let job = sample_job();
+        repo.create(&job, None).await.unwrap();
+        assert_eq!(repo.get_cached(&job.id).await.unwrap().unwrap().status, JobStatus::Pending);
+
+        // A write behind the repository's back isn't seen: the hit never
+        // reached the database
+        sqlx::query("UPDATE jobs SET status = 'running' WHERE id = ?")
+            .bind(&job.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(repo.get_cached(&job.id).await.unwrap().unwrap().status, JobStatus::Pending);
+        assert_eq!(repo.get(&job.id).await.unwrap().unwrap().status, JobStatus::Running);
+
+        // Writes through the repository invalidate
+        repo.update_status(&job.id, JobStatus::Completed).await.unwrap();
+        assert_eq!(repo.get_cached(&job.id).await.unwrap().unwrap().status, JobStatus::Completed);
+        repo.set_exit_code(&job.id, 3).await.unwrap();
+        assert_eq!(repo.get_cached(&job.id).await.unwrap().unwrap().exit_code, Some(3));
+    }
+
     #[tokio::test]
     async fn test_get_resource_usage() {
         let pool = create_test_pool().await;
@@ -480,25 +1073,11 @@ mod tests {
 
         // Create running job
         let job1 = Job {
-            id: JobRepository::generate_id(),
-            user_id: "default".to_string(),
-            job_type: JobType::Worker,
             status: JobStatus::Running,
-            command: Some("echo test".to_string()),
-            task: None,
-            context: None,
-            git_branch: None,
-            files_id: None,
-            image: "ubuntu:22.04".to_string(),
             cpus: 4,
             memory_gb: 8,
-            timeout_minutes: 30,
-            container_id: None,
-            exit_code: None,
-            error: None,
-            created_at: Utc::now(),
             started_at: Some(Utc::now()),
-            completed_at: None,
+            ..sample_job()
         };
 
         let job2 = Job {
@@ -517,4 +1096,90 @@ mod tests {
         assert_eq!(usage.used_memory_gb, 12);
         assert_eq!(usage.running_jobs, 2);
     }
+
+    #[tokio::test]
+    async fn test_costs_since_sums_per_user() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool.clone());
+        let now = Utc::now();
+
+        for (user, cost, days_ago) in [("alice", 2.0, 0), ("alice", 3.5, 1), ("bob", 1.0, 0), ("bob", 9.0, 10)] {
+            let job = Job {
+                user_id: user.to_string(),
+                ..sample_job()
+            };
+            repo.create(&job, None).await.unwrap();
+            repo.update_status(&job.id, JobStatus::Completed).await.unwrap();
+            sqlx::query("UPDATE jobs SET completed_at = ? WHERE id = ?")
+                .bind((now - chrono::Duration::days(days_ago)).to_rfc3339())
+                .bind(&job.id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            repo.set_cost_units(&job.id, cost).await.unwrap();
+        }
+        // Finished but not costed yet: left out until the sweep records it
+        let uncosted = sample_job();
+        repo.create(&uncosted, None).await.unwrap();
+        repo.update_status(&uncosted.id, JobStatus::Failed).await.unwrap();
+        let pending: Vec<String> = repo.get_uncosted().await.unwrap().into_iter().map(|j| j.id).collect();
+        assert_eq!(pending, vec![uncosted.id.clone()]);
+
+        let since = now - chrono::Duration::days(7);
+        let costs = repo.costs_since(since, None).await.unwrap();
+        assert_eq!(
+            costs,
+            vec![
+                UserCost { user_id: "alice".to_string(), jobs: 2, cost_units: 5.5 },
+                UserCost { user_id: "bob".to_string(), jobs: 1, cost_units: 1.0 },
+            ]
+        );
+
+        let bob = repo.costs_since(now - chrono::Duration::days(30), Some("bob")).await.unwrap();
+        assert_eq!(bob, vec![UserCost { user_id: "bob".to_string(), jobs: 2, cost_units: 10.0 }]);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_created_at_is_surfaced() {
+        let pool = create_test_pool().await;
+
+        sqlx::query(
+            "INSERT INTO jobs (id, job_type, status, image, created_at)
+             VALUES ('job_badts', 'worker', 'pending', 'ubuntu:22.04', 'not-a-timestamp')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = JobRepository::new(pool);
+        let job = repo.get("job_badts").await.unwrap().unwrap();
+
+        // Must not be silently replaced with the current time
+        assert_eq!(job.created_at, INVALID_TIMESTAMP);
+    }
+
+    #[tokio::test]
+    async fn test_transition_status_respects_current_state() {
+        let pool = create_test_pool().await;
+        let repo = JobRepository::new(pool);
+
+        let job = sample_job();
+        repo.create(&job, None).await.unwrap();
+
+        assert!(repo
+            .transition_status(&job.id, &[JobStatus::Pending], JobStatus::Starting)
+            .await
+            .unwrap());
+
+        // A concurrent cancel wins; the later Running transition must not clobber it
+        repo.update_status(&job.id, JobStatus::Cancelled).await.unwrap();
+        assert!(!repo
+            .transition_status(&job.id, &[JobStatus::Starting], JobStatus::Running)
+            .await
+            .unwrap());
+
+        let fetched = repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(fetched.status, JobStatus::Cancelled);
+        assert!(fetched.started_at.is_none());
+    }
 }
@@ -0,0 +1,91 @@
+use super::DbPool;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Serializes maintenance runs so a slow VACUUM is never started twice
+static MAINTENANCE_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Outcome of a maintenance run
+#[derive(Debug, Clone, Copy)]
+pub struct MaintenanceReport {
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+impl MaintenanceReport {
+    /// Bytes returned to the filesystem
+    pub fn reclaimed_bytes(&self) -> i64 {
+        (self.size_before - self.size_after).max(0)
+    }
+}
+
+/// Checkpoint and truncate the WAL, then VACUUM the database.
+///
+/// Returns `None` without doing anything if another run is already in
+/// progress.
+pub async fn run_maintenance(pool: &DbPool) -> Result<Option<MaintenanceReport>, sqlx::Error> {
+    let Ok(_guard) = MAINTENANCE_LOCK.try_lock() else {
+        return Ok(None);
+    };
+
+    let size_before = database_size(pool).await?;
+
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool.inner())
+        .await?;
+    sqlx::query("VACUUM").execute(pool.inner()).await?;
+
+    let report = MaintenanceReport {
+        size_before,
+        size_after: database_size(pool).await?,
+    };
+    info!(
+        "Database maintenance reclaimed {} bytes ({} -> {})",
+        report.reclaimed_bytes(),
+        report.size_before,
+        report.size_after
+    );
+
+    Ok(Some(report))
+}
+
+/// Size of the main database file in bytes
+async fn database_size(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    let (page_count,): (i64,) = sqlx::query_as("PRAGMA page_count")
+        .fetch_one(pool.inner())
+        .await?;
+    let (page_size,): (i64,) = sqlx::query_as("PRAGMA page_size")
+        .fetch_one(pool.inner())
+        .await?;
+    Ok(page_count * page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_maintenance_on_populated_db() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("maintenance.db");
+        let pool = crate::db::init_db(path.to_str().unwrap()).await.unwrap();
+
+        for i in 0..500 {
+            sqlx::query(
+                "INSERT INTO uploads (id, state, created_at) VALUES (?, 'expired', '2026-01-01T00:00:00Z')",
+            )
+            .bind(format!("upload_{}_{}", i, "x".repeat(200)))
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        }
+        sqlx::query("DELETE FROM uploads")
+            .execute(pool.inner())
+            .await
+            .unwrap();
+
+        let report = run_maintenance(&pool).await.unwrap().unwrap();
+        assert!(report.size_after < report.size_before);
+        assert!(report.reclaimed_bytes() > 0);
+    }
+}
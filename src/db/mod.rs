@@ -1,17 +1,37 @@
-pub use jobs::{JobRepository, ResourceUsage};
+use chrono::{DateTime, Utc};
+
+pub use artifacts::{ArtifactRepository, NewArtifact};
+pub use errors::{database_error_response, database_error_status};
+pub use job_cache::JobCache;
+pub use jobs::{ImportError, JobRepository, ResourceUsage};
+pub use maintenance::run_maintenance;
 pub use pool::DbPool;
-pub use uploads::{FinalizeError, UploadRepository};
+pub use resources::ResourceSampleRepository;
+pub use retry::retry_on_lock;
+pub use uploads::{FinalizeError, TouchError, UploadRepository};
 
+mod artifacts;
+mod errors;
+mod job_cache;
 mod jobs;
+mod maintenance;
 mod pool;
+mod resources;
+mod retry;
 mod uploads;
 
 pub type Database = DbPool;
 
+/// Value substituted for stored timestamps that fail to parse.
+///
+/// Deliberately far from "now" so a corrupt row is visibly wrong instead of
+/// looking freshly created.
+pub const INVALID_TIMESTAMP: DateTime<Utc> = DateTime::<Utc>::UNIX_EPOCH;
+
 pub async fn init_db(db_path: &str) -> Result<Database, sqlx::Error> {
     let db = Database::new(db_path).await?;
 
-    pool::run_migrations(&db).await?;
+    pool::run_migrations(&db, &crate::middleware::auth::default_user_id()).await?;
 
     Ok(db)
 }
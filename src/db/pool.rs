@@ -15,38 +15,90 @@ impl DbPool {
     }
 }
 
-pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
+/// Create or update the schema. Rows inserted without an owner belong to
+/// `default_user` (see `FLASHPODS_DEFAULT_USER`); tables created by an older
+/// schema keep the default they were created with.
+pub async fn run_migrations(pool: &DbPool, default_user: &str) -> Result<(), sqlx::Error> {
     info!("Running database migrations");
+    let default_user = format!("'{}'", default_user.replace('\'', "''"));
 
     // Create jobs table
-    sqlx::query(
+    sqlx::query(&format!(
         r#"
         CREATE TABLE IF NOT EXISTS jobs (
             id TEXT PRIMARY KEY,
-            user_id TEXT NOT NULL DEFAULT 'default',
+            user_id TEXT NOT NULL DEFAULT {default_user},
             job_type TEXT NOT NULL CHECK (job_type IN ('worker', 'agent')),
             status TEXT NOT NULL CHECK (status IN ('pending', 'starting', 'running', 'completed', 'failed', 'timed_out', 'cancelled', 'cleaning', 'cleaned')),
             command TEXT,
+            commands TEXT,
             task TEXT,
             context TEXT,
             git_branch TEXT,
             files_id TEXT,
+            input_from_job TEXT,
             image TEXT NOT NULL,
             cpus INTEGER NOT NULL DEFAULT 2,
             memory_gb INTEGER NOT NULL DEFAULT 4,
             timeout_minutes INTEGER NOT NULL DEFAULT 30,
+            restartable INTEGER NOT NULL DEFAULT 0,
             container_id TEXT,
             exit_code INTEGER,
             error TEXT,
             created_at TEXT NOT NULL,
             started_at TEXT,
-            completed_at TEXT
+            completed_at TEXT,
+            pending_reason TEXT,
+            labels TEXT NOT NULL DEFAULT '{{}}',
+            annotations TEXT NOT NULL DEFAULT '{{}}',
+            secret_refs TEXT NOT NULL DEFAULT '[]',
+            client_ip TEXT,
+            user_agent TEXT,
+            request_id TEXT,
+            start_after TEXT,
+            max_total_runtime_minutes INTEGER,
+            runtime_seconds INTEGER NOT NULL DEFAULT 0,
+            node_selector TEXT NOT NULL DEFAULT '{{}}',
+            log_created_at TEXT,
+            logs_deleted_at TEXT,
+            artifact_paths TEXT NOT NULL DEFAULT '[]',
+            ephemeral INTEGER NOT NULL DEFAULT 0,
+            cost_units REAL,
+            success_exit_codes TEXT NOT NULL DEFAULT '[0]',
+            failure_category TEXT,
+            shm_size_mb INTEGER,
+            promote_log_to_artifact INTEGER NOT NULL DEFAULT 0
         )
     "#,
-    )
+    ))
     .execute(pool.inner())
     .await?;
 
+    // Columns added after the initial schema
+    add_column_if_missing(pool, "jobs", "labels", "TEXT NOT NULL DEFAULT '{}'").await?;
+    add_column_if_missing(pool, "jobs", "annotations", "TEXT NOT NULL DEFAULT '{}'").await?;
+    add_column_if_missing(pool, "jobs", "commands", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "pending_reason", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "input_from_job", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "restartable", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(pool, "jobs", "secret_refs", "TEXT NOT NULL DEFAULT '[]'").await?;
+    add_column_if_missing(pool, "jobs", "client_ip", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "user_agent", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "request_id", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "start_after", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "max_total_runtime_minutes", "INTEGER").await?;
+    add_column_if_missing(pool, "jobs", "runtime_seconds", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(pool, "jobs", "node_selector", "TEXT NOT NULL DEFAULT '{}'").await?;
+    add_column_if_missing(pool, "jobs", "log_created_at", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "logs_deleted_at", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "artifact_paths", "TEXT NOT NULL DEFAULT '[]'").await?;
+    add_column_if_missing(pool, "jobs", "ephemeral", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(pool, "jobs", "cost_units", "REAL").await?;
+    add_column_if_missing(pool, "jobs", "success_exit_codes", "TEXT NOT NULL DEFAULT '[0]'").await?;
+    add_column_if_missing(pool, "jobs", "failure_category", "TEXT").await?;
+    add_column_if_missing(pool, "jobs", "shm_size_mb", "INTEGER").await?;
+    add_column_if_missing(pool, "jobs", "promote_log_to_artifact", "INTEGER NOT NULL DEFAULT 0").await?;
+
     // Create jobs indexes
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_jobs_user_id ON jobs(user_id)")
         .execute(pool.inner())
@@ -77,11 +129,11 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
     .await?;
 
     // Create uploads table
-    sqlx::query(
+    sqlx::query(&format!(
         r#"
         CREATE TABLE IF NOT EXISTS uploads (
             id TEXT PRIMARY KEY,
-            user_id TEXT NOT NULL DEFAULT 'default',
+            user_id TEXT NOT NULL DEFAULT {default_user},
             state TEXT NOT NULL CHECK (state IN ('uploading', 'finalized', 'consumed', 'expired')),
             size_bytes INTEGER,
             file_count INTEGER,
@@ -92,7 +144,7 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
             job_id TEXT REFERENCES jobs(id) ON DELETE SET NULL
         )
     "#,
-    )
+    ))
     .execute(pool.inner())
     .await?;
 
@@ -113,6 +165,7 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
             job_id TEXT NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
             name TEXT NOT NULL,
             path TEXT NOT NULL,
+            original_path TEXT,
             size_bytes INTEGER NOT NULL,
             created_at TEXT NOT NULL,
             UNIQUE(job_id, name)
@@ -122,15 +175,79 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
     .execute(pool.inner())
     .await?;
 
+    add_column_if_missing(pool, "artifacts", "original_path", "TEXT").await?;
+
     // Create artifacts index
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_artifacts_job_id ON artifacts(job_id)")
         .execute(pool.inner())
         .await?;
 
+    // Create job event log
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS job_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id TEXT NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+            at TEXT NOT NULL,
+            event TEXT NOT NULL
+        )
+    "#,
+    )
+    .execute(pool.inner())
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_job_events_job_id ON job_events(job_id)")
+        .execute(pool.inner())
+        .await?;
+
+    // Create resource usage history
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS resource_samples (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            sampled_at TEXT NOT NULL,
+            used_cpus INTEGER NOT NULL,
+            used_memory_gb INTEGER NOT NULL,
+            running_jobs INTEGER NOT NULL
+        )
+    "#,
+    )
+    .execute(pool.inner())
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_resource_samples_sampled_at ON resource_samples(sampled_at)",
+    )
+    .execute(pool.inner())
+    .await?;
+
     info!("Database migrations completed");
     Ok(())
 }
 
+/// Add a column to an existing table, for databases created by an older schema
+async fn add_column_if_missing(
+    pool: &DbPool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let columns: Vec<(i64, String, String, i64, Option<String>, i64)> =
+        sqlx::query_as(&format!("PRAGMA table_info({})", table))
+            .fetch_all(pool.inner())
+            .await?;
+
+    if columns.iter().any(|c| c.1 == column) {
+        return Ok(());
+    }
+
+    info!("Adding column {}.{}", table, column);
+    sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,7 +256,9 @@ mod tests {
     async fn create_test_pool() -> DbPool {
         // Use in-memory database for tests
         let pool = DbPool::new(":memory:").await.expect("Failed to create test pool");
-        run_migrations(&pool).await.expect("Failed to run migrations");
+        run_migrations(&pool, crate::middleware::auth::DEFAULT_USER_ID)
+            .await
+            .expect("Failed to run migrations");
         pool
     }
 
@@ -156,7 +275,10 @@ mod tests {
         .await
         .expect("Failed to query tables");
 
-        assert_eq!(tables, vec!["artifacts", "idempotency_keys", "jobs", "uploads"]);
+        assert_eq!(
+            tables,
+            vec!["artifacts", "idempotency_keys", "job_events", "jobs", "resource_samples", "uploads"]
+        );
     }
 
     #[tokio::test]
@@ -173,9 +295,11 @@ mod tests {
 
         let expected = vec![
             "idx_artifacts_job_id",
+            "idx_job_events_job_id",
             "idx_idempotency_active",
             "idx_jobs_status",
             "idx_jobs_user_id",
+            "idx_resource_samples_sampled_at",
             "idx_uploads_expires_at",
             "idx_uploads_state",
         ];
@@ -386,6 +510,36 @@ mod tests {
         assert_eq!(row.get::<String, _>("user_id"), "default");
     }
 
+    #[tokio::test]
+    async fn test_rows_without_owner_get_configured_user() {
+        let pool = DbPool::new(":memory:").await.unwrap();
+        run_migrations(&pool, "o'neil-lab").await.unwrap();
+
+        // Written the way a legacy import would, without an owner
+        sqlx::query(
+            "INSERT INTO jobs (id, job_type, status, image, created_at)
+             VALUES ('job_legacy', 'worker', 'completed', 'rust:latest', '2026-01-28T10:00:00Z')",
+        )
+        .execute(pool.inner())
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO uploads (id, state, created_at)
+             VALUES ('upload_legacy', 'expired', '2026-01-28T10:00:00Z')",
+        )
+        .execute(pool.inner())
+        .await
+        .unwrap();
+
+        for query in [
+            "SELECT user_id FROM jobs WHERE id = 'job_legacy'",
+            "SELECT user_id FROM uploads WHERE id = 'upload_legacy'",
+        ] {
+            let row = sqlx::query(query).fetch_one(pool.inner()).await.unwrap();
+            assert_eq!(row.get::<String, _>("user_id"), "o'neil-lab");
+        }
+    }
+
     #[tokio::test]
     async fn test_idempotency_active_index_works() {
         let pool = create_test_pool().await;
@@ -510,12 +664,42 @@ mod tests {
         assert!(rows.contains(&"upload_exp2".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_migrations_add_missing_columns() {
+        let pool = DbPool::new(":memory:").await.unwrap();
+
+        // Simulate a database created before the labels column existed
+        sqlx::query(
+            "CREATE TABLE jobs (id TEXT PRIMARY KEY, user_id TEXT NOT NULL DEFAULT 'default',
+             job_type TEXT NOT NULL, status TEXT NOT NULL, image TEXT NOT NULL,
+             created_at TEXT NOT NULL)",
+        )
+        .execute(pool.inner())
+        .await
+        .unwrap();
+
+        add_column_if_missing(&pool, "jobs", "labels", "TEXT NOT NULL DEFAULT '{}'")
+            .await
+            .unwrap();
+        // Second run is a no-op
+        add_column_if_missing(&pool, "jobs", "labels", "TEXT NOT NULL DEFAULT '{}'")
+            .await
+            .unwrap();
+
+        let columns: Vec<String> = sqlx::query("PRAGMA table_info(jobs)")
+            .map(|row: SqliteRow| row.get("name"))
+            .fetch_all(pool.inner())
+            .await
+            .unwrap();
+        assert!(columns.contains(&"labels".to_string()));
+    }
+
     #[tokio::test]
     async fn test_migrations_are_idempotent() {
         let pool = create_test_pool().await;
 
         // Run migrations again on the same pool
-        let result = run_migrations(&pool).await;
+        let result = run_migrations(&pool, crate::middleware::auth::DEFAULT_USER_ID).await;
 
         // Should succeed without error (IF NOT EXISTS)
         assert!(result.is_ok(), "Migrations should be idempotent");
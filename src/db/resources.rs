@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use super::{retry_on_lock, ResourceUsage};
+
+/// History of node resource usage, sampled periodically for capacity
+/// planning
+pub struct ResourceSampleRepository {
+    pool: SqlitePool,
+}
+
+/// Peak and average usage over a set of samples
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourcePeaks {
+    pub samples: i64,
+    pub peak_cpus: i32,
+    pub peak_memory_gb: i32,
+    pub peak_running_jobs: i32,
+    pub avg_cpus: f64,
+    pub avg_memory_gb: f64,
+}
+
+impl ResourceSampleRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record usage as of `at`
+    pub async fn record(&self, at: DateTime<Utc>, usage: &ResourceUsage) -> Result<(), sqlx::Error> {
+        retry_on_lock(|| {
+            sqlx::query(
+                "INSERT INTO resource_samples (sampled_at, used_cpus, used_memory_gb, running_jobs)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(at.to_rfc3339())
+            .bind(usage.used_cpus)
+            .bind(usage.used_memory_gb)
+            .bind(usage.running_jobs)
+            .execute(&self.pool)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Peaks and averages of the samples taken since `since`
+    pub async fn peaks_since(&self, since: DateTime<Utc>) -> Result<ResourcePeaks, sqlx::Error> {
+        let row = sqlx::query_as::<_, PeaksRow>(
+            "SELECT COUNT(*) AS samples, MAX(used_cpus) AS peak_cpus,
+                    MAX(used_memory_gb) AS peak_memory_gb, MAX(running_jobs) AS peak_running_jobs,
+                    AVG(used_cpus) AS avg_cpus, AVG(used_memory_gb) AS avg_memory_gb
+             FROM resource_samples WHERE sampled_at >= ?",
+        )
+        .bind(since.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Aggregates over no rows are NULL
+        Ok(ResourcePeaks {
+            samples: row.samples,
+            peak_cpus: row.peak_cpus.unwrap_or(0) as i32,
+            peak_memory_gb: row.peak_memory_gb.unwrap_or(0) as i32,
+            peak_running_jobs: row.peak_running_jobs.unwrap_or(0) as i32,
+            avg_cpus: row.avg_cpus.unwrap_or(0.0),
+            avg_memory_gb: row.avg_memory_gb.unwrap_or(0.0),
+        })
+    }
+
+    /// Delete samples taken before `before`, returning how many were removed
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = retry_on_lock(|| {
+            sqlx::query("DELETE FROM resource_samples WHERE sampled_at < ?")
+                .bind(before.to_rfc3339())
+                .execute(&self.pool)
+        })
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PeaksRow {
+    samples: i64,
+    peak_cpus: Option<i64>,
+    peak_memory_gb: Option<i64>,
+    peak_running_jobs: Option<i64>,
+    avg_cpus: Option<f64>,
+    avg_memory_gb: Option<f64>,
+}
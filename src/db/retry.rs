@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+use super::errors::{classify_error, DbErrorKind};
+
+/// Attempts made before a lock error is returned to the caller
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled before each one after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(20);
+
+/// Run a database write, retrying with backoff while SQLite reports the
+/// database busy or locked. A statement that failed that way made no
+/// change, so running it again is safe. Other errors return immediately.
+///
+/// `op` builds and runs the statement afresh on each attempt.
+pub async fn retry_on_lock<T, F, Fut>(mut op: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < MAX_ATTEMPTS && classify_error(&e) == DbErrorKind::Locked => {
+                warn!(
+                    "Database locked (attempt {}/{}), retrying in {:?}: {}",
+                    attempt, MAX_ATTEMPTS, backoff, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::{ConnectOptions, Connection};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// A real `database is locked` error: one connection holds the write
+    /// lock while another, which won't wait for it, tries to write
+    async fn locked_error() -> sqlx::Error {
+        let dir = tempfile::TempDir::new().unwrap();
+        let options = SqliteConnectOptions::from_str(&format!(
+            "sqlite:{}?mode=rwc",
+            dir.path().join("locked.db").display()
+        ))
+        .unwrap();
+        let mut holder = options.connect().await.unwrap();
+        sqlx::query("CREATE TABLE t (x INTEGER)").execute(&mut holder).await.unwrap();
+        let mut writer = options.clone().busy_timeout(Duration::ZERO).connect().await.unwrap();
+
+        let mut tx = holder.begin().await.unwrap();
+        sqlx::query("INSERT INTO t VALUES (1)").execute(&mut *tx).await.unwrap();
+        sqlx::query("INSERT INTO t VALUES (2)")
+            .execute(&mut writer)
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_the_lock_clears() {
+        let transient = Mutex::new(Some(locked_error().await));
+        assert_eq!(classify_error(transient.lock().unwrap().as_ref().unwrap()), DbErrorKind::Locked);
+        let attempts = AtomicU32::new(0);
+
+        let result = retry_on_lock(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            let outcome = match transient.lock().unwrap().take() {
+                Some(e) => Err(e),
+                None => Ok(7),
+            };
+            async move { outcome }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = retry_on_lock(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(sqlx::Error::RowNotFound) }
+        })
+        .await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
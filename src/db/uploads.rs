@@ -1,7 +1,8 @@
 use crate::models::{Upload, UploadState};
+use super::{retry_on_lock, INVALID_TIMESTAMP};
 use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
-use tracing::info;
+use tracing::{error, info};
 
 pub struct UploadRepository {
     pool: SqlitePool,
@@ -29,24 +30,28 @@ impl UploadRepository {
         let now = Utc::now();
         let state = "uploading";
 
-        sqlx::query(
-            "INSERT INTO uploads (id, user_id, state, created_at) VALUES (?, ?, ?, ?)",
-        )
-        .bind(id)
-        .bind(user_id)
-        .bind(state)
-        .bind(now.to_rfc3339())
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                "INSERT INTO uploads (id, user_id, state, created_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(state)
+            .bind(now.to_rfc3339())
+            .execute(&self.pool)
+        })
         .await?;
 
         // Set initial expiry for uploading state (30 min default)
         let expires_at = now + chrono::Duration::minutes(30);
-        sqlx::query(
-            "UPDATE uploads SET expires_at = ? WHERE id = ?",
-        )
-        .bind(expires_at.to_rfc3339())
-        .bind(id)
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE uploads SET expires_at = ? WHERE id = ?",
+            )
+            .bind(expires_at.to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
 
         self.get(id).await?.ok_or_else(|| {
@@ -63,21 +68,23 @@ impl UploadRepository {
                 let now = Utc::now();
                 let expires_at = now + chrono::Duration::minutes(60);
 
-                sqlx::query(
-                    r#"UPDATE uploads
-                       SET state = 'finalized',
-                           size_bytes = ?,
-                           file_count = ?,
-                           finalized_at = ?,
-                           expires_at = ?
-                       WHERE id = ?"#,
-                )
-                .bind(size_bytes)
-                .bind(file_count)
-                .bind(now.to_rfc3339())
-                .bind(expires_at.to_rfc3339())
-                .bind(id)
-                .execute(&self.pool)
+                retry_on_lock(|| {
+                    sqlx::query(
+                        r#"UPDATE uploads
+                           SET state = 'finalized',
+                               size_bytes = ?,
+                               file_count = ?,
+                               finalized_at = ?,
+                               expires_at = ?
+                           WHERE id = ?"#,
+                    )
+                    .bind(size_bytes)
+                    .bind(file_count)
+                    .bind(now.to_rfc3339())
+                    .bind(expires_at.to_rfc3339())
+                    .bind(id)
+                    .execute(&self.pool)
+                })
                 .await?;
 
                 info!("Finalized upload {} ({} bytes, {} files)", id, size_bytes, file_count);
@@ -89,20 +96,56 @@ impl UploadRepository {
         }
     }
 
+    /// Extend an uploading upload's expiry to `ttl` from now, never past
+    /// `max_lifetime` after it was created
+    pub async fn touch(
+        &self,
+        id: &str,
+        ttl: chrono::Duration,
+        max_lifetime: chrono::Duration,
+    ) -> Result<Upload, TouchError> {
+        let upload = self.get(id).await?.ok_or(TouchError::NotFound)?;
+
+        if upload.state != UploadState::Uploading {
+            return Err(TouchError::NotUploading(upload.state));
+        }
+
+        let now = Utc::now();
+        let deadline = upload.created_at + max_lifetime;
+        if now >= deadline {
+            return Err(TouchError::LifetimeExceeded);
+        }
+        let expires_at = (now + ttl).min(deadline);
+
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE uploads SET expires_at = ? WHERE id = ? AND state = 'uploading'",
+            )
+            .bind(expires_at.to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+        })
+        .await?;
+
+        self.get(id).await?.ok_or(TouchError::NotFound)
+    }
+
     /// Mark upload as consumed (called when job reaches running state)
     pub async fn consume(&self, id: &str, job_id: &str) -> Result<(), sqlx::Error> {
         let now = Utc::now();
-        sqlx::query(
-            r#"UPDATE uploads
-               SET state = 'consumed',
-                   consumed_at = ?,
-                   job_id = ?
-               WHERE id = ?"#,
-        )
-        .bind(now.to_rfc3339())
-        .bind(job_id)
-        .bind(id)
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                r#"UPDATE uploads
+                   SET state = 'consumed',
+                       consumed_at = ?,
+                       job_id = ?
+                   WHERE id = ?"#,
+            )
+            .bind(now.to_rfc3339())
+            .bind(job_id)
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
 
         info!("Consumed upload {} for job {}", id, job_id);
@@ -111,11 +154,13 @@ impl UploadRepository {
 
     /// Delete an upload (soft delete by marking as expired)
     pub async fn delete(&self, id: &str) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query(
-            "UPDATE uploads SET state = 'expired' WHERE id = ? AND state IN ('uploading', 'finalized')",
-        )
-        .bind(id)
-        .execute(&self.pool)
+        let result = retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE uploads SET state = 'expired' WHERE id = ? AND state IN ('uploading', 'finalized')",
+            )
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
 
         let deleted = result.rows_affected() > 0;
@@ -125,6 +170,25 @@ impl UploadRepository {
         Ok(deleted)
     }
 
+    /// Expire an upload whose job has been cleaned. Returns `false` if it was
+    /// not finalized or consumed (e.g. already expired).
+    pub async fn expire_used(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE uploads SET state = 'expired' WHERE id = ? AND state IN ('finalized', 'consumed')",
+            )
+            .bind(id)
+            .execute(&self.pool)
+        })
+        .await?;
+
+        let expired = result.rows_affected() > 0;
+        if expired {
+            info!("Expired upload {} after its job was cleaned", id);
+        }
+        Ok(expired)
+    }
+
     /// Get total disk usage for uploads in uploading or finalized state
     pub async fn get_total_disk_usage(&self) -> Result<i64, sqlx::Error> {
         let row: Option<(i64,)> = sqlx::query_as(
@@ -136,6 +200,27 @@ impl UploadRepository {
         Ok(row.map(|(v,)| v).unwrap_or(0))
     }
 
+    /// Ids of every upload with a record, whatever its state
+    pub async fn all_ids(&self) -> Result<std::collections::HashSet<String>, sqlx::Error> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT id FROM uploads")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Disk usage of one user's uploads that still hold their files
+    pub async fn get_user_disk_usage(&self, user_id: &str) -> Result<i64, sqlx::Error> {
+        let (usage,): (i64,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(size_bytes), 0) FROM uploads
+             WHERE user_id = ? AND state IN ('uploading', 'finalized')",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(usage)
+    }
+
     /// Get expired uploads for cleanup
     pub async fn get_expired(&self) -> Result<Vec<Upload>, sqlx::Error> {
         let now = Utc::now();
@@ -153,11 +238,13 @@ impl UploadRepository {
 
     /// Mark upload as expired
     pub async fn mark_expired(&self, id: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            "UPDATE uploads SET state = 'expired' WHERE id = ?",
-        )
-        .bind(id)
-        .execute(&self.pool)
+        retry_on_lock(|| {
+            sqlx::query(
+                "UPDATE uploads SET state = 'expired' WHERE id = ?",
+            )
+            .bind(id)
+            .execute(&self.pool)
+        })
         .await?;
         Ok(())
     }
@@ -177,6 +264,18 @@ pub enum FinalizeError {
     Database(#[from] sqlx::Error),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum TouchError {
+    #[error("Upload not found")]
+    NotFound,
+    #[error("Upload is {0}, only uploading uploads can be touched")]
+    NotUploading(UploadState),
+    #[error("Upload has reached its maximum lifetime")]
+    LifetimeExceeded,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
 /// Raw database row for uploads
 #[derive(sqlx::FromRow)]
 struct UploadRow {
@@ -212,7 +311,10 @@ impl UploadRow {
 fn parse_datetime(s: &str) -> DateTime<Utc> {
     DateTime::parse_from_rfc3339(s)
         .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now())
+        .unwrap_or_else(|e| {
+            error!("Malformed stored timestamp {:?}: {}", s, e);
+            INVALID_TIMESTAMP
+        })
 }
 
 fn parse_datetime_opt(s: &str) -> Option<DateTime<Utc>> {
@@ -339,5 +441,78 @@ mod tests {
 
         let usage = repo.get_total_disk_usage().await.unwrap();
         assert_eq!(usage, 3000);
+
+        repo.create("upload_test8", "user2").await.unwrap();
+        repo.finalize("upload_test8", 500, 1).await.unwrap();
+        assert_eq!(repo.get_user_disk_usage("user1").await.unwrap(), 3000);
+        assert_eq!(repo.get_user_disk_usage("user2").await.unwrap(), 500);
+        assert_eq!(repo.get_user_disk_usage("nobody").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_touch_refreshes_expiry() {
+        let pool = create_test_pool().await;
+        let repo = UploadRepository::new(pool);
+
+        let upload = repo.create("upload_touch1", "user1").await.unwrap();
+        let touched = repo
+            .touch("upload_touch1", chrono::Duration::hours(2), chrono::Duration::hours(24))
+            .await
+            .unwrap();
+        assert!(touched.expires_at.unwrap() > upload.expires_at.unwrap());
+        assert_eq!(touched.state, UploadState::Uploading);
+
+        repo.finalize("upload_touch1", 10, 1).await.unwrap();
+        let result = repo
+            .touch("upload_touch1", chrono::Duration::hours(2), chrono::Duration::hours(24))
+            .await;
+        assert!(matches!(result, Err(TouchError::NotUploading(UploadState::Finalized))));
+    }
+
+    #[tokio::test]
+    async fn test_touch_caps_total_lifetime() {
+        let pool = create_test_pool().await;
+        let repo = UploadRepository::new(pool.clone());
+
+        let upload = repo.create("upload_touch2", "user1").await.unwrap();
+        let max_lifetime = chrono::Duration::minutes(45);
+
+        // Expiry is clamped to created_at + max_lifetime
+        let touched = repo
+            .touch("upload_touch2", chrono::Duration::hours(2), max_lifetime)
+            .await
+            .unwrap();
+        assert_eq!(
+            touched.expires_at.unwrap().timestamp(),
+            (upload.created_at + max_lifetime).timestamp()
+        );
+
+        // Once past the cap, touching is refused
+        sqlx::query("UPDATE uploads SET created_at = ? WHERE id = 'upload_touch2'")
+            .bind((Utc::now() - chrono::Duration::hours(1)).to_rfc3339())
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result = repo
+            .touch("upload_touch2", chrono::Duration::minutes(30), max_lifetime)
+            .await;
+        assert!(matches!(result, Err(TouchError::LifetimeExceeded)));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_created_at_is_surfaced() {
+        let pool = create_test_pool().await;
+
+        sqlx::query(
+            "INSERT INTO uploads (id, state, created_at) VALUES ('upload_badts', 'uploading', '2026-13-45')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = UploadRepository::new(pool);
+        let upload = repo.get("upload_badts").await.unwrap().unwrap();
+
+        assert_eq!(upload.created_at, INVALID_TIMESTAMP);
     }
 }
@@ -0,0 +1,69 @@
+use crate::middleware::Caller;
+use crate::models::Job;
+
+/// Outcome of checking whether a caller may see a job
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobAccess {
+    Found,
+    NotFound,
+    Forbidden,
+}
+
+/// Decide whether `caller` may access `job`.
+///
+/// Owners always see their jobs. Other regular users get `NotFound`, exactly
+/// as if the job didn't exist, so job ids can't be probed across users.
+/// Admins get `Forbidden` instead: they are trusted with the knowledge that
+/// the job exists but go through admin endpoints to act on it.
+pub fn authorize_job_access(job: Option<&Job>, caller: &Caller) -> JobAccess {
+    match job {
+        None => JobAccess::NotFound,
+        Some(job) if job.user_id == caller.user_id => JobAccess::Found,
+        Some(_) if caller.is_admin => JobAccess::Forbidden,
+        Some(_) => JobAccess::NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::JobStatus;
+
+    fn job_owned_by(user_id: &str) -> Job {
+        Job {
+            command: Some("true".to_string()),
+            timeout_minutes: 5,
+            ..Job::sample("job_access", user_id, JobStatus::Running)
+        }
+    }
+
+    fn caller(user_id: &str, is_admin: bool) -> Caller {
+        Caller {
+            user_id: user_id.to_string(),
+            is_admin,
+        }
+    }
+
+    #[test]
+    fn test_owner_finds_job() {
+        let job = job_owned_by("alice");
+        assert_eq!(authorize_job_access(Some(&job), &caller("alice", false)), JobAccess::Found);
+    }
+
+    #[test]
+    fn test_other_user_gets_not_found() {
+        let job = job_owned_by("alice");
+        assert_eq!(authorize_job_access(Some(&job), &caller("bob", false)), JobAccess::NotFound);
+    }
+
+    #[test]
+    fn test_admin_gets_forbidden() {
+        let job = job_owned_by("alice");
+        assert_eq!(authorize_job_access(Some(&job), &caller("root", true)), JobAccess::Forbidden);
+    }
+
+    #[test]
+    fn test_missing_job_is_not_found() {
+        assert_eq!(authorize_job_access(None, &caller("root", true)), JobAccess::NotFound);
+    }
+}
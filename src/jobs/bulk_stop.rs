@@ -0,0 +1,201 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::models::{Job, JobType};
+use crate::AppState;
+
+/// Which containers a bulk stop stops first. Set with `FLASHPODS_STOP_ORDER`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StopOrder {
+    /// Longest-running first (`oldest-first`)
+    #[default]
+    OldestFirst,
+    /// Agents, which write to their upload, before workers; oldest first
+    /// within each (`agents-first`)
+    AgentsFirst,
+}
+
+impl FromStr for StopOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest-first" => Ok(Self::OldestFirst),
+            "agents-first" => Ok(Self::AgentsFirst),
+            _ => Err(format!(
+                "Invalid stop order '{}': expected 'oldest-first' or 'agents-first'",
+                s
+            )),
+        }
+    }
+}
+
+/// Sort jobs into the order their containers should be stopped in
+pub fn order_for_stop(jobs: &mut [Job], order: StopOrder) {
+    let started = |job: &Job| job.started_at.unwrap_or(job.created_at);
+    match order {
+        StopOrder::OldestFirst => jobs.sort_by_key(started),
+        StopOrder::AgentsFirst => {
+            jobs.sort_by_key(|job| (job.job_type != JobType::Agent, started(job)))
+        }
+    }
+}
+
+/// Outcome of a bulk stop
+#[derive(Debug, Default, PartialEq)]
+pub struct StopSummary {
+    pub stopped: usize,
+    pub failed: usize,
+}
+
+/// Stop the containers of `jobs`, at most `concurrency` at a time, starting
+/// them in `order`. Jobs without a container are skipped; a failure is
+/// logged and counted without holding up the rest. Job statuses are left to
+/// the caller.
+pub async fn stop_jobs(
+    state: &AppState,
+    mut jobs: Vec<Job>,
+    order: StopOrder,
+    concurrency: usize,
+) -> StopSummary {
+    order_for_stop(&mut jobs, order);
+    let grace_seconds = state.job_config.kill_grace_seconds;
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for job in jobs {
+        let Some(container_id) = job.container_id else {
+            continue;
+        };
+        // Taking the permit before spawning keeps the stops starting in order
+        let permit = semaphore.clone().acquire_owned().await;
+        let state = state.clone();
+        tasks.spawn_blocking(move || {
+            let _permit = permit;
+            let result = super::stop_or_kill(&state, &container_id, grace_seconds);
+            (job.id, result)
+        });
+    }
+
+    let mut summary = StopSummary::default();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((job_id, Ok(()))) => {
+                info!("Stopped container of job {}", job_id);
+                summary.stopped += 1;
+            }
+            Ok((job_id, Err(e))) => {
+                warn!("Failed to stop container of job {}: {}", job_id, e);
+                summary.failed += 1;
+            }
+            Err(e) => {
+                warn!("Container stop task panicked: {}", e);
+                summary.failed += 1;
+            }
+        }
+    }
+    summary
+}
+
+/// Stop every active job's container, per `stop_order` and
+/// `stop_concurrency`, e.g. when the API shuts down
+pub async fn stop_active_jobs(state: &AppState) -> Result<StopSummary, sqlx::Error> {
+    let jobs = state.job_repo.get_active_jobs().await?;
+    Ok(stop_jobs(
+        state,
+        jobs,
+        state.job_config.stop_order,
+        state.job_config.stop_concurrency,
+    )
+    .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JobRepository;
+    use crate::models::JobStatus;
+    use chrono::{Duration, Utc};
+
+    fn running(job_type: JobType, minutes_ago: i64, container_id: Option<&str>) -> Job {
+        let started = Utc::now() - Duration::minutes(minutes_ago);
+        Job {
+            job_type,
+            container_id: container_id.map(str::to_string),
+            created_at: started,
+            started_at: Some(started),
+            ..Job::sample(&JobRepository::generate_id(), "default", JobStatus::Running)
+        }
+    }
+
+    fn stop_calls(dir: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(dir.join("calls.log"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.strip_prefix("stop -t 10 "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_stop_order() {
+        assert_eq!("oldest-first".parse(), Ok(StopOrder::OldestFirst));
+        assert_eq!("agents-first".parse(), Ok(StopOrder::AgentsFirst));
+        assert!("random".parse::<StopOrder>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stops_follow_the_order_policy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: Arc::new(crate::podman::PodmanService::scripted(dir.path(), "")),
+            ..crate::test_state().await
+        };
+        let jobs = vec![
+            running(JobType::Worker, 5, Some("ctr_worker_new")),
+            running(JobType::Agent, 10, Some("ctr_agent_new")),
+            running(JobType::Worker, 60, Some("ctr_worker_old")),
+            running(JobType::Agent, 30, Some("ctr_agent_old")),
+            running(JobType::Worker, 90, None),
+        ];
+
+        // One at a time, so the calls land in stop order
+        let summary = stop_jobs(&state, jobs.clone(), StopOrder::OldestFirst, 1).await;
+        assert_eq!(summary, StopSummary { stopped: 4, failed: 0 });
+        assert_eq!(
+            stop_calls(dir.path()),
+            ["ctr_worker_old", "ctr_agent_old", "ctr_agent_new", "ctr_worker_new"]
+        );
+
+        std::fs::remove_file(dir.path().join("calls.log")).unwrap();
+        stop_jobs(&state, jobs, StopOrder::AgentsFirst, 1).await;
+        assert_eq!(
+            stop_calls(dir.path()),
+            ["ctr_agent_old", "ctr_agent_new", "ctr_worker_old", "ctr_worker_new"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_stops_reach_every_container_despite_failures() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$*" in "stop -t 10 ctr_3"|"kill ctr_3") exit 1 ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let jobs: Vec<Job> = (0..8)
+            .map(|i| running(JobType::Worker, i, Some(&format!("ctr_{}", i))))
+            .collect();
+
+        let summary = stop_jobs(&state, jobs, StopOrder::OldestFirst, 3).await;
+        assert_eq!(summary, StopSummary { stopped: 7, failed: 1 });
+        let mut stopped = stop_calls(dir.path());
+        stopped.sort();
+        assert_eq!(stopped, (0..8).map(|i| format!("ctr_{}", i)).collect::<Vec<_>>());
+    }
+}
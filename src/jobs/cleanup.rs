@@ -0,0 +1,414 @@
+use chrono::{Duration, Utc};
+use tracing::{info, warn};
+
+use crate::models::{Job, JobStatus, JobType};
+use crate::AppState;
+
+/// Move a finished job to `cleaned`: delete its artifacts (including a
+/// promoted log) and release the upload it ran from. The captured log has
+/// its own retention (see `expire_log`). Returns `false` if the job wasn't
+/// in a terminal state.
+///
+/// A job left in `cleaning` by an earlier failure is picked up again, so
+/// every step here is safe to repeat.
+pub async fn clean_job(state: &AppState, job: &Job) -> Result<bool, sqlx::Error> {
+    let cleanable = [
+        JobStatus::Completed,
+        JobStatus::Failed,
+        JobStatus::TimedOut,
+        JobStatus::Cancelled,
+        JobStatus::Cleaning,
+    ];
+    if !state
+        .job_repo
+        .transition_status(&job.id, &cleanable, JobStatus::Cleaning)
+        .await?
+    {
+        return Ok(false);
+    }
+
+    let artifacts = state.podman.artifacts_path(&job.id);
+    report_removal(&artifacts, tokio::fs::remove_dir_all(&artifacts).await);
+    let job_state = state.podman.state_path(&job.id);
+    report_removal(&job_state, tokio::fs::remove_dir_all(&job_state).await);
+    let promoted = crate::logs::promoted_log_path(&state.job_config.logs_dir, &job.id);
+    report_removal(&promoted.to_string_lossy(), tokio::fs::remove_file(&promoted).await);
+    release_upload(state, job).await?;
+
+    state.job_repo.update_status(&job.id, JobStatus::Cleaned).await?;
+    info!("Cleaned job {}", job.id);
+    Ok(true)
+}
+
+/// Delete a finished job's captured log and record that it's gone
+pub async fn expire_log(state: &AppState, job: &Job) -> Result<(), sqlx::Error> {
+    let log = crate::logs::log_path(&state.job_config.logs_dir, &job.id);
+    report_removal(&log.to_string_lossy(), tokio::fs::remove_file(&log).await);
+    state.job_repo.mark_logs_deleted(&job.id).await?;
+    info!("Deleted logs of job {}", job.id);
+    Ok(())
+}
+
+/// Expire the job's upload and delete its files. A worker's read-only
+/// upload is kept while another unfinished job still mounts it; an agent
+/// wrote to its upload, so it is never reused.
+async fn release_upload(state: &AppState, job: &Job) -> Result<(), sqlx::Error> {
+    let Some(upload_id) = &job.files_id else {
+        return Ok(());
+    };
+    if job.job_type == JobType::Worker && state.job_repo.upload_in_use(upload_id, &job.id).await? {
+        info!("Keeping upload {}: still used by another job", upload_id);
+        return Ok(());
+    }
+
+    if state.upload_repo.expire_used(upload_id).await? {
+        let dir = std::path::Path::new(&state.upload_config.upload_dir).join(upload_id);
+        report_removal(&dir.to_string_lossy(), tokio::fs::remove_dir_all(&dir).await);
+    }
+    Ok(())
+}
+
+/// Log a failed deletion; files that are already gone are fine
+fn report_removal(path: &str, result: std::io::Result<()>) {
+    match result {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to delete {}: {}", path, e),
+    }
+}
+
+/// Remove the containers of finished jobs, which podman keeps when
+/// `container_autoremove` is off. Containers of unknown jobs are left alone.
+/// Returns how many were removed.
+pub async fn reap_containers(state: &AppState) -> usize {
+    let podman = state.podman.clone();
+    let containers = match tokio::task::spawn_blocking(move || podman.list_containers()).await {
+        Ok(Ok(containers)) => containers,
+        Ok(Err(e)) => {
+            warn!("Failed to list containers to reap: {}", e);
+            return 0;
+        }
+        Err(e) => {
+            warn!("Container list task failed: {}", e);
+            return 0;
+        }
+    };
+
+    let mut removed = 0;
+    for container in containers {
+        let Some(job_id) = container.labels.get("flashpods-job-id") else {
+            continue;
+        };
+        let finished = match state.job_repo.get(job_id).await {
+            Ok(Some(job)) => {
+                job.status.is_terminal()
+                    || matches!(job.status, JobStatus::Cleaning | JobStatus::Cleaned)
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Failed to look up job {} to reap its container: {}", job_id, e);
+                false
+            }
+        };
+        if !finished {
+            continue;
+        }
+
+        let podman = state.podman.clone();
+        let container_id = container.id.clone();
+        match tokio::task::spawn_blocking(move || podman.remove_container(&container_id)).await {
+            Ok(Ok(())) => removed += 1,
+            Ok(Err(e)) => warn!("Failed to remove container {} of job {}: {}", container.id, job_id, e),
+            Err(e) => warn!("Container remove task failed: {}", e),
+        }
+    }
+    removed
+}
+
+/// Record `cost_units` for finished jobs that don't have one yet, using
+/// `cost_formula`. Returns how many were recorded.
+pub async fn record_costs(state: &AppState) -> Result<usize, sqlx::Error> {
+    let mut recorded = 0;
+    for job in state.job_repo.get_uncosted().await? {
+        let cost = state.job_config.cost_formula.job_cost(&job);
+        match state.job_repo.set_cost_units(&job.id, cost).await {
+            Ok(()) => recorded += 1,
+            Err(e) => warn!("Failed to record cost of job {}: {}", job.id, e),
+        }
+    }
+    Ok(recorded)
+}
+
+/// One cleanup pass: record finished jobs' costs, remove finished
+/// containers unless podman does so itself, clean jobs whose artifacts are
+/// past `artifact_retention_minutes`, then delete logs past
+/// `log_retention_minutes`. The two retentions expire independently.
+pub async fn sweep(state: &AppState) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    if let Err(e) = record_costs(state).await {
+        warn!("Failed to record job costs: {}", e);
+    }
+    if !state.job_config.container_autoremove {
+        reap_containers(state).await;
+    }
+
+    let before = now - Duration::minutes(state.job_config.artifact_retention_minutes);
+    for job in state.job_repo.get_cleanable(before).await? {
+        if let Err(e) = clean_job(state, &job).await {
+            warn!("Failed to clean job {}: {}", job.id, e);
+        }
+    }
+
+    let before = now - Duration::minutes(state.job_config.log_retention_minutes);
+    for job in state.job_repo.get_expired_logs(before).await? {
+        if let Err(e) = expire_log(state, &job).await {
+            warn!("Failed to delete logs of job {}: {}", job.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Run `sweep` every `interval_secs`
+pub async fn cleanup_loop(state: AppState, interval_secs: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep(&state).await {
+            warn!("Job cleanup pass failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JobRepository;
+    use crate::models::UploadState;
+
+    fn job_using(upload_id: &str, job_type: JobType, status: JobStatus) -> Job {
+        Job {
+            job_type,
+            files_id: Some(upload_id.to_string()),
+            ..Job::sample(&JobRepository::generate_id(), "default", status)
+        }
+    }
+
+    async fn state_with_upload(uploads: &std::path::Path, upload_id: &str) -> AppState {
+        let state = AppState {
+            upload_config: crate::models::UploadConfig {
+                upload_dir: uploads.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            ..crate::test_state().await
+        };
+        std::fs::create_dir(uploads.join(upload_id)).unwrap();
+        state.upload_repo.create(upload_id, "default").await.unwrap();
+        state.upload_repo.finalize(upload_id, 1, 1).await.unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn test_cleaning_job_expires_its_upload() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let state = state_with_upload(uploads.path(), "upload_clean1").await;
+        let job = job_using("upload_clean1", JobType::Worker, JobStatus::Completed);
+        state.job_repo.create(&job, None).await.unwrap();
+
+        assert!(clean_job(&state, &job).await.unwrap());
+
+        let cleaned = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(cleaned.status, JobStatus::Cleaned);
+        let upload = state.upload_repo.get("upload_clean1").await.unwrap().unwrap();
+        assert_eq!(upload.state, UploadState::Expired);
+        assert!(!uploads.path().join("upload_clean1").exists());
+
+        // Already cleaned jobs are left alone
+        assert!(!clean_job(&state, &cleaned).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_left_cleaning_are_cleaned_again() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let state = state_with_upload(uploads.path(), "upload_stuck1").await;
+        let job = job_using("upload_stuck1", JobType::Worker, JobStatus::Completed);
+        state.job_repo.create(&job, None).await.unwrap();
+        // An earlier pass failed after marking it
+        state.job_repo.update_status(&job.id, JobStatus::Completed).await.unwrap();
+        state.job_repo.update_status(&job.id, JobStatus::Cleaning).await.unwrap();
+
+        let cleanable = state.job_repo.get_cleanable(Utc::now()).await.unwrap();
+        let stuck = cleanable.iter().find(|j| j.id == job.id).unwrap();
+        assert!(clean_job(&state, stuck).await.unwrap());
+
+        let cleaned = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(cleaned.status, JobStatus::Cleaned);
+        let upload = state.upload_repo.get("upload_stuck1").await.unwrap().unwrap();
+        assert_eq!(upload.state, UploadState::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_shared_worker_upload_kept_while_in_use() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let state = state_with_upload(uploads.path(), "upload_shared1").await;
+        let done = job_using("upload_shared1", JobType::Worker, JobStatus::Completed);
+        let running = job_using("upload_shared1", JobType::Worker, JobStatus::Running);
+        state.job_repo.create(&done, None).await.unwrap();
+        state.job_repo.create(&running, None).await.unwrap();
+
+        assert!(clean_job(&state, &done).await.unwrap());
+        let upload = state.upload_repo.get("upload_shared1").await.unwrap().unwrap();
+        assert_eq!(upload.state, UploadState::Finalized);
+        assert!(uploads.path().join("upload_shared1").exists());
+
+        // An agent's upload was writable, so it goes regardless
+        let agent = job_using("upload_shared1", JobType::Agent, JobStatus::Failed);
+        state.job_repo.create(&agent, None).await.unwrap();
+        assert!(clean_job(&state, &agent).await.unwrap());
+        let upload = state.upload_repo.get("upload_shared1").await.unwrap().unwrap();
+        assert_eq!(upload.state, UploadState::Expired);
+        assert!(!uploads.path().join("upload_shared1").exists());
+    }
+
+    #[tokio::test]
+    async fn test_reaper_removes_finished_containers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let list = serde_json::json!([
+            {"Id": "ctr_done", "State": "exited", "Labels": {"flashpods-job-id": "job_reap_done"}},
+            {"Id": "ctr_live", "State": "running", "Labels": {"flashpods-job-id": "job_reap_live"}},
+            {"Id": "ctr_other", "State": "exited", "Labels": {"flashpods-job-id": "job_unknown"}}
+        ]);
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                &format!("case \"$1\" in ps) echo '{}' ;; esac", list),
+            )),
+            ..crate::test_state().await
+        };
+        assert!(!state.job_config.container_autoremove);
+        for (id, status) in [
+            ("job_reap_done", JobStatus::Completed),
+            ("job_reap_live", JobStatus::Running),
+        ] {
+            let job = Job {
+                id: id.to_string(),
+                ..job_using("upload_reap", JobType::Worker, status)
+            };
+            state.job_repo.create(&job, None).await.unwrap();
+        }
+
+        sweep(&state).await.unwrap();
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        let removed: Vec<&str> = calls.lines().filter(|l| l.starts_with("rm ")).collect();
+        assert_eq!(removed, ["rm -f ctr_done"]);
+
+        // With autoremove on, podman removes containers itself
+        std::fs::remove_file(dir.path().join("calls.log")).unwrap();
+        let state = AppState {
+            job_config: crate::models::JobConfig {
+                container_autoremove: true,
+                ..state.job_config.clone()
+            },
+            ..state
+        };
+        sweep(&state).await.unwrap();
+        assert!(!dir.path().join("calls.log").exists());
+    }
+
+    /// A finished job with a captured log, both dated now
+    async fn finished_with_log(state: &AppState, status: JobStatus) -> Job {
+        let job = job_using("upload_ret1", JobType::Worker, JobStatus::Running);
+        state.job_repo.create(&job, None).await.unwrap();
+        state.job_repo.set_log_created_at(&job.id, Utc::now()).await.unwrap();
+        state.job_repo.update_status(&job.id, status).await.unwrap();
+        let log = crate::logs::log_path(&state.job_config.logs_dir, &job.id);
+        std::fs::write(log, "out\n").unwrap();
+        job
+    }
+
+    fn with_retention(state: AppState, artifact_minutes: i64, log_minutes: i64) -> AppState {
+        AppState {
+            job_config: crate::models::JobConfig {
+                artifact_retention_minutes: artifact_minutes,
+                log_retention_minutes: log_minutes,
+                ..state.job_config.clone()
+            },
+            ..state
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logs_and_artifacts_expire_independently() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = state_with_upload(dir.path(), "upload_ret1").await;
+        let state = AppState {
+            job_config: crate::models::JobConfig {
+                logs_dir: dir.path().join("logs").display().to_string(),
+                ..Default::default()
+            },
+            ..state
+        };
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        let log_of = |job: &Job| crate::logs::log_path(&state.job_config.logs_dir, &job.id);
+
+        // Artifacts expired, logs still retained
+        let job = finished_with_log(&state, JobStatus::Completed).await;
+        sweep(&with_retention(state.clone(), 0, 60)).await.unwrap();
+        let swept = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(swept.status, JobStatus::Cleaned);
+        assert!(swept.logs_deleted_at.is_none());
+        assert!(log_of(&job).exists());
+
+        // Once their own retention ends, the logs go too
+        sweep(&with_retention(state.clone(), 0, 0)).await.unwrap();
+        let swept = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert!(swept.logs_deleted_at.is_some());
+        assert!(!log_of(&job).exists());
+
+        // The other way round: logs expire first, artifacts are kept
+        let job = finished_with_log(&state, JobStatus::Failed).await;
+        sweep(&with_retention(state.clone(), 120, 0)).await.unwrap();
+        let swept = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(swept.status, JobStatus::Failed);
+        assert!(swept.logs_deleted_at.is_some());
+        assert!(!log_of(&job).exists());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_records_costs_of_finished_jobs() {
+        let state = crate::test_state().await;
+        let job = Job {
+            files_id: None,
+            cpus: 2,
+            memory_gb: 4,
+            ..job_using("", JobType::Worker, JobStatus::Pending)
+        };
+        state.job_repo.create(&job, None).await.unwrap();
+        state.job_repo.update_status(&job.id, JobStatus::Running).await.unwrap();
+        state.job_repo.update_status(&job.id, JobStatus::Completed).await.unwrap();
+        // Ran for half an hour
+        sqlx::query("UPDATE jobs SET started_at = ?, completed_at = ? WHERE id = ?")
+            .bind((Utc::now() - Duration::minutes(30)).to_rfc3339())
+            .bind(Utc::now().to_rfc3339())
+            .bind(&job.id)
+            .execute(state.db.inner())
+            .await
+            .unwrap();
+
+        let still_running = Job {
+            id: JobRepository::generate_id(),
+            ..job.clone()
+        };
+        state.job_repo.create(&still_running, None).await.unwrap();
+        state.job_repo.update_status(&still_running.id, JobStatus::Running).await.unwrap();
+
+        sweep(&state).await.unwrap();
+        let costed = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert!((costed.cost_units.unwrap() - 4.0).abs() < 0.01);
+        let running = state.job_repo.get(&still_running.id).await.unwrap().unwrap();
+        assert_eq!(running.cost_units, None);
+
+        // Recorded once
+        assert_eq!(record_costs(&state).await.unwrap(), 0);
+    }
+}
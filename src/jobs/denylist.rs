@@ -0,0 +1,79 @@
+use regex::Regex;
+
+/// Operator-configured commands that worker jobs may not run.
+///
+/// Each pattern is a plain substring, or a regex when prefixed with `re:`.
+/// This is defense in depth on shared runners; container isolation remains
+/// the real boundary.
+#[derive(Debug, Default)]
+pub struct CommandDenylist {
+    patterns: Vec<(String, Matcher)>,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl CommandDenylist {
+    /// Compile `patterns`, failing on the first invalid regex
+    pub fn new(patterns: &[String]) -> Result<Self, regex::Error> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let matcher = match pattern.strip_prefix("re:") {
+                    Some(re) => Matcher::Regex(Regex::new(re)?),
+                    None => Matcher::Substring(pattern.clone()),
+                };
+                Ok((pattern.clone(), matcher))
+            })
+            .collect::<Result<_, regex::Error>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// The first pattern matching `command`, if any
+    pub fn find_match(&self, command: &str) -> Option<&str> {
+        self.patterns
+            .iter()
+            .find(|(_, matcher)| match matcher {
+                Matcher::Substring(s) => command.contains(s.as_str()),
+                Matcher::Regex(re) => re.is_match(command),
+            })
+            .map(|(pattern, _)| pattern.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denylist(patterns: &[&str]) -> CommandDenylist {
+        let patterns: Vec<String> = patterns.iter().map(|s| s.to_string()).collect();
+        CommandDenylist::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn test_substring_and_regex_matches() {
+        let denylist = denylist(&["rm -rf /", r"re:\bmkfs(\.\w+)?\b"]);
+
+        assert_eq!(denylist.find_match("cd /tmp && rm -rf / "), Some("rm -rf /"));
+        assert_eq!(
+            denylist.find_match("mkfs.ext4 /dev/sda1"),
+            Some(r"re:\bmkfs(\.\w+)?\b")
+        );
+        assert_eq!(denylist.find_match("cargo build --release"), None);
+        assert_eq!(denylist.find_match("rm -rf ./target"), None);
+    }
+
+    #[test]
+    fn test_empty_denylist_allows_everything() {
+        assert_eq!(CommandDenylist::default().find_match("rm -rf /"), None);
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        assert!(CommandDenylist::new(&["re:(".to_string()]).is_err());
+    }
+}
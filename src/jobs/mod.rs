@@ -1,59 +1,178 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use chrono::Utc;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::time::Duration;
 
-use crate::db::JobRepository;
+use crate::artifacts::{collect_artifacts, sha256_file, validate_artifact_name};
+use crate::db::{JobRepository, ResourceUsage};
+use crate::middleware::{Caller, RequestId};
 use crate::models::{
-    CreateJobRequest, CreateJobResponse, Job, JobResponse, JobStatus, JobType, ResourceLimits,
+    ArtifactInfo, CreateJobRequest, CreateJobResponse, FailureCategory, InvalidEnumValue, Job, JobConfig, JobResponse, JobStatus,
+    AdminJobResponse, JobSpec, JobSummary, JobType, ResourceLimits, SecretRef, SubmissionSource,
+    JOB_SPEC_VERSION,
 };
-use crate::podman::{ContainerConfig, PodmanService};
+use crate::podman::ContainerConfig;
 use crate::AppState;
 
+mod access;
+mod bulk_stop;
+mod cleanup;
+mod denylist;
+mod pipeline;
+mod rate_limit;
+mod reconcile;
+mod scheduler;
+mod startup;
+mod timeline;
+
+pub use access::{authorize_job_access, JobAccess};
+pub use bulk_stop::{stop_active_jobs, StopOrder};
+pub use cleanup::cleanup_loop;
+pub use denylist::CommandDenylist;
+pub use pipeline::PipelineGate;
+pub use rate_limit::SubmissionLimiter;
+pub use reconcile::reconcile_after_restart;
+pub use scheduler::scheduler_loop;
+pub use startup::{CancelOutcome, StartupTracker};
+use startup::CancelOnDrop;
+use timeline::merge_timeline;
+
 pub fn routes() -> axum::Router<AppState> {
     axum::Router::new()
-        .route("/", axum::routing::post(create_job).get(list_jobs))
+        .route(
+            "/",
+            axum::routing::post(create_job)
+                .get(list_jobs)
+                .delete(kill_job_by_client_id),
+        )
+        .route("/can-admit", axum::routing::get(can_admit))
         .route("/:id", axum::routing::get(get_job).delete(kill_job))
+        .route("/:id/admin", axum::routing::get(get_job_admin))
+        .route("/:id/export", axum::routing::get(export_job))
         .route("/:id/output", axum::routing::get(get_output))
+        .route("/:id/output/stream", axum::routing::get(stream_output))
+        .route("/:id/timeline", axum::routing::get(get_timeline))
+        .route("/:id/env", axum::routing::get(get_env))
         .route("/:id/artifacts", axum::routing::get(list_artifacts))
+        .route("/:id/artifacts/:name/info", axum::routing::get(get_artifact_info))
+}
+
+#[derive(serde::Deserialize)]
+struct CreateJobQuery {
+    #[serde(default)]
+    validate_upload: bool,
 }
 
-/// POST /jobs - Create a new job
+/// POST /jobs - Create a new job, or with `?validate_upload=true` only
+/// report whether its upload is ready
 async fn create_job(
     State(state): State<AppState>,
+    caller: Caller,
+    request_id: RequestId,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    params: Result<
+        axum::extract::Query<CreateJobQuery>,
+        axum::extract::rejection::QueryRejection,
+    >,
     Json(req): Json<CreateJobRequest>,
-) -> impl IntoResponse {
-    // Parse job type
-    let job_type: JobType = match req.job_type.parse() {
-        Ok(t) => t,
-        Err(e) => {
-            return Err((
+) -> axum::response::Response {
+    let params = match params {
+        Ok(axum::extract::Query(params)) => params,
+        Err(rejection) => {
+            return (
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({
-                    "error": "invalid_job_type",
-                    "message": e
+                    "error": "invalid_query",
+                    "message": rejection.body_text()
                 })),
-            ));
+            )
+                .into_response();
         }
     };
+    if params.validate_upload {
+        return validate_upload(&state, &req).await.into_response();
+    }
+    submit_job(state, caller, request_id, connect_info, headers, req)
+        .await
+        .into_response()
+}
+
+async fn submit_job(
+    state: AppState,
+    caller: Caller,
+    request_id: RequestId,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    req: CreateJobRequest,
+) -> impl IntoResponse {
+    if state.job_config.strict_requests && !req.unknown_fields.is_empty() {
+        let fields: Vec<&str> = req.unknown_fields.keys().map(String::as_str).collect();
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "unknown_fields",
+                "message": format!("Unknown fields: {}", fields.join(", ")),
+                "fields": fields
+            })),
+        ));
+    }
+
+    if let Some(version) = req.spec_version.filter(|v| *v != JOB_SPEC_VERSION) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "unsupported_spec_version",
+                "message": format!(
+                    "Job spec version {} is not supported (expected {})",
+                    version, JOB_SPEC_VERSION
+                )
+            })),
+        ));
+    }
+
+    // Parse job type
+    let job_type: JobType = req.job_type.parse().map_err(invalid_enum_value)?;
 
     // Validate required fields based on job type
     match job_type {
         JobType::Worker => {
-            if req.command.is_none() {
+            if let Err((code, message)) = check_worker_commands(&req) {
                 return Err((
                     StatusCode::BAD_REQUEST,
                     Json(serde_json::json!({
-                        "error": "missing_command",
-                        "message": "Worker jobs require a 'command' field"
+                        "error": code,
+                        "message": message
                     })),
                 ));
             }
         }
         JobType::Agent => {
+            if req.restartable {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "restartable_not_supported",
+                        "message": "Only worker jobs can be restartable"
+                    })),
+                ));
+            }
+            // An agent's work is only handed back through /artifacts
+            if req.ephemeral {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "ephemeral_not_supported",
+                        "message": "Only worker jobs can be ephemeral"
+                    })),
+                ));
+            }
             if req.task.is_none() {
                 return Err((
                     StatusCode::BAD_REQUEST,
@@ -66,6 +185,76 @@ async fn create_job(
         }
     }
 
+    // Refuse commands the operator has ruled out on this runner
+    let commands = req.command.iter().chain(req.commands.iter().flatten());
+    if let Some(pattern) = commands
+        .filter_map(|c| state.command_denylist.find_match(c))
+        .next()
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "command_forbidden",
+                "message": format!("Command matches forbidden pattern {:?}", pattern)
+            })),
+        ));
+    }
+
+    // Bound client-supplied metadata before it reaches podman args or the DB
+    for (kind, map) in [("labels", &req.labels), ("annotations", &req.annotations)] {
+        if let Err((code, message)) = check_metadata_limits(kind, map, &state.job_config) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": code,
+                    "message": message
+                })),
+            ));
+        }
+    }
+
+    if let Err(message) = check_secret_refs(&req.secret_refs) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_secret_ref",
+                "message": message
+            })),
+        ));
+    }
+
+    let artifact_paths = check_artifact_paths(&req.artifact_paths).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_artifact_paths",
+                "message": message
+            })),
+        )
+    })?;
+
+    let success_exit_codes = check_success_exit_codes(&req.success_exit_codes).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_success_exit_codes",
+                "message": message
+            })),
+        )
+    })?;
+
+    let unmatched = unmatched_node_selector(&req.node_selector, &state.job_config.node_labels);
+    if !unmatched.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "node_selector_mismatch",
+                "message": format!("No node matches selector keys: {}", unmatched.join(", ")),
+                "unmatched": unmatched
+            })),
+        ));
+    }
+
     // Check idempotency key
     if let Some(ref client_job_id) = req.client_job_id {
         if let Ok(Some(existing_job)) = state.job_repo.get_by_client_id(client_job_id).await {
@@ -86,68 +275,117 @@ async fn create_job(
 
     // Validate upload if files_id provided
     if let Some(ref files_id) = req.files_id {
-        match state.upload_repo.get(files_id).await {
-            Ok(Some(upload)) => {
-                if upload.state != crate::models::UploadState::Finalized {
-                    return Err((
-                        StatusCode::CONFLICT,
-                        Json(serde_json::json!({
-                            "error": "upload_not_finalized",
-                            "message": format!("Upload {} is in {} state, must be finalized", files_id, upload.state)
-                        })),
-                    ));
-                }
-            }
-            Ok(None) => {
-                return Err((
-                    StatusCode::NOT_FOUND,
-                    Json(serde_json::json!({
-                        "error": "upload_not_found",
-                        "message": format!("Upload {} not found", files_id)
-                    })),
-                ));
-            }
-            Err(e) => {
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({
-                        "error": "database_error",
-                        "message": e.to_string()
-                    })),
-                ));
-            }
+        let assessment = assess_upload(&state, files_id)
+            .await
+            .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+        let require_on_disk = state.job_config.require_upload_on_disk;
+        if let Some((status, code, message)) = assessment.problem(files_id, job_type, require_on_disk) {
+            return Err((
+                status,
+                Json(serde_json::json!({
+                    "error": code,
+                    "message": message
+                })),
+            ));
         }
+        if !assessment.on_disk {
+            tracing::warn!(
+                "Upload {} has no directory on disk; job will see an empty /work",
+                files_id
+            );
+        }
+    }
+
+    // Validate the upstream job whose artifacts become this job's input
+    if let Some(ref input_job_id) = req.input_from_job {
+        check_input_job(&state, input_job_id, &caller).await?;
     }
 
     // Clamp resource limits
     let limits = ResourceLimits::for_job_type(job_type);
     let (cpus, memory_gb, timeout_minutes) =
         limits.clamp(req.cpus, req.memory_gb, req.timeout_minutes);
+    if req.max_total_runtime_minutes.is_some_and(|max| max < 1) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_max_total_runtime",
+                "message": "max_total_runtime_minutes must be at least 1"
+            })),
+        ));
+    }
+    if let Some(shm_size_mb) = req.shm_size_mb {
+        if let Err(message) = check_shm_size(shm_size_mb, memory_gb, state.job_config.max_shm_size_mb) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "invalid_shm_size",
+                    "message": message
+                })),
+            ));
+        }
+    }
+    if req.promote_log_to_artifact {
+        let unavailable = if req.ephemeral {
+            Some("Ephemeral jobs keep no artifacts")
+        } else if !state.job_config.capture_logs {
+            Some("Log capture is disabled on this server")
+        } else {
+            None
+        };
+        if let Some(reason) = unavailable {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "log_promotion_unavailable",
+                    "message": format!("Can't promote the log to an artifact: {}", reason)
+                })),
+            ));
+        }
+    }
+    if let Err(message) = check_image_requirements(&state, &req.image, cpus, memory_gb).await {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "insufficient_resources_for_image",
+                "message": message
+            })),
+        ));
+    }
+
+    // Agents are heavier than workers and have their own cap
+    if job_type == JobType::Agent {
+        check_agent_limit(&state).await?;
+    }
+
+    // Delayed jobs wait in Pending; the scheduler checks resources when they're due
+    let scheduled_for = req.start_after.filter(|start_after| *start_after > Utc::now());
+    if let Some(start_after) = scheduled_for {
+        let latest = Utc::now() + chrono::Duration::minutes(state.job_config.max_start_delay_minutes);
+        if start_after > latest {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "invalid_start_after",
+                    "message": format!(
+                        "start_after may be at most {} minutes in the future",
+                        state.job_config.max_start_delay_minutes
+                    )
+                })),
+            ));
+        }
+    }
 
     // Check resource availability
     match state.job_repo.get_resource_usage().await {
+        Ok(_) if scheduled_for.is_some() => {}
         Ok(usage) => {
-            // Simple admission control: reject if adding this job would exceed limits
-            // In production, you'd want configurable limits
-            let max_cpus = 16;
-            let max_memory_gb = 32;
-
-            if usage.used_cpus + cpus > max_cpus {
+            if let Err(message) = check_capacity(&usage, cpus, memory_gb) {
                 return Err((
                     StatusCode::TOO_MANY_REQUESTS,
                     Json(serde_json::json!({
                         "error": "resource_exhausted",
-                        "message": format!("Insufficient CPU: {} used, {} requested, {} max", usage.used_cpus, cpus, max_cpus)
-                    })),
-                ));
-            }
-
-            if usage.used_memory_gb + memory_gb > max_memory_gb {
-                return Err((
-                    StatusCode::TOO_MANY_REQUESTS,
-                    Json(serde_json::json!({
-                        "error": "resource_exhausted",
-                        "message": format!("Insufficient memory: {}GB used, {}GB requested, {}GB max", usage.used_memory_gb, memory_gb, max_memory_gb)
+                        "message": message
                     })),
                 ));
             }
@@ -161,26 +399,70 @@ async fn create_job(
     let job_id = JobRepository::generate_id();
     let job = Job {
         id: job_id.clone(),
-        user_id: "default".to_string(),
+        user_id: caller.user_id.clone(),
         job_type,
         status: JobStatus::Pending,
         command: req.command.clone(),
+        commands: req.commands.clone(),
         task: req.task.clone(),
         context: req.context.clone(),
         git_branch: req.git_branch.clone(),
         files_id: req.files_id.clone(),
+        input_from_job: req.input_from_job.clone(),
         image: req.image.clone(),
         cpus,
         memory_gb,
         timeout_minutes,
+        restartable: req.restartable,
+        start_after: req.start_after,
+        secret_refs: req.secret_refs.clone(),
+        max_total_runtime_minutes: req.max_total_runtime_minutes,
+        node_selector: req.node_selector.clone(),
+        artifact_paths,
+        ephemeral: req.ephemeral,
+        success_exit_codes,
+        shm_size_mb: req.shm_size_mb,
+        promote_log_to_artifact: req.promote_log_to_artifact,
         container_id: None,
         exit_code: None,
         error: None,
+        runtime_seconds: 0,
+        cost_units: None,
+        failure_category: None,
+        log_created_at: None,
+        logs_deleted_at: None,
         created_at: Utc::now(),
         started_at: None,
         completed_at: None,
+        pending_reason: None,
+        labels: req.labels.clone(),
+        annotations: req.annotations.clone(),
+        submission: SubmissionSource {
+            client_ip: connect_info.map(|ConnectInfo(addr)| addr.ip().to_string()),
+            user_agent: headers
+                .get(axum::http::header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from),
+            request_id: Some(request_id.0).filter(|id| !id.is_empty()),
+        },
     };
 
+    // Only submissions that would create a job count: rejected requests and
+    // idempotent replays never reach podman
+    if let Err(retry_after) = state.submission_limiter.check(&caller.user_id) {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "error": "job_rate_limited",
+                "message": format!(
+                    "Too many job submissions: limit is {} per minute",
+                    state.submission_limiter.max_per_minute()
+                ),
+                "retry_after_seconds": retry_after.as_secs().max(1)
+            })),
+        ));
+    }
+
     // Save to database
     let job = match state
         .job_repo
@@ -189,48 +471,176 @@ async fn create_job(
     {
         Ok(j) => j,
         Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "database_error",
-                    "message": format!("Failed to create job: {}", e)
-                })),
+            return Err(crate::db::database_error_response(
+                &e,
+                format!("Failed to create job: {}", e),
             ));
         }
     };
 
-    // Start container
-    // First update status to starting
-    if let Err(e) = state.job_repo.update_status(&job.id, JobStatus::Starting).await {
-        tracing::warn!("Failed to update status to starting: {}", e);
+    if let Some(start_after) = scheduled_for {
+        scheduler::update_pending_reason(&state, &job).await;
+        return Ok((
+            StatusCode::CREATED,
+            Json(CreateJobResponse {
+                job_id: job.id,
+                status: JobStatus::Pending,
+                created: true,
+                message: Some(format!("Scheduled to start after {}", start_after.to_rfc3339())),
+            }),
+        ));
+    }
+
+    // Start container. The startup tracker lets a concurrent DELETE cancel the
+    // job at any point in this window without leaving a container behind.
+    state.startups.begin(&job.id);
+
+    // Startup runs on its own task so it always reaches a consistent end. If
+    // this request is dropped (client went away) or passes its deadline, the
+    // guard cancels the startup and the task stops any container it created.
+    let guard = CancelOnDrop::new(state.startups.clone(), job.id.clone());
+    let startup = tokio::spawn(start_job(state.clone(), job));
+    let result = match request_deadline(&headers) {
+        Some(deadline) => match tokio::time::timeout(deadline, startup).await {
+            Ok(result) => result,
+            Err(_) => {
+                return Err((
+                    StatusCode::GATEWAY_TIMEOUT,
+                    Json(serde_json::json!({
+                        "error": "request_timeout",
+                        "message": "Job did not start before the request deadline and was cancelled"
+                    })),
+                ));
+            }
+        },
+        None => startup.await,
+    };
+    guard.disarm();
+
+    result.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "internal_error",
+                "message": format!("Job startup task failed: {}", e)
+            })),
+        )
+    })?
+}
+
+/// Client-supplied deadline for the request, from `X-Request-Timeout` (seconds)
+fn request_deadline(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("x-request-timeout")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Drive a created job from `Pending` to `Running`, honouring cancellation
+/// requested through the startup tracker at any point along the way
+async fn start_job(
+    state: AppState,
+    job: Job,
+) -> Result<(StatusCode, Json<CreateJobResponse>), (StatusCode, Json<serde_json::Value>)> {
+    match state
+        .job_repo
+        .transition_status(&job.id, &[JobStatus::Pending], JobStatus::Starting)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            state.startups.finish(&job.id);
+            return Ok(cancelled_before_start(job.id));
+        }
+        Err(e) => tracing::warn!("Failed to update status to starting: {}", e),
+    }
+
+    // Held until the container is up; cancels that arrive while queued are
+    // caught by the check below
+    let _slot = state.pipeline.enter().await;
+
+    if state.startups.is_cancelled(&job.id) {
+        state.startups.finish(&job.id);
+        mark_cancelled(&state, &job.id).await;
+        return Ok(cancelled_before_start(job.id));
     }
 
-    match start_container(&state, &job) {
+    let started = {
+        let (state, job) = (state.clone(), job.clone());
+        tokio::task::spawn_blocking(move || start_container(&state, &job))
+            .await
+            .map_err(|e| crate::podman::PodmanError::Command(e.to_string()))
+            .and_then(|result| result)
+    };
+
+    match started {
         Ok(container_id) => {
             // Update job with container ID and status
             if let Err(e) = state.job_repo.set_container_id(&job.id, &container_id).await {
                 tracing::error!("Failed to set container ID: {}", e);
             }
-            if let Err(e) = state.job_repo.update_status(&job.id, JobStatus::Running).await {
-                tracing::error!("Failed to update job status: {}", e);
+
+            if let Some(exit_code) = exited_during_startup(&state, &job, &container_id).await {
+                state.startups.finish(&job.id);
+                return Ok(fail_instant_exit(&state, job.id, exit_code).await);
+            }
+
+            let still_wanted = state.startups.mark_started(&job.id, &container_id)
+                && match state
+                    .job_repo
+                    .transition_status(&job.id, &[JobStatus::Starting], JobStatus::Running)
+                    .await
+                {
+                    Ok(moved) => moved,
+                    Err(e) => {
+                        tracing::error!("Failed to update job status: {}", e);
+                        true
+                    }
+                };
+            state.startups.finish(&job.id);
+
+            if !still_wanted {
+                tracing::info!("Job {} cancelled during startup, stopping container", job.id);
+                // The job is already cancelled; a failure here is logged by stop_or_kill
+                let grace_seconds = state.job_config.kill_grace_seconds;
+                let (stopper, container_id) = (state.clone(), container_id.clone());
+                let _ = tokio::task::spawn_blocking(move || stop_or_kill(&stopper, &container_id, grace_seconds))
+                    .await;
+                mark_cancelled(&state, &job.id).await;
+                return Ok(cancelled_before_start(job.id));
+            }
+
+            if state.job_config.capture_logs {
+                spawn_log_capture(&state, &job, container_id);
             }
         }
         Err(e) => {
+            state.startups.finish(&job.id);
             tracing::error!("Failed to start container: {}", e);
-            if let Err(err) = state
+            // A cancel that arrived while podman ran keeps the job cancelled
+            match state
                 .job_repo
-                .update_status(&job.id, JobStatus::Failed)
+                .transition_status(&job.id, &[JobStatus::Starting], JobStatus::Failed)
                 .await
             {
-                tracing::error!("Failed to update job status: {}", err);
-            }
-            if let Err(err) = state.job_repo.set_error(&job.id, &e.to_string()).await {
-                tracing::error!("Failed to set job error: {}", err);
+                Ok(true) => {
+                    if let Err(err) = state.job_repo.set_error(&job.id, &e.to_string()).await {
+                        tracing::error!("Failed to set job error: {}", err);
+                    }
+                    record_failure_category(&state, &job.id, start_failure_category(&e)).await;
+                }
+                Ok(false) => return Ok(cancelled_before_start(job.id)),
+                Err(err) => tracing::error!("Failed to update job status: {}", err),
             }
+            let (status, code) = start_error_status(&e);
             return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(serde_json::json!({
-                    "error": "container_start_failed",
+                    "error": code,
                     "message": e.to_string()
                 })),
             ));
@@ -248,177 +658,3736 @@ async fn create_job(
     ))
 }
 
-/// Start a container for a job
-fn start_container(state: &AppState, job: &Job) -> Result<String, crate::podman::PodmanError> {
-    let config = ContainerConfig {
-        job_id: job.id.clone(),
-        job_type: match job.job_type {
-            JobType::Worker => crate::podman::JobType::Worker,
-            JobType::Agent => crate::podman::JobType::Agent,
-        },
-        upload_id: job.files_id.clone().unwrap_or_default(),
-        image: job.image.clone(),
-        command: job.command.clone(),
-        cpus: job.cpus,
-        memory_gb: job.memory_gb,
-        task: job.task.clone(),
-        context: job.context.clone(),
-        git_branch: job.git_branch.clone(),
-    };
-
-    // Update status to starting
-    // Note: This is a sync wrapper - the caller handles async updates
-    state.podman.create_container(&config)
+/// HTTP status and error code for a container that failed to start: the
+/// caller's mistakes are 4xx, a host short on memory is retryable
+fn start_error_status(e: &crate::podman::PodmanError) -> (StatusCode, &'static str) {
+    use crate::podman::PodmanError;
+    match e {
+        PodmanError::ImageNotFound(_) => (StatusCode::BAD_REQUEST, "image_not_found"),
+        PodmanError::ImagePullUnauthorized(_) => (StatusCode::FORBIDDEN, "image_pull_unauthorized"),
+        PodmanError::InsufficientMemory(_) => (StatusCode::SERVICE_UNAVAILABLE, "insufficient_memory"),
+        PodmanError::NameInUse(_) => (StatusCode::CONFLICT, "container_name_in_use"),
+        PodmanError::InvalidMount(_) => (StatusCode::INTERNAL_SERVER_ERROR, "invalid_mount"),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "container_start_failed"),
+    }
 }
 
-/// GET /jobs - List jobs
-async fn list_jobs(
-    State(state): State<AppState>,
-    axum::extract::Query(params): axum::extract::Query<ListJobsQuery>,
-) -> impl IntoResponse {
-    let status_filter = params.status.as_deref();
-    let limit = params.limit.unwrap_or(20).min(100);
-
-    match state.job_repo.list(status_filter, limit).await {
-        Ok(jobs) => {
-            let job_responses: Vec<JobResponse> = jobs.into_iter().map(JobResponse::from).collect();
-            Ok(Json(serde_json::json!({
-                "jobs": job_responses,
-                "total": job_responses.len()
-            })))
-        }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "database_error",
-                "message": e.to_string()
-            })),
-        )),
+/// Why a container failed to start, for dead-lettering: problems with the
+/// job itself are permanent, a busy host isn't
+fn start_failure_category(e: &crate::podman::PodmanError) -> FailureCategory {
+    use crate::podman::PodmanError;
+    match e {
+        PodmanError::ImageNotFound(_) | PodmanError::ImagePullUnauthorized(_) => FailureCategory::Image,
+        PodmanError::Secret(_) => FailureCategory::Secret,
+        PodmanError::InvalidMount(_) => FailureCategory::Mount,
+        PodmanError::InsufficientMemory(_) => FailureCategory::Resources,
+        _ => FailureCategory::Start,
     }
 }
 
-#[derive(serde::Deserialize)]
-struct ListJobsQuery {
-    status: Option<String>,
-    limit: Option<i32>,
+/// Best-effort: a job whose category couldn't be stored is still failed
+async fn record_failure_category(state: &AppState, job_id: &str, category: FailureCategory) {
+    if let Err(e) = state.job_repo.set_failure_category(job_id, category).await {
+        tracing::error!("Failed to set failure category: {}", e);
+    }
 }
 
-/// GET /jobs/:id - Get job details
-async fn get_job(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> impl IntoResponse {
-    match state.job_repo.get(&id).await {
-        Ok(Some(job)) => Ok(Json(JobResponse::from(job))),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "error": "job_not_found",
-                "message": format!("Job {} not found", id)
-            })),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "database_error",
-                "message": e.to_string()
-            })),
-        )),
+/// Wait `startup_confirm_ms` and re-inspect a freshly started container.
+/// Returns its exit code if it has already exited with a code outside the
+/// job's `success_exit_codes`. A container already removed by `--rm` has
+/// exited too; its code is read from podman's events.
+///
+/// Best-effort: a container that exited successfully, or whose state or
+/// exit code can't be read, is left to the normal completion path.
+async fn exited_during_startup(state: &AppState, job: &Job, container_id: &str) -> Option<i32> {
+    let wait_ms = state.job_config.startup_confirm_ms;
+    if wait_ms == 0 {
+        return None;
+    }
+    tokio::time::sleep(Duration::from_millis(wait_ms)).await;
+
+    let podman = state.podman.clone();
+    let (job_id, container_id) = (job.id.clone(), container_id.to_string());
+    match tokio::task::spawn_blocking(move || podman.inspect_job_container(&job_id, &container_id)).await {
+        Ok(Ok(Some(info))) if info.state == crate::podman::ContainerState::Exited => info
+            .exit_code
+            .filter(|code| job.status_for_exit(*code) == JobStatus::Failed),
+        Ok(Ok(None)) => removed_container_exit(state, job)
+            .await
+            .filter(|code| job.status_for_exit(*code) == JobStatus::Failed),
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => {
+            tracing::debug!("Skipping startup confirmation: {}", e);
+            None
+        }
+        Err(e) => {
+            tracing::error!("Startup confirmation task failed: {}", e);
+            None
+        }
     }
 }
 
-/// DELETE /jobs/:id - Kill a job
-async fn kill_job(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> impl IntoResponse {
-    // Get job
-    let job = match state.job_repo.get(&id).await {
-        Ok(Some(j)) => j,
-        Ok(None) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "job_not_found",
-                    "message": format!("Job {} not found", id)
-                })),
-            ));
+/// Exit code of a job's container that is already gone, from the last
+/// `died` event podman logged for it
+async fn removed_container_exit(state: &AppState, job: &Job) -> Option<i32> {
+    let podman = state.podman.clone();
+    let (job_id, since) = (job.id.clone(), job.created_at);
+    let events = match tokio::task::spawn_blocking(move || podman.container_events(&job_id, since)).await {
+        Ok(Ok(events)) => events,
+        Ok(Err(e)) => {
+            tracing::debug!("No exit code for removed container of job {}: {}", job.id, e);
+            return None;
         }
         Err(e) => {
-            return Err((
+            tracing::error!("Container events task failed: {}", e);
+            return None;
+        }
+    };
+    events
+        .iter()
+        .rev()
+        .filter(|event| event.event == "died")
+        .find_map(|event| event.detail.as_deref()?.strip_prefix("exit code ")?.parse().ok())
+}
+
+/// Fail a job whose container crashed before it was marked running
+async fn fail_instant_exit(
+    state: &AppState,
+    job_id: String,
+    exit_code: i32,
+) -> (StatusCode, Json<CreateJobResponse>) {
+    match state
+        .job_repo
+        .transition_status(&job_id, &[JobStatus::Starting], JobStatus::Failed)
+        .await
+    {
+        Ok(true) => {}
+        // Cancelled while we waited; the container is gone either way
+        Ok(false) => return cancelled_before_start(job_id),
+        Err(e) => tracing::error!("Failed to update job status: {}", e),
+    }
+
+    let message = format!("Container exited during startup with exit code {}", exit_code);
+    tracing::warn!("Job {}: {}", job_id, message);
+    if let Err(e) = state.job_repo.set_exit_code(&job_id, exit_code).await {
+        tracing::error!("Failed to set exit code: {}", e);
+    }
+    if let Err(e) = state.job_repo.set_error(&job_id, &message).await {
+        tracing::error!("Failed to set job error: {}", e);
+    }
+    record_failure_category(state, &job_id, FailureCategory::ExitCode).await;
+
+    (
+        StatusCode::CREATED,
+        Json(CreateJobResponse {
+            job_id,
+            status: JobStatus::Failed,
+            created: true,
+            message: Some(message),
+        }),
+    )
+}
+
+/// Mirror a started container's output into its log file in the background.
+/// When every background process slot is taken the capture waits for one;
+/// `podman logs -f` replays the output from the start, so nothing is lost
+/// unless an autoremoved container exits and is removed in the meantime.
+/// Once the container exits the log is promoted, if the job asked for it.
+pub(crate) fn spawn_log_capture(state: &AppState, job: &Job, container_id: String) {
+    let podman = state.podman.clone();
+    let processes = state.processes.clone();
+    let path = crate::logs::log_path(&state.job_config.logs_dir, &job.id);
+    let max_bytes = state.job_config.max_log_bytes;
+    let autoremove = state.job_config.container_autoremove;
+    let job_id = job.id.clone();
+    let job_repo = state.job_repo.clone();
+    let promote = job.promote_log_to_artifact.then(|| (state.clone(), job.clone()));
+    tokio::spawn(async move {
+        // Log retention counts from here
+        if let Err(e) = job_repo.set_log_created_at(&job_id, Utc::now()).await {
+            tracing::warn!("Failed to record log creation for job {}: {}", job_id, e);
+        }
+        let slot = match processes.try_acquire() {
+            Ok(slot) => slot,
+            Err(e) => {
+                if autoremove {
+                    tracing::warn!(
+                        "Log capture for job {} is queued ({}); its output is lost if the container exits and is removed first",
+                        job_id,
+                        e
+                    );
+                }
+                processes.acquire().await
+            }
+        };
+        match crate::logs::capture_logs(&podman, &container_id, &path, max_bytes, slot).await {
+            Ok(summary) if summary.truncated => {
+                tracing::warn!("Log for job {} truncated at {} bytes", job_id, max_bytes)
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Log capture for job {} failed: {}", job_id, e);
+                return;
+            }
+        }
+        if let Some((state, job)) = promote {
+            promote_job_log(&state, &job).await;
+        }
+    });
+}
+
+/// Copy a job's finished log out of its container's reach and record it
+/// among the job's artifacts. Runs once the log is complete: when its
+/// capture ends, or from reconciliation for a job that finished while the
+/// API was down. A job's own `job.log` keeps the plain name.
+pub(crate) async fn promote_job_log(state: &AppState, job: &Job) {
+    let log = crate::logs::log_path(&state.job_config.logs_dir, &job.id);
+    let promoted = crate::logs::promoted_log_path(&state.job_config.logs_dir, &job.id);
+    let size = match crate::logs::promote_log(&log, &promoted).await {
+        Ok(Some(size)) => size,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to promote log of job {} to an artifact: {}", job.id, e);
+            return;
+        }
+    };
+    let outputs = match collect_job_outputs(state, job).await {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            tracing::warn!("Failed to read artifacts of job {} to name its log: {}", job.id, e);
+            return;
+        }
+    };
+    let path = promoted.to_string_lossy();
+    let mut name = crate::logs::PROMOTED_LOG_ARTIFACT.to_string();
+    if outputs.iter().any(|file| file.name == name) {
+        let max_len = state.job_config.max_artifact_name_len;
+        name = crate::artifacts::with_path_suffix(
+            &name,
+            &path,
+            max_len.clamp(16, crate::artifacts::MAX_ARTIFACT_NAME_LEN),
+        );
+    }
+    if let Err(e) = state
+        .artifact_repo
+        .create(&job.id, &name, &path, None, size as i64)
+        .await
+    {
+        tracing::warn!("Failed to record the promoted log of job {}: {}", job.id, e);
+    }
+}
+
+/// Record a cancellation that didn't come through `kill_job` (which sets the
+/// status itself), e.g. a dropped request.
+///
+/// The client never learned the outcome of a dropped request, so its
+/// `client_job_id` is freed: retrying with it starts a new job instead of
+/// returning the cancelled one.
+async fn mark_cancelled(state: &AppState, job_id: &str) {
+    if let Err(e) = state
+        .job_repo
+        .transition_status(job_id, &[JobStatus::Pending, JobStatus::Starting], JobStatus::Cancelled)
+        .await
+    {
+        tracing::error!("Failed to update job status: {}", e);
+    }
+    if state.startups.take_abandoned(job_id) {
+        if let Err(e) = state.job_repo.release_client_id(job_id).await {
+            tracing::error!("Failed to release idempotency key of job {}: {}", job_id, e);
+        }
+    }
+}
+
+/// Check a job's resources against the minimums its image declares in
+/// `flashpods.min-*` labels. Best-effort: images that aren't present
+/// locally or can't be inspected pass.
+async fn check_image_requirements(
+    state: &AppState,
+    image: &str,
+    cpus: i32,
+    memory_gb: i32,
+) -> Result<(), String> {
+    let podman = state.podman.clone();
+    let image = image.to_string();
+    let labels = match tokio::task::spawn_blocking(move || podman.image_labels(&image)).await {
+        Ok(Ok(Some(labels))) => labels,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(e)) => {
+            tracing::debug!("Skipping image requirement check: {}", e);
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Image inspect task failed: {}", e);
+            return Ok(());
+        }
+    };
+    crate::podman::ImageRequirements::from_labels(&labels).check(cpus, memory_gb)
+}
+
+// Simple admission control: reject if adding a job would exceed these.
+// In production, you'd want configurable limits
+pub(crate) const HOST_MAX_CPUS: i32 = 16;
+pub(crate) const HOST_MAX_MEMORY_GB: i32 = 32;
+
+/// Check that a job of the given size fits alongside current usage
+fn check_capacity(usage: &ResourceUsage, cpus: i32, memory_gb: i32) -> Result<(), String> {
+    if usage.used_cpus.checked_add(cpus).is_none_or(|total| total > HOST_MAX_CPUS) {
+        return Err(format!(
+            "Insufficient CPU: {} used, {} requested, {} max",
+            usage.used_cpus, cpus, HOST_MAX_CPUS
+        ));
+    }
+    if usage
+        .used_memory_gb
+        .checked_add(memory_gb)
+        .is_none_or(|total| total > HOST_MAX_MEMORY_GB)
+    {
+        return Err(format!(
+            "Insufficient memory: {}GB used, {}GB requested, {}GB max",
+            usage.used_memory_gb, memory_gb, HOST_MAX_MEMORY_GB
+        ));
+    }
+    Ok(())
+}
+
+#[derive(serde::Deserialize)]
+struct CanAdmitQuery {
+    #[serde(rename = "type")]
+    job_type: Option<String>,
+    cpus: Option<i32>,
+    memory_gb: Option<i32>,
+}
+
+/// GET /jobs/can-admit - Whether a job of the given size would pass
+/// admission right now. Nothing is reserved, so a later create can still
+/// be refused.
+async fn can_admit(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<CanAdmitQuery>,
+) -> impl IntoResponse {
+    let job_type: JobType = params
+        .job_type
+        .as_deref()
+        .unwrap_or("worker")
+        .parse()
+        .map_err(invalid_enum_value)?;
+    // Sized as `POST /jobs` would clamp the job
+    let (cpus, memory_gb, _) = ResourceLimits::for_job_type(job_type).clamp(
+        params.cpus.unwrap_or(2),
+        params.memory_gb.unwrap_or(4),
+        0,
+    );
+
+    let usage = state.job_repo.get_resource_usage().await.map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    let verdict = check_capacity(&usage, cpus, memory_gb);
+
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "admit": verdict.is_ok(),
+        "reason": verdict.err(),
+        "requested": { "cpus": cpus, "memory_gb": memory_gb },
+        "available": {
+            "cpus": (HOST_MAX_CPUS - usage.used_cpus).max(0),
+            "memory_gb": (HOST_MAX_MEMORY_GB - usage.used_memory_gb).max(0)
+        },
+        "host_capacity": { "cpus": HOST_MAX_CPUS, "memory_gb": HOST_MAX_MEMORY_GB },
+        "running_jobs": usage.running_jobs
+    })))
+}
+
+/// Reject a new agent job if the configured number of agents is already active
+async fn check_agent_limit(state: &AppState) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let Some(max_agents) = state.job_config.max_concurrent_agents else {
+        return Ok(());
+    };
+
+    match state.job_repo.count_active_by_type(JobType::Agent).await {
+        Ok(active) if active >= max_agents => Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "error": "agent_limit_reached",
+                "message": format!("{} agent jobs active, at most {} allowed", active, max_agents)
+            })),
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to count active agents: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Enforce the configured count and total-size caps on a job's labels or
+/// annotations. Returns the error code and message on violation.
+pub(crate) fn check_metadata_limits(
+    kind: &str,
+    map: &BTreeMap<String, String>,
+    config: &JobConfig,
+) -> Result<(), (String, String)> {
+    if map.len() > config.max_labels {
+        return Err((
+            format!("too_many_{}", kind),
+            format!("{} {} given, at most {} allowed", map.len(), kind, config.max_labels),
+        ));
+    }
+
+    let total_bytes: usize = map.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if total_bytes > config.max_label_bytes {
+        return Err((
+            format!("{}_too_large", kind),
+            format!(
+                "{} total {} bytes, at most {} allowed",
+                kind, total_bytes, config.max_label_bytes
+            ),
+        ));
+    }
+
+    // flashpods-* labels identify managed containers and must not be spoofed
+    if let Some(key) = map.keys().find(|k| !is_valid_metadata_key(k)) {
+        return Err((
+            format!("invalid_{}", kind),
+            format!(
+                "{} key {:?} must start with a letter or digit and use only letters, digits, '.', '_', '-' and '/', and the flashpods prefix is reserved",
+                kind, key
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Label and annotation keys are passed to podman as `--label key=value`,
+/// so they can't contain `=` or whitespace, and flashpods' own keys
+/// (any case) are reserved
+fn is_valid_metadata_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/'))
+        && !key.to_ascii_lowercase().starts_with("flashpods")
+}
+
+/// Normalize a job's `artifact_paths`, see `normalize_artifact_path`
+fn check_artifact_paths(paths: &[String]) -> Result<Vec<String>, String> {
+    if paths.len() > crate::artifacts::MAX_ARTIFACT_PATHS {
+        return Err(format!(
+            "At most {} artifact_paths are allowed",
+            crate::artifacts::MAX_ARTIFACT_PATHS
+        ));
+    }
+    paths
+        .iter()
+        .map(|path| {
+            crate::artifacts::normalize_artifact_path(path)
+                .map_err(|reason| format!("Invalid artifact path {:?}: {}", path, reason))
+        })
+        .collect()
+}
+
+/// Sorted, deduplicated `success_exit_codes`; each must be a possible
+/// process exit status
+fn check_success_exit_codes(codes: &[i32]) -> Result<Vec<i32>, String> {
+    if codes.is_empty() {
+        return Err("success_exit_codes must list at least one exit code".to_string());
+    }
+    if let Some(code) = codes.iter().find(|code| !(0..=255).contains(*code)) {
+        return Err(format!("Exit code {} is outside 0-255", code));
+    }
+    let mut codes = codes.to_vec();
+    codes.sort_unstable();
+    codes.dedup();
+    Ok(codes)
+}
+
+/// `/dev/shm` lives in the container's memory, so it can't usefully exceed
+/// the job's memory limit, nor the operator's cap
+fn check_shm_size(shm_size_mb: i32, memory_gb: i32, max_shm_size_mb: i32) -> Result<(), String> {
+    if shm_size_mb < 1 {
+        return Err("shm_size_mb must be at least 1".to_string());
+    }
+    if shm_size_mb > max_shm_size_mb {
+        return Err(format!("shm_size_mb may be at most {}", max_shm_size_mb));
+    }
+    if i64::from(shm_size_mb) > i64::from(memory_gb) * 1024 {
+        return Err(format!(
+            "shm_size_mb {} is larger than the job's {}GB of memory",
+            shm_size_mb, memory_gb
+        ));
+    }
+    Ok(())
+}
+
+/// Keys of `selector` that this node's labels don't satisfy. With a single
+/// node, a job is only accepted if this node matches all of them.
+fn unmatched_node_selector<'a>(
+    selector: &'a BTreeMap<String, String>,
+    node_labels: &BTreeMap<String, String>,
+) -> Vec<&'a str> {
+    selector
+        .iter()
+        .filter(|(key, value)| node_labels.get(*key) != Some(value))
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
+
+/// Check that each secret ref names a usable, unique environment variable.
+/// Whether the ref itself resolves is only known when the container starts.
+fn check_secret_refs(refs: &[SecretRef]) -> Result<(), String> {
+    let mut seen = std::collections::BTreeSet::new();
+    for secret in refs {
+        let name = &secret.env_name;
+        let valid_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!("{:?} is not a valid environment variable name", name));
+        }
+        // FLASHPODS_* variables are set by the server for agents
+        if name.starts_with("FLASHPODS_") {
+            return Err(format!("{:?} is reserved", name));
+        }
+        if !seen.insert(name) {
+            return Err(format!("{:?} is set more than once", name));
+        }
+        if secret.reference.is_empty() {
+            return Err(format!("{:?} has an empty ref", name));
+        }
+    }
+    Ok(())
+}
+
+/// What's known about an upload a job is about to use
+struct UploadAssessment {
+    upload: Option<crate::models::Upload>,
+    /// The record can outlive its files, e.g. after manual cleanup
+    on_disk: bool,
+    /// Unfinished jobs already using the upload
+    users: Vec<Job>,
+    /// Storage quota the upload's owner is over, when re-checking is on
+    over_quota: Option<String>,
+}
+
+impl UploadAssessment {
+    /// Why a job of `job_type` can't use the upload, as status, error code
+    /// and message. Workers mount it read-only and may share it; an agent
+    /// writes to it, so it needs the upload to itself, and holds it long
+    /// enough that it must fit the storage quota.
+    fn problem(
+        &self,
+        files_id: &str,
+        job_type: JobType,
+        require_on_disk: bool,
+    ) -> Option<(StatusCode, &'static str, String)> {
+        let Some(upload) = &self.upload else {
+            return Some((
+                StatusCode::NOT_FOUND,
+                "upload_not_found",
+                format!("Upload {} not found", files_id),
+            ));
+        };
+        if upload.state != crate::models::UploadState::Finalized {
+            return Some((
+                StatusCode::CONFLICT,
+                "upload_not_finalized",
+                format!("Upload {} is in {} state, must be finalized", files_id, upload.state),
+            ));
+        }
+        if !self.on_disk && require_on_disk {
+            return Some((
+                StatusCode::CONFLICT,
+                "upload_missing_on_disk",
+                format!("Upload {} is finalized but its files are gone", files_id),
+            ));
+        }
+
+        let conflict = match job_type {
+            JobType::Agent => self.users.first(),
+            JobType::Worker => self.users.iter().find(|j| j.job_type == JobType::Agent),
+        };
+        let conflict = conflict.map(|other| {
+            let message = match job_type {
+                JobType::Agent => format!(
+                    "Upload {} is in use by job {}; agent jobs need it to themselves",
+                    files_id, other.id
+                ),
+                JobType::Worker => format!(
+                    "Upload {} is being modified by agent job {}",
+                    files_id, other.id
+                ),
+            };
+            (StatusCode::CONFLICT, "upload_in_use", message)
+        });
+        if conflict.is_some() {
+            return conflict;
+        }
+
+        match (&self.over_quota, job_type) {
+            (Some(message), JobType::Agent) => Some((
+                StatusCode::INSUFFICIENT_STORAGE,
+                "insufficient_storage",
+                message.clone(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+async fn assess_upload(state: &AppState, files_id: &str) -> Result<UploadAssessment, sqlx::Error> {
+    let upload = state.upload_repo.get(files_id).await?;
+    let on_disk = std::path::Path::new(&state.upload_config.upload_dir)
+        .join(files_id)
+        .is_dir();
+    let users = match upload {
+        Some(_) => state.job_repo.get_active_by_upload(files_id).await?,
+        None => Vec::new(),
+    };
+    // The upload is already counted in the usage, so nothing is added
+    let over_quota = match &upload {
+        Some(upload) if state.job_config.recheck_upload_quota => {
+            crate::uploads::storage_quota_violation(state, &upload.user_id, 0).await?
+        }
+        _ => None,
+    };
+    Ok(UploadAssessment {
+        upload,
+        on_disk,
+        users,
+        over_quota,
+    })
+}
+
+/// POST /jobs?validate_upload=true - Report whether the request's upload is
+/// ready for a job of its type, without creating the job. The answer is 200
+/// either way; `usable` and `error` carry the outcome create would have.
+async fn validate_upload(
+    state: &AppState,
+    req: &CreateJobRequest,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let job_type: JobType = req.job_type.parse().map_err(invalid_enum_value)?;
+    let Some(files_id) = &req.files_id else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "missing_files_id",
+                "message": "validate_upload requires 'files_id'"
+            })),
+        ));
+    };
+
+    let db_error = |e: sqlx::Error| crate::db::database_error_response(&e, e.to_string());
+    let assessment = assess_upload(state, files_id).await.map_err(db_error)?;
+    let total_disk_bytes = state.upload_repo.get_total_disk_usage().await.map_err(db_error)?;
+    let problem = assessment.problem(files_id, job_type, state.job_config.require_upload_on_disk);
+    let upload = assessment.upload.as_ref();
+
+    Ok(Json(serde_json::json!({
+        "upload_id": files_id,
+        "job_type": job_type,
+        "mount": match job_type {
+            JobType::Worker => "ro",
+            JobType::Agent => "rw",
+        },
+        "usable": problem.is_none(),
+        "error": problem.as_ref().map(|(_, code, _)| *code),
+        "message": problem.as_ref().map(|(_, _, message)| message),
+        "state": upload.map(|u| u.state.to_string()),
+        "size_bytes": upload.and_then(|u| u.size_bytes),
+        "file_count": upload.and_then(|u| u.file_count),
+        "expires_at": upload.and_then(|u| u.expires_at).map(|t| t.to_rfc3339()),
+        "on_disk": assessment.on_disk,
+        "in_use_by": assessment.users.iter().map(|j| &j.id).collect::<Vec<_>>(),
+        "disk_usage": {
+            "total_bytes": total_disk_bytes,
+            "max_total_bytes": state.upload_config.max_total_disk_bytes
+        }
+    })))
+}
+
+/// Validate a worker's `command` / `commands` fields, which are mutually
+/// exclusive. Returns the error code and message on violation.
+fn check_worker_commands(req: &CreateJobRequest) -> Result<(), (&'static str, String)> {
+    match (&req.command, &req.commands) {
+        (Some(_), Some(_)) => Err((
+            "conflicting_commands",
+            "Specify either 'command' or 'commands', not both".to_string(),
+        )),
+        (None, None) => Err((
+            "missing_command",
+            "Worker jobs require a 'command' or 'commands' field".to_string(),
+        )),
+        (None, Some(commands)) if commands.is_empty() => Err((
+            "invalid_commands",
+            "'commands' must contain at least one command".to_string(),
+        )),
+        (None, Some(commands)) => match commands.iter().position(|c| c.trim().is_empty()) {
+            Some(i) => Err(("invalid_commands", format!("Command {} is empty", i + 1))),
+            None => Ok(()),
+        },
+        (Some(_), None) => Ok(()),
+    }
+}
+
+/// Check that `input_job_id` is a completed job, visible to `caller`, whose
+/// artifacts are still on disk
+async fn check_input_job(
+    state: &AppState,
+    input_job_id: &str,
+    caller: &Caller,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let input_job = load_job(state, input_job_id, caller).await?;
+
+    if input_job.status != JobStatus::Completed {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "input_job_not_completed",
+                "message": format!("Input job {} is {}, must be completed", input_job_id, input_job.status)
+            })),
+        ));
+    }
+
+    if !std::path::Path::new(&state.podman.artifacts_path(input_job_id)).is_dir() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "input_artifacts_missing",
+                "message": format!("Artifacts for input job {} no longer exist", input_job_id)
+            })),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Response for a job that was cancelled while it was being started
+fn cancelled_before_start(job_id: String) -> (StatusCode, Json<CreateJobResponse>) {
+    (
+        StatusCode::CREATED,
+        Json(CreateJobResponse {
+            job_id,
+            status: JobStatus::Cancelled,
+            created: true,
+            message: Some("Job cancelled before its container started".to_string()),
+        }),
+    )
+}
+
+/// Stop a container, falling back to SIGKILL if the graceful stop fails.
+///
+/// Returns the kill error when both attempts fail, meaning the container may
+/// still be running.
+fn stop_or_kill(
+    state: &AppState,
+    container_id: &str,
+    grace_seconds: u64,
+) -> Result<(), crate::podman::PodmanError> {
+    if let Err(e) = state.podman.stop_container(container_id, grace_seconds) {
+        tracing::warn!("Failed to stop container {}: {}", container_id, e);
+        // Try kill as fallback
+        if let Err(e) = state.podman.kill_container(container_id) {
+            tracing::error!("Failed to kill container {}: {}", container_id, e);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Start a container for a job
+fn start_container(state: &AppState, job: &Job) -> Result<String, crate::podman::PodmanError> {
+    let secret_env = crate::secrets::resolve_all(state.secrets.as_ref(), &job.secret_refs)?;
+    let config = ContainerConfig {
+        job_id: job.id.clone(),
+        user_id: job.user_id.clone(),
+        job_type: match job.job_type {
+            JobType::Worker => crate::podman::JobType::Worker,
+            JobType::Agent => crate::podman::JobType::Agent,
+        },
+        upload_id: job.files_id.clone().unwrap_or_default(),
+        input_from_job: job.input_from_job.clone(),
+        input_mount_path: state.job_config.input_mount_path.clone(),
+        image: job.image.clone(),
+        command: job.command.clone(),
+        commands: job.commands.clone(),
+        cpus: job.cpus,
+        memory_gb: job.memory_gb,
+        shm_size_mb: job.shm_size_mb,
+        task: job.task.clone(),
+        context: job.context.clone(),
+        git_branch: job.git_branch.clone(),
+        labels: job.labels.clone(),
+        annotations: job.annotations.clone(),
+        secret_env,
+        mount_artifacts: !job.ephemeral,
+    };
+
+    // Update status to starting
+    // Note: This is a sync wrapper - the caller handles async updates
+    state.podman.create_container(&config)
+}
+
+/// 400 `invalid_enum_value` for a field that only takes certain values,
+/// listing them
+pub(crate) fn invalid_enum_value(e: InvalidEnumValue) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({
+            "error": "invalid_enum_value",
+            "message": e.to_string(),
+            "field": e.field,
+            "value": e.value,
+            "allowed": e.allowed
+        })),
+    )
+}
+
+/// GET /jobs - List jobs
+async fn list_jobs(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<ListJobsQuery>,
+) -> impl IntoResponse {
+    // An unknown status would otherwise just match nothing
+    let status_filter = params
+        .status
+        .as_deref()
+        .filter(|status| !status.eq_ignore_ascii_case("all"))
+        .map(str::parse::<JobStatus>)
+        .transpose()
+        .map_err(invalid_enum_value)?
+        .map(|status| status.to_string());
+    let status_filter = status_filter.as_deref();
+    let limit = list_limit(params.limit).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_limit",
+                "message": message
+            })),
+        )
+    })?;
+
+    let jobs = if params.dead_letter {
+        state.job_repo.list_dead_letter(limit).await
+    } else {
+        state.job_repo.list(status_filter, limit).await
+    };
+    match jobs {
+        Ok(jobs) => {
+            let total = jobs.len();
+            // Summaries keep large listings small; ?verbose=true has everything
+            let jobs = if params.verbose {
+                serde_json::json!(jobs.into_iter().map(JobResponse::from).collect::<Vec<_>>())
+            } else {
+                serde_json::json!(jobs.into_iter().map(JobSummary::from).collect::<Vec<_>>())
+            };
+            Ok(Json(serde_json::json!({
+                "jobs": jobs,
+                "total": total
+            })))
+        }
+        Err(e) => Err(crate::db::database_error_response(&e, e.to_string())),
+    }
+}
+
+/// Jobs listed when `?limit=` isn't given
+const DEFAULT_LIST_LIMIT: i32 = 20;
+/// Most jobs one listing returns; larger limits are clamped to it
+const MAX_LIST_LIMIT: i32 = 100;
+
+/// The number of jobs to list for a requested `?limit=`. Zero and negative
+/// limits are rejected rather than reaching SQL, where `LIMIT -1` means
+/// no limit at all.
+fn list_limit(requested: Option<i64>) -> Result<i32, String> {
+    match requested {
+        None => Ok(DEFAULT_LIST_LIMIT),
+        Some(limit) if limit < 1 => Err(format!("limit must be at least 1, got {}", limit)),
+        Some(limit) => Ok(limit.min(i64::from(MAX_LIST_LIMIT)) as i32),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ListJobsQuery {
+    status: Option<String>,
+    limit: Option<i64>,
+    /// Full `JobResponse`s instead of `JobSummary`s
+    #[serde(default)]
+    verbose: bool,
+    /// Only dead-lettered jobs: failed for a permanent reason. Replaces
+    /// `status`.
+    #[serde(default)]
+    dead_letter: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct KillJobQuery {
+    grace: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct KillByClientIdQuery {
+    client_job_id: String,
+    grace: Option<u64>,
+}
+
+/// Grace period for a kill: the request's `?grace=` if given, otherwise the
+/// configured default
+fn kill_grace_seconds(requested: Option<u64>, config: &JobConfig) -> u64 {
+    requested.unwrap_or(config.kill_grace_seconds)
+}
+
+/// GET /jobs/:id - Get job details, through the job cache since clients
+/// poll this
+async fn get_job(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = state
+        .job_repo
+        .get_cached(&id)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    authorize_loaded_job(job, &id, &caller).map(|job| {
+        // Only a failed job has a step to blame
+        let multi_step = job.commands.is_some() && job.status == JobStatus::Failed;
+        let failed_step = if multi_step {
+            state.podman.failed_step(&job.id)
+        } else {
+            None
+        };
+        let mut response = JobResponse::from(job);
+        response.failed_step = failed_step;
+        Json(response)
+    })
+}
+
+/// GET /jobs/:id/admin - Any user's job with its owner and submission
+/// source, for admins
+async fn get_job_admin(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    caller.require_admin("This endpoint requires the admin token")?;
+
+    match state.job_repo.get(&id).await {
+        Ok(Some(job)) => Ok(Json(AdminJobResponse::from(job))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "job_not_found",
+                "message": format!("Job {} not found", id)
+            })),
+        )),
+        Err(e) => Err(crate::db::database_error_response(&e, e.to_string())),
+    }
+}
+
+/// GET /jobs/:id/export - Portable spec that `POST /jobs` accepts to
+/// recreate the job
+async fn export_job(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    load_job(&state, &id, &caller)
+        .await
+        .map(|job| Json(JobSpec::from(job)))
+}
+
+/// GET /jobs/:id/timeline - Status transitions and container lifecycle
+/// events in one time-ordered list
+async fn get_timeline(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = load_job(&state, &id, &caller).await?;
+
+    let job_events = state
+        .job_repo
+        .events(&id)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+
+    // Container events are best-effort: podman's event log may be rotated
+    // or unavailable, and the recorded transitions still tell most of it
+    let podman = state.podman.clone();
+    let since = job.created_at;
+    let container_events =
+        match tokio::task::spawn_blocking(move || podman.container_events(&id, since)).await {
+            Ok(Ok(events)) => Some(events),
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to read container events for job {}: {}", job.id, e);
+                None
+            }
+            Err(e) => {
+                tracing::error!("Container events task failed: {}", e);
+                None
+            }
+        };
+    let container_events_available = container_events.is_some();
+
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "job_id": job.id,
+        "events": merge_timeline(job_events, container_events.unwrap_or_default()),
+        "container_events_available": container_events_available
+    })))
+}
+
+/// Stands in for secret values in `GET /jobs/:id/env`
+const REDACTED: &str = "[redacted]";
+
+/// GET /jobs/:id/env - The environment the job's container runs with,
+/// rebuilt from the stored job. Secret values are never returned.
+async fn get_env(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = load_job(&state, &id, &caller).await?;
+
+    let job_type = match job.job_type {
+        JobType::Worker => crate::podman::JobType::Worker,
+        JobType::Agent => crate::podman::JobType::Agent,
+    };
+    let mut env: std::collections::BTreeMap<String, String> = crate::podman::job_env(
+        &job.id,
+        job_type,
+        job.task.as_deref(),
+        job.context.as_deref(),
+        job.git_branch.as_deref(),
+    )
+    .into_iter()
+    .collect();
+    let mut redacted = Vec::new();
+    for secret in &job.secret_refs {
+        env.insert(secret.env_name.clone(), REDACTED.to_string());
+        redacted.push(secret.env_name.clone());
+    }
+    redacted.sort();
+
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "job_id": job.id,
+        "env": env,
+        "redacted": redacted
+    })))
+}
+
+/// Load a job on behalf of `caller`, mapping missing and inaccessible jobs to
+/// the response dictated by `authorize_job_access`
+async fn load_job(
+    state: &AppState,
+    id: &str,
+    caller: &Caller,
+) -> Result<Job, (StatusCode, Json<serde_json::Value>)> {
+    let job = state.job_repo.get(id).await.map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    authorize_loaded_job(job, id, caller)
+}
+
+/// Check the caller may see a job already read from the repository
+fn authorize_loaded_job(
+    job: Option<Job>,
+    id: &str,
+    caller: &Caller,
+) -> Result<Job, (StatusCode, Json<serde_json::Value>)> {
+    match authorize_job_access(job.as_ref(), caller) {
+        JobAccess::Found => Ok(job.expect("found job is present")),
+        JobAccess::NotFound => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "job_not_found",
+                "message": format!("Job {} not found", id)
+            })),
+        )),
+        JobAccess::Forbidden => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "job_forbidden",
+                "message": format!("Job {} belongs to another user", id)
+            })),
+        )),
+    }
+}
+
+/// DELETE /jobs/:id - Kill a job
+async fn kill_job(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<KillJobQuery>,
+) -> impl IntoResponse {
+    let job = load_job(&state, &id, &caller).await?;
+    cancel_job(&state, job, params.grace).await
+}
+
+/// DELETE /jobs?client_job_id= - Kill the job an active idempotency key maps
+/// to, for clients that only track their own id
+async fn kill_job_by_client_id(
+    State(state): State<AppState>,
+    caller: Caller,
+    axum::extract::Query(params): axum::extract::Query<KillByClientIdQuery>,
+) -> impl IntoResponse {
+    let resolved = state
+        .job_repo
+        .get_by_client_id(&params.client_job_id)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    let Some(resolved) = resolved else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "job_not_found",
+                "message": format!("No active job for client_job_id {}", params.client_job_id)
+            })),
+        ));
+    };
+
+    let job = load_job(&state, &resolved.id, &caller).await?;
+    cancel_job(&state, job, params.grace).await
+}
+
+/// Stop a job's container, if any, and mark it cancelled
+async fn cancel_job(
+    state: &AppState,
+    job: Job,
+    grace: Option<u64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let id = job.id.clone();
+
+    // Check if job can be killed
+    if job.status.is_terminal() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "job_already_terminal",
+                "message": format!("Job {} is already in terminal state: {}", id, job.status)
+            })),
+        ));
+    }
+
+    // Resolve the container, coordinating with a create_job that may still be
+    // starting it. If it is, the starter sees the cancel and stops the container.
+    let container_id = match job.container_id {
+        Some(container_id) => Some(container_id),
+        None => match state.startups.request_cancel(&id) {
+            CancelOutcome::BeforeStart => None,
+            CancelOutcome::Started(container_id) => Some(container_id),
+            CancelOutcome::NotTracked => state
+                .job_repo
+                .get(&id)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|j| j.container_id),
+        },
+    };
+
+    // Kill container
+    if let Some(ref container_id) = container_id {
+        let grace_seconds = kill_grace_seconds(grace, &state.job_config);
+        let stopped = {
+            let (state, container_id) = (state.clone(), container_id.clone());
+            tokio::task::spawn_blocking(move || stop_or_kill(&state, &container_id, grace_seconds))
+                .await
+                .map_err(|e| crate::podman::PodmanError::Command(e.to_string()))
+                .and_then(|result| result)
+        };
+        if let Err(e) = stopped {
+            // The container may still be running, so leave the status alone
+            // and record why the cancel didn't take
+            let message = format!("Failed to stop container {}: {}", container_id, e);
+            if let Err(e) = state.job_repo.set_error(&id, &message).await {
+                tracing::error!("Failed to set job error: {}", e);
+            }
+            return Err((
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({
+                    "error": "container_stop_failed",
+                    "message": message
+                })),
+            ));
+        }
+    }
+
+    // Update status
+    if let Err(e) = state.job_repo.update_status(&id, JobStatus::Cancelled).await {
+        tracing::error!("Failed to update job status: {}", e);
+    }
+    if container_id.is_some() {
+        if let Err(e) = state.job_repo.set_exit_code(&id, 137).await {
+            tracing::error!("Failed to set exit code: {}", e);
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "job_id": id,
+        "status": "cancelled",
+        "message": "Job termination initiated"
+    })))
+}
+
+#[derive(serde::Deserialize)]
+struct GetOutputQuery {
+    tail: Option<usize>,
+    /// Only return lines containing this substring, or matching the regex
+    /// after a `re:` prefix
+    grep: Option<String>,
+}
+
+const DEFAULT_OUTPUT_TAIL: usize = 100;
+const MAX_OUTPUT_TAIL: usize = 10000;
+
+/// GET /jobs/:id/output - Get job output
+///
+/// Reads from podman while the container is live and from the captured file
+/// afterwards, see `logs::select_source`.
+async fn get_output(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<GetOutputQuery>,
+) -> impl IntoResponse {
+    let job = load_job(&state, &id, &caller).await?;
+
+    if job.status == JobStatus::Pending {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "logs_not_available",
+                "message": "Job has not started yet"
+            })),
+        ));
+    }
+
+    let filter = match query.grep.as_deref().map(crate::logs::LineFilter::parse) {
+        Some(Ok(filter)) => Some(filter),
+        Some(Err(message)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "invalid_grep_pattern",
+                    "message": message
+                })),
+            ))
+        }
+        None => None,
+    };
+    let tail = query
+        .tail
+        .unwrap_or(DEFAULT_OUTPUT_TAIL)
+        .min(MAX_OUTPUT_TAIL);
+    let path = crate::logs::log_path(&state.job_config.logs_dir, &id);
+    let file_exists = tokio::fs::try_exists(&path).await.unwrap_or(false);
+    // Logs outlive a cleaned job's artifacts until their own retention ends
+    if job.logs_deleted_at.is_some() || (job.status == JobStatus::Cleaned && !file_exists) {
+        return Err((
+            StatusCode::GONE,
+            Json(serde_json::json!({
+                "error": "logs_deleted",
+                "message": "Job's logs were deleted after their retention period"
+            })),
+        ));
+    }
+    let mut source = crate::logs::select_source(
+        &job.status,
+        job.container_id.is_some(),
+        file_exists,
+    );
+
+    let mut log = None;
+    if let (crate::logs::LogSource::Podman, Some(container_id)) = (source, job.container_id) {
+        let podman = state.podman.clone();
+        // Searching needs the whole log; `tail` then applies to the matches.
+        // Either way no more is read than a capture would keep.
+        let podman_tail = if filter.is_some() { None } else { Some(tail) };
+        let max_bytes = state.job_config.max_log_bytes;
+        let logs = tokio::task::spawn_blocking(move || {
+            podman.logs(&container_id, podman_tail, max_bytes)
+        })
+        .await;
+        match logs {
+            Ok(Ok(logs)) => {
+                let text = crate::logs::normalize_podman_logs(&logs.text);
+                log = Some((text, "podman", logs.truncated))
+            }
+            // The container may have exited between the status read and now
+            Ok(Err(e)) => tracing::debug!("podman logs for job {} failed: {}", id, e),
+            Err(e) => tracing::error!("podman logs task for job {} failed: {}", id, e),
+        }
+        if log.is_none() {
+            source = crate::logs::LogSource::File;
+        }
+    }
+
+    if log.is_none() && source == crate::logs::LogSource::File {
+        if let Ok(contents) = tokio::fs::read_to_string(&path).await {
+            let truncated = crate::logs::is_truncated(&contents);
+            log = Some((contents, "file", truncated));
+        }
+    }
+    let (log, source, truncated) = log.unwrap_or_else(|| (String::new(), "none", false));
+
+    let mut response = match &filter {
+        Some(filter) => {
+            let (matches, match_count) = crate::logs::grep_lines(&log, filter, tail);
+            let output: Vec<&str> = matches.iter().map(|(_, text)| *text).collect();
+            serde_json::json!({
+                "output": output.join("\n"),
+                "lines": matches.len(),
+                "matches": matches
+                    .iter()
+                    .map(|(line, text)| serde_json::json!({"line": line, "text": text}))
+                    .collect::<Vec<_>>(),
+                "match_count": match_count,
+            })
+        }
+        None => {
+            let (output, lines) = crate::logs::tail_lines(&log, tail);
+            serde_json::json!({"output": output, "lines": lines})
+        }
+    };
+    response["truncated"] = truncated.into();
+    response["total_bytes"] = log.len().into();
+    response["source"] = source.into();
+    Ok(Json(response))
+}
+
+/// GET /jobs/:id/output/stream - Follow a running job's output as
+/// server-sent events, one `data:` line per log line
+async fn stream_output(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> Result<
+    axum::response::sse::Sse<
+        impl futures_util::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>,
+    >,
+    (StatusCode, Json<serde_json::Value>),
+> {
+    let job = load_job(&state, &id, &caller).await?;
+
+    let container_id = match (&job.status, job.container_id) {
+        (JobStatus::Starting | JobStatus::Running, Some(container_id)) => container_id,
+        _ => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "job_not_running",
+                    "message": format!("Job is {}; use GET /jobs/{}/output instead", job.status, id)
+                })),
+            ))
+        }
+    };
+
+    let subscription = state
+        .log_streams
+        .subscribe_podman(&id, state.podman.clone(), &container_id, &state.processes)
+        .map_err(|e| {
+            let (status, error) = match e {
+                crate::logs::StreamError::TooManySubscribers { .. } => {
+                    (StatusCode::TOO_MANY_REQUESTS, "too_many_log_subscribers")
+                }
+                crate::logs::StreamError::NoProcessSlot(_) => {
+                    (StatusCode::SERVICE_UNAVAILABLE, "too_many_background_processes")
+                }
+            };
+            (
+                status,
+                Json(serde_json::json!({
+                    "error": error,
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    // Lines from before this subscriber joined come first
+    let replay = futures_util::stream::iter(
+        subscription
+            .replay
+            .into_iter()
+            .map(|line| Ok(axum::response::sse::Event::default().data(line))),
+    );
+    let live = futures_util::stream::unfold(subscription.receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(line) => {
+                    let event = axum::response::sse::Event::default().data(line);
+                    return Some((Ok(event), receiver));
+                }
+                // A slow client misses lines rather than holding up the others
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(axum::response::sse::Sse::new(futures_util::StreamExt::chain(replay, live)))
+}
+
+/// GET /jobs/:id/artifacts - List job artifacts
+///
+/// The first listing of a finished job records what it left in its
+/// artifacts directory.
+async fn list_artifacts(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = load_job(&state, &id, &caller).await?;
+
+    if job.status == JobStatus::Cleaned {
+        return Err((
+            StatusCode::GONE,
+            Json(serde_json::json!({
+                "error": "artifacts_deleted",
+                "message": "Job was cleaned up and its artifacts deleted"
+            })),
+        ));
+    }
+    if !job.status.is_terminal() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "artifacts_not_available",
+                "message": format!("Job is {}; artifacts are available once it finishes", job.status)
+            })),
+        ));
+    }
+
+    let mut artifacts = state
+        .artifact_repo
+        .list(&id)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    // A promoted log is recorded by log capture, not with the job's files
+    let promoted = crate::logs::promoted_log_path(&state.job_config.logs_dir, &id);
+    if artifacts
+        .iter()
+        .all(|a| std::path::Path::new(&a.path) == promoted)
+    {
+        artifacts = record_artifacts(&state, &job).await?;
+    }
+
+    let total_size_bytes: i64 = artifacts.iter().map(|a| a.size_bytes).sum();
+    let artifacts: Vec<serde_json::Value> = artifacts
+        .into_iter()
+        .map(|a| {
+            let mut entry = serde_json::json!({
+                "name": a.name,
+                "size_bytes": a.size_bytes,
+                "created_at": a.created_at
+            });
+            if let Some(original_path) = a.original_path.filter(|p| *p != a.name) {
+                entry["original_path"] = original_path.into();
+            }
+            entry
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "artifacts": artifacts,
+        "total_size_bytes": total_size_bytes,
+        "copy_in_progress": false
+    })))
+}
+
+/// Record every file in a finished job's artifacts directory under a
+/// sanitized name, keeping the path the job used alongside it. Concurrent
+/// listings may both get here; they record the same rows.
+async fn record_artifacts(
+    state: &AppState,
+    job: &Job,
+) -> Result<Vec<crate::models::Artifact>, (StatusCode, Json<serde_json::Value>)> {
+    if job.ephemeral {
+        return Ok(Vec::new());
+    }
+    let outputs = match collect_job_outputs(state, job).await {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            tracing::error!("Failed to read artifacts for job {}: {}", job.id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "internal_error",
+                    "message": format!("Failed to read artifacts: {}", e)
+                })),
+            ));
+        }
+    };
+
+    let host_paths: Vec<String> = outputs
+        .iter()
+        .map(|file| file.host_path.to_string_lossy().into_owned())
+        .collect();
+    let artifacts: Vec<_> = outputs
+        .iter()
+        .zip(&host_paths)
+        .map(|(file, host_path)| crate::db::NewArtifact {
+            name: &file.name,
+            path: host_path,
+            original_path: Some(&file.original_path),
+            size_bytes: file.size_bytes as i64,
+        })
+        .collect();
+    state
+        .artifact_repo
+        .create_all(&job.id, &artifacts)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))
+}
+
+/// The files in a job's artifacts directory that its allowlist keeps
+async fn collect_job_outputs(
+    state: &AppState,
+    job: &Job,
+) -> std::io::Result<Vec<crate::artifacts::CollectedArtifact>> {
+    let dir = std::path::PathBuf::from(state.podman.artifacts_path(&job.id));
+    let max_name_len = state.job_config.max_artifact_name_len;
+    // The job's own allowlist replaces the operator default
+    let allowed = if job.artifact_paths.is_empty() {
+        state.job_config.artifact_paths.clone()
+    } else {
+        job.artifact_paths.clone()
+    };
+    match tokio::task::spawn_blocking(move || {
+        if dir.is_dir() {
+            collect_artifacts(&dir, max_name_len, &allowed)
+        } else {
+            Ok(Vec::new())
+        }
+    })
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => Err(std::io::Error::other(e.to_string())),
+    }
+}
+
+/// GET /jobs/:id/artifacts/:name/info - Artifact metadata without the body
+async fn get_artifact_info(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Err(reason) = validate_artifact_name(&name) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_artifact_name",
+                "message": format!("Invalid artifact name: {}", reason)
+            })),
+        ));
+    }
+
+    load_job(&state, &id, &caller).await?;
+
+    let artifact_not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "artifact_not_found",
+                "message": format!("Artifact {} not found for job {}", name, id)
+            })),
+        )
+    };
+
+    let artifact = match state.artifact_repo.get(&id, &name).await {
+        Ok(Some(a)) => a,
+        Ok(None) => return Err(artifact_not_found()),
+        Err(e) => {
+            return Err(crate::db::database_error_response(&e, e.to_string()));
+        }
+    };
+
+    // Hash off the async runtime; artifacts can be large
+    let path = std::path::PathBuf::from(&artifact.path);
+    let sha256 = match tokio::task::spawn_blocking(move || sha256_file(&path)).await {
+        Ok(Ok(digest)) => digest,
+        Ok(Err(e)) => {
+            tracing::warn!("Artifact {} recorded but unreadable: {}", artifact.path, e);
+            return Err(artifact_not_found());
+        }
+        Err(e) => {
+            return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({
-                    "error": "database_error",
+                    "error": "internal_error",
                     "message": e.to_string()
                 })),
             ));
         }
-    };
+    };
+
+    Ok(Json(ArtifactInfo {
+        name: artifact.name,
+        original_path: artifact.original_path,
+        size_bytes: artifact.size_bytes,
+        sha256,
+        created_at: artifact.created_at,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PendingReason;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    async fn post_json(
+        app: axum::Router,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    fn sample_job(id: &str, status: JobStatus) -> Job {
+        Job {
+            command: Some("echo hi".to_string()),
+            cpus: 2,
+            memory_gb: 4,
+            ..Job::sample(id, "default", status)
+        }
+    }
+
+    #[test]
+    fn test_metadata_count_limit() {
+        let config = JobConfig {
+            max_labels: 2,
+            ..JobConfig::default()
+        };
+        let mut labels = BTreeMap::new();
+        labels.insert("a".to_string(), "1".to_string());
+        labels.insert("b".to_string(), "2".to_string());
+        assert!(check_metadata_limits("labels", &labels, &config).is_ok());
+
+        labels.insert("c".to_string(), "3".to_string());
+        let (code, _) = check_metadata_limits("labels", &labels, &config).unwrap_err();
+        assert_eq!(code, "too_many_labels");
+    }
+
+    #[test]
+    fn test_metadata_size_limit() {
+        let config = JobConfig {
+            max_label_bytes: 10,
+            ..JobConfig::default()
+        };
+        let mut annotations = BTreeMap::new();
+        annotations.insert("note".to_string(), "short".to_string());
+        assert!(check_metadata_limits("annotations", &annotations, &config).is_ok());
+
+        annotations.insert("note".to_string(), "much too long".to_string());
+        let (code, _) = check_metadata_limits("annotations", &annotations, &config).unwrap_err();
+        assert_eq!(code, "annotations_too_large");
+    }
+
+    #[test]
+    fn test_metadata_reserved_keys() {
+        let mut labels = BTreeMap::new();
+        labels.insert("flashpods-job-id".to_string(), "spoofed".to_string());
+        let (code, _) = check_metadata_limits("labels", &labels, &JobConfig::default()).unwrap_err();
+        assert_eq!(code, "invalid_labels");
+    }
+
+    #[test]
+    fn test_metadata_key_format() {
+        for key in ["team", "app.kubernetes.io/name", "build_id", "v2-cache"] {
+            let labels = BTreeMap::from([(key.to_string(), "x".to_string())]);
+            assert!(check_metadata_limits("labels", &labels, &JobConfig::default()).is_ok(), "{}", key);
+        }
+        for key in ["", " ", " team", "team name", "a=flashpods-job-id", "-team", "FlashPods-Job-Id", "t\u{e9}am"] {
+            let labels = BTreeMap::from([(key.to_string(), "x".to_string())]);
+            let (code, _) = check_metadata_limits("labels", &labels, &JobConfig::default()).unwrap_err();
+            assert_eq!(code, "invalid_labels", "{:?}", key);
+        }
+    }
+
+    /// State whose podman is a script that logs its arguments and takes a
+    /// second to `run`, so requests can be dropped mid-create
+    fn slow_podman_state(state: AppState, dir: &std::path::Path) -> AppState {
+        let podman = crate::podman::PodmanService::scripted(
+            dir,
+            r#"case "$1" in run) sleep 1; echo ctr_slow ;; esac"#,
+        );
+        AppState {
+            podman: std::sync::Arc::new(podman),
+            ..state
+        }
+    }
+
+    async fn wait_for_status(state: &AppState, id: &str, status: JobStatus) -> Job {
+        for _ in 0..50 {
+            let job = state.job_repo.get(id).await.unwrap().unwrap();
+            if job.status == status {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("job {} never reached {}", id, status);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_cancels_startup() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = slow_podman_state(crate::test_state().await, dir.path());
+        let app = routes().with_state(state.clone());
+
+        // The client gives up while podman is still creating the container
+        let request = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "client_job_id": "drop-1"}),
+        );
+        assert!(tokio::time::timeout(Duration::from_millis(300), request)
+            .await
+            .is_err());
+
+        let job = state.job_repo.get_by_client_id("drop-1").await.unwrap().unwrap();
+        let job = wait_for_status(&state, &job.id, JobStatus::Cancelled).await;
+        assert_eq!(job.container_id.as_deref(), Some("ctr_slow"));
+
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.lines().any(|l| l.starts_with("stop") && l.ends_with("ctr_slow")));
+
+        // The client never saw that outcome, so its retry starts afresh
+        let (status, body) = post_json(
+            routes().with_state(state.clone()),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "client_job_id": "drop-1"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        assert_eq!(body["created"], true);
+        assert_ne!(body["job_id"], job.id.as_str());
+        assert_eq!(body["status"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_create_job_records_caller_user() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_owned ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+
+        let mut request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({"type": "worker", "command": "make"}).to_string(),
+            ))
+            .unwrap();
+        request.extensions_mut().insert(Caller {
+            user_id: "team-a".to_string(),
+            is_admin: false,
+        });
+
+        let response = routes()
+            .with_state(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let job = state
+            .job_repo
+            .get(body["job_id"].as_str().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.user_id, "team-a");
+    }
+
+    #[tokio::test]
+    async fn test_exported_spec_recreates_job() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_export ;; esac"#,
+            )),
+            secrets: std::sync::Arc::new(crate::secrets::FileSecretProvider::new(dir.path())),
+            // Exports must not carry fields POST /jobs doesn't know
+            job_config: JobConfig {
+                strict_requests: true,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        std::fs::create_dir(dir.path().join("ci")).unwrap();
+        std::fs::write(dir.path().join("ci/token"), "s3cret\n").unwrap();
+        let app = routes().with_state(state.clone());
+
+        let (status, created) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({
+                "type": "worker",
+                "commands": ["make", "make test"],
+                "image": "rust:1.80",
+                "cpus": 4,
+                "memory_gb": 8,
+                "restartable": true,
+                "secret_refs": [{"env_name": "API_TOKEN", "ref": "ci/token"}],
+                "labels": {"team": "build"}
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let original_id = created["job_id"].as_str().unwrap();
+
+        let (status, spec) = get_json(app.clone(), &format!("/{}/export", original_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(spec["spec_version"], 1);
+        assert!(spec.get("id").is_none() && spec.get("status").is_none());
+
+        let (status, recreated) = post_json(app.clone(), "/", spec.clone()).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let recreated_id = recreated["job_id"].as_str().unwrap();
+        assert_ne!(recreated_id, original_id);
+
+        let (_, respec) = get_json(app.clone(), &format!("/{}/export", recreated_id)).await;
+        assert_eq!(respec, spec);
+
+        // Single-use inputs aren't part of the replayable spec
+        let with_inputs = Job {
+            files_id: Some("upload_used".to_string()),
+            input_from_job: Some(original_id.to_string()),
+            ..sample_job("job_export_inputs", JobStatus::Completed)
+        };
+        state.job_repo.create(&with_inputs, None).await.unwrap();
+        let (status, spec_inputs) = get_json(app.clone(), "/job_export_inputs/export").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(spec_inputs.get("files_id").is_none());
+        assert!(spec_inputs.get("input_from_job").is_none());
+
+        // Specs from a future format are refused rather than half-understood
+        let mut future = spec;
+        future["spec_version"] = 2.into();
+        let (status, body) = post_json(app, "/", future).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "unsupported_spec_version");
+    }
+
+    #[tokio::test]
+    async fn test_submission_source_recorded_and_shown_to_admins() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_audit ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+
+        let mut request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header("user-agent", "flashpods-mcp/1.2")
+            .body(Body::from(
+                serde_json::json!({"type": "worker", "command": "make"}).to_string(),
+            ))
+            .unwrap();
+        let addr: SocketAddr = "203.0.113.7:51234".parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        request
+            .extensions_mut()
+            .insert(RequestId("req-123".to_string()));
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let job_id = body["job_id"].as_str().unwrap().to_string();
+
+        let admin_get = |is_admin: bool| {
+            let mut request = axum::http::Request::builder()
+                .uri(format!("/{}/admin", job_id))
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(Caller {
+                user_id: "ops".to_string(),
+                is_admin,
+            });
+            app.clone().oneshot(request)
+        };
+
+        let response = admin_get(true).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["id"], job_id.as_str());
+        assert_eq!(body["user_id"], "default");
+        assert_eq!(
+            body["submission"],
+            serde_json::json!({
+                "client_ip": "203.0.113.7",
+                "user_agent": "flashpods-mcp/1.2",
+                "request_id": "req-123"
+            })
+        );
+
+        let response = admin_get(false).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_future_start_after_leaves_job_pending() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_early ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+        let start_after = Utc::now() + chrono::Duration::hours(2);
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "start_after": start_after}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "pending");
+        // Only the image was inspected; nothing ran
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap_or_default();
+        assert!(!calls.lines().any(|l| l.starts_with("run")));
+
+        let (_, body) = get_json(app.clone(), &format!("/{}", body["job_id"].as_str().unwrap())).await;
+        assert_eq!(body["pending_reason"], "scheduled");
+        assert!(body["start_after"].is_string());
+
+        let too_far = Utc::now() + chrono::Duration::days(30);
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "start_after": too_far}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_start_after");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_wins_over_a_failed_start() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) sleep 1; echo "Error: boom" >&2; exit 125 ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let request = tokio::spawn(post_json(
+            routes().with_state(state.clone()),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "client_job_id": "fail-1"}),
+        ));
+
+        // Cancelled while podman is still failing to create the container
+        let job = loop {
+            if let Some(job) = state.job_repo.get_by_client_id("fail-1").await.unwrap() {
+                break job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        wait_for_status(&state, &job.id, JobStatus::Starting).await;
+        let response = routes()
+            .with_state(state.clone())
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/{}", job.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (status, body) = request.await.unwrap();
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        assert_eq!(body["status"], "cancelled");
+        let job = state.job_repo.get(&job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert_eq!(job.error, None);
+        assert_eq!(job.failure_category, None);
+    }
+
+    #[tokio::test]
+    async fn test_request_deadline_cancels_startup() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = slow_podman_state(crate::test_state().await, dir.path());
+
+        let response = routes()
+            .with_state(state.clone())
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header("content-type", "application/json")
+                    .header("x-request-timeout", "0")
+                    .body(Body::from(
+                        serde_json::json!({"type": "worker", "command": "make", "client_job_id": "deadline-1"})
+                            .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let job = state.job_repo.get_by_client_id("deadline-1").await.unwrap().unwrap();
+        wait_for_status(&state, &job.id, JobStatus::Cancelled).await;
+    }
+
+    #[tokio::test]
+    async fn test_kill_reports_stop_and_kill_failure() {
+        let state = AppState {
+            podman: std::sync::Arc::new(
+                crate::podman::PodmanService::new().with_podman_path("/nonexistent/podman"),
+            ),
+            ..crate::test_state().await
+        };
+        let job = Job {
+            container_id: Some("ctr_stuck".to_string()),
+            ..sample_job("job_stuck", JobStatus::Running)
+        };
+        state.job_repo.create(&job, None).await.unwrap();
+        state
+            .job_repo
+            .set_container_id("job_stuck", "ctr_stuck")
+            .await
+            .unwrap();
+
+        let response = routes()
+            .with_state(state.clone())
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("DELETE")
+                    .uri("/job_stuck?grace=0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "container_stop_failed");
+
+        // Not claimed as cancelled, but the failure is on record
+        let job = state.job_repo.get("job_stuck").await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert!(job.error.unwrap().contains("ctr_stuck"));
+    }
+
+    #[tokio::test]
+    async fn test_create_job_checks_image_minimums() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+                    image) echo '[{"Config":{"Labels":{"flashpods.min-memory":"8"}}}]' ;;
+                    run) echo ctr_sized ;;
+                esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+        let request = |memory_gb: i32| {
+            serde_json::json!({
+                "type": "worker",
+                "command": "make",
+                "image": "builder:latest",
+                "cpus": 2,
+                "memory_gb": memory_gb
+            })
+        };
+
+        let (status, body) = post_json(app.clone(), "/", request(4)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "insufficient_resources_for_image");
+        assert!(body["message"].as_str().unwrap().contains("8GB"));
+
+        let (status, _) = post_json(app, "/", request(8)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.contains("image inspect --format json builder:latest"));
+    }
+
+    #[tokio::test]
+    async fn test_instant_exit_fails_without_running() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+                    run) echo ctr_crash ;;
+                    inspect) echo '[{"Id":"ctr_crash","State":{"Status":"exited","ExitCode":3}}]' ;;
+                esac"#,
+            )),
+            job_config: JobConfig {
+                startup_confirm_ms: 10,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "false"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "failed");
+
+        let job = state.job_repo.get(body["job_id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.exit_code, Some(3));
+        assert!(job.started_at.is_none());
+        assert!(job.error.unwrap().contains("exited during startup"));
+    }
+
+    #[tokio::test]
+    async fn test_instant_exit_of_autoremoved_container_fails() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // `--rm` removed the container before it could be inspected
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+                    run) echo ctr_gone ;;
+                    inspect) echo "Error: no such container $4" >&2; exit 125 ;;
+                    events) echo '{"Type":"container","Status":"start","Time":"2026-01-21T10:00:00Z"}'
+                            echo '{"Type":"container","Status":"die","Time":"2026-01-21T10:00:01Z","ContainerExitCode":2}' ;;
+                esac"#,
+            )),
+            job_config: JobConfig {
+                startup_confirm_ms: 10,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "false"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "failed");
+        let job = state.job_repo.get(body["job_id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.exit_code, Some(2));
+        assert_eq!(job.failure_category, Some(FailureCategory::ExitCode));
+
+        // An exit the job counts as success isn't failed
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "lint", "success_exit_codes": [0, 2]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_ne!(body["status"], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_instant_exit_with_listed_code_is_not_failed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+                    run) echo ctr_lint ;;
+                    inspect) echo '[{"Id":"ctr_lint","State":{"Status":"exited","ExitCode":3}}]' ;;
+                esac"#,
+            )),
+            job_config: JobConfig {
+                startup_confirm_ms: 10,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "lint", "success_exit_codes": [3, 0, 3]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_ne!(body["status"], "failed");
+
+        let job_id = body["job_id"].as_str().unwrap();
+        let job = state.job_repo.get(job_id).await.unwrap().unwrap();
+        assert_eq!(job.success_exit_codes, [0, 3]);
+        assert_eq!(job.status_for_exit(3), JobStatus::Completed);
+        assert_eq!(job.status_for_exit(1), JobStatus::Failed);
+
+        let (_, body) = get_json(app.clone(), &format!("/{}", job_id)).await;
+        assert_eq!(body["success_exit_codes"], serde_json::json!([0, 3]));
+
+        for bad in [serde_json::json!([]), serde_json::json!([0, 256]), serde_json::json!([-1])] {
+            let (status, body) = post_json(
+                app.clone(),
+                "/",
+                serde_json::json!({"type": "worker", "command": "lint", "success_exit_codes": bad}),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_success_exit_codes");
+        }
+    }
 
-    // Check if job can be killed
-    if job.status.is_terminal() {
-        return Err((
-            StatusCode::CONFLICT,
-            Json(serde_json::json!({
-                "error": "job_already_terminal",
-                "message": format!("Job {} is already in terminal state: {}", id, job.status)
-            })),
-        ));
+    #[tokio::test]
+    async fn test_confirmed_container_is_marked_running() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+                    run) echo ctr_up ;;
+                    inspect) echo '[{"Id":"ctr_up","State":{"Status":"running"}}]' ;;
+                esac"#,
+            )),
+            job_config: JobConfig {
+                startup_confirm_ms: 10,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "sleep 60"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "running");
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.lines().any(|l| l == "inspect --format json ctr_up"));
     }
 
-    // Kill container
-    if let Some(ref container_id) = job.container_id {
-        if let Err(e) = state.podman.stop_container(container_id, 10) {
-            tracing::warn!("Failed to stop container {}: {}", container_id, e);
-            // Try kill as fallback
-            let _ = state.podman.kill_container(container_id);
+    #[tokio::test]
+    async fn test_list_returns_summaries_unless_verbose() {
+        let state = crate::test_state().await;
+        let finished = Job {
+            started_at: Some(Utc::now() - chrono::Duration::minutes(10)),
+            completed_at: Some(Utc::now() - chrono::Duration::minutes(4)),
+            ..sample_job("job_listed", JobStatus::Completed)
+        };
+        state.job_repo.create(&finished, None).await.unwrap();
+        sqlx::query("UPDATE jobs SET started_at = ?, completed_at = ? WHERE id = ?")
+            .bind(finished.started_at.unwrap().to_rfc3339())
+            .bind(finished.completed_at.unwrap().to_rfc3339())
+            .bind(&finished.id)
+            .execute(state.db.inner())
+            .await
+            .unwrap();
+        let app = routes().with_state(state);
+
+        let (status, body) = get_json(app.clone(), "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 1);
+        let summary = body["jobs"][0].as_object().unwrap();
+        let mut keys: Vec<&str> = summary.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["created_at", "elapsed_seconds", "id", "status", "type"]);
+        assert_eq!(summary["id"], "job_listed");
+        assert_eq!(summary["type"], "worker");
+        assert_eq!(summary["status"], "completed");
+        // Elapsed time stops at completion
+        assert_eq!(summary["elapsed_seconds"], 360);
+
+        let (_, body) = get_json(app, "/?verbose=true").await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["jobs"][0]["command"], "echo hi");
+        assert_eq!(body["jobs"][0]["duration_seconds"], 360);
+    }
+
+    #[test]
+    fn test_list_limit() {
+        assert_eq!(list_limit(None), Ok(DEFAULT_LIST_LIMIT));
+        assert_eq!(list_limit(Some(1)), Ok(1));
+        assert_eq!(list_limit(Some(100)), Ok(100));
+        assert_eq!(list_limit(Some(101)), Ok(MAX_LIST_LIMIT));
+        assert_eq!(list_limit(Some(i64::MAX)), Ok(MAX_LIST_LIMIT));
+        assert!(list_limit(Some(0)).is_err());
+        assert!(list_limit(Some(-1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_rejects_non_positive_limits() {
+        let state = crate::test_state().await;
+        for i in 0..3 {
+            state
+                .job_repo
+                .create(&sample_job(&format!("job_limit_{}", i), JobStatus::Pending), None)
+                .await
+                .unwrap();
+        }
+        let app = routes().with_state(state);
+
+        for limit in ["0", "-1", "-100"] {
+            let (status, body) = get_json(app.clone(), &format!("/?limit={}", limit)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", limit);
+            assert_eq!(body["error"], "invalid_limit");
         }
+
+        let (_, body) = get_json(app.clone(), "/?limit=2").await;
+        assert_eq!(body["total"], 2);
+        let (status, body) = get_json(app, "/?limit=100000").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 3);
     }
 
-    // Update status
-    if let Err(e) = state.job_repo.update_status(&id, JobStatus::Cancelled).await {
-        tracing::error!("Failed to update job status: {}", e);
+    #[tokio::test]
+    async fn test_invalid_enum_values_list_the_allowed_ones() {
+        let state = crate::test_state().await;
+        state
+            .job_repo
+            .create(&sample_job("job_enum", JobStatus::Running), None)
+            .await
+            .unwrap();
+        let app = routes().with_state(state);
+
+        let (status, body) = post_json(app.clone(), "/", serde_json::json!({"type": "workr", "command": "make"})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_enum_value");
+        assert_eq!(body["field"], "type");
+        assert_eq!(body["value"], "workr");
+        assert_eq!(body["allowed"], serde_json::json!(["worker", "agent"]));
+        assert_eq!(body["message"], "Invalid type 'workr': expected one of worker, agent");
+
+        let (status, body) = get_json(app.clone(), "/?status=runing").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_enum_value");
+        assert_eq!(body["field"], "status");
+        assert_eq!(body["allowed"].as_array().unwrap().len(), JobStatus::VALUES.len());
+        assert!(body["allowed"].as_array().unwrap().contains(&serde_json::json!("timed_out")));
+
+        // Valid filters are matched whatever their case
+        let (status, body) = get_json(app.clone(), "/?status=RUNNING").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 1);
+        let (_, body) = get_json(app, "/?status=all").await;
+        assert_eq!(body["total"], 1);
     }
-    if let Err(e) = state.job_repo.set_exit_code(&id, 137).await {
-        tracing::error!("Failed to set exit code: {}", e);
+
+    #[tokio::test]
+    async fn test_shm_size_is_capped_and_passed_to_podman() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_shm ;; esac"#,
+            )),
+            job_config: JobConfig {
+                max_shm_size_mb: 2048,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+        let submit = |shm_size_mb: i32, memory_gb: i32| {
+            post_json(
+                app.clone(),
+                "/",
+                serde_json::json!({
+                    "type": "worker",
+                    "command": "python train.py",
+                    "memory_gb": memory_gb,
+                    "shm_size_mb": shm_size_mb
+                }),
+            )
+        };
+
+        let (status, body) = submit(1024, 4).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let job = state.job_repo.get(body["job_id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.shm_size_mb, Some(1024));
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.contains("--shm-size=1024m"));
+
+        // Zero, over the operator's cap, and over the job's own memory
+        for (shm_size_mb, memory_gb) in [(0, 4), (4096, 8), (2000, 1)] {
+            let (status, body) = submit(shm_size_mb, memory_gb).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", shm_size_mb);
+            assert_eq!(body["error"], "invalid_shm_size");
+        }
     }
 
-    Ok(Json(serde_json::json!({
-        "job_id": id,
-        "status": "cancelled",
-        "message": "Job termination initiated"
-    })))
-}
+    #[tokio::test]
+    async fn test_kill_by_client_job_id() {
+        let state = crate::test_state().await;
+        state
+            .job_repo
+            .create(&sample_job("job_by_cid", JobStatus::Pending), Some("cancel-me"))
+            .await
+            .unwrap();
+        let app = routes().with_state(state.clone());
 
-/// GET /jobs/:id/output - Get job output
-async fn get_output(
-    Path(_id): Path<String>,
-) -> impl IntoResponse {
-    // TODO: Implement log retrieval
-    axum::Json(serde_json::json!({
-        "output": "",
-        "lines": 0,
-        "truncated": false,
-        "total_bytes": 0
-    }))
-}
+        let delete = |uri: &'static str| {
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .method("DELETE")
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
 
-/// GET /jobs/:id/artifacts - List job artifacts
-async fn list_artifacts(
-    Path(_id): Path<String>,
-) -> impl IntoResponse {
-    // TODO: Implement artifact listing
-    axum::Json(serde_json::json!({
-        "artifacts": [],
-        "total_size_bytes": 0,
-        "expires_at": "2026-01-21T11:35:00Z",
-        "copy_in_progress": false
-    }))
-}
+        let response = delete("/?client_job_id=cancel-me").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["job_id"], "job_by_cid");
+        let job = state.job_repo.get("job_by_cid").await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let response = delete("/?client_job_id=never-submitted").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "job_not_found");
+    }
+
+    #[tokio::test]
+    async fn test_timeline_merges_job_and_container_events() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let t0 = Utc::now() + chrono::Duration::seconds(10);
+        let at = |secs: i64| (t0 + chrono::Duration::seconds(secs)).to_rfc3339();
+        let lines = [
+            format!(r#"{{"Type":"container","Status":"create","Time":"{}"}}"#, at(0)),
+            format!(r#"{{"Type":"container","Status":"start","Time":"{}"}}"#, at(1)),
+            format!(r#"{{"Type":"container","Status":"exec","Time":"{}"}}"#, at(2)),
+            format!(
+                r#"{{"Type":"container","Status":"died","Time":"{}","ContainerExitCode":3}}"#,
+                at(3)
+            ),
+            // Same death reported again by an older podman
+            format!(r#"{{"Type":"container","Status":"die","Time":"{}"}}"#, at(3)),
+        ];
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                &format!("case \"$1\" in events) cat <<'EOF'\n{}\nEOF\n;; esac", lines.join("\n")),
+            )),
+            ..crate::test_state().await
+        };
+        state
+            .job_repo
+            .create(&sample_job("job_timeline", JobStatus::Pending), None)
+            .await
+            .unwrap();
+        state
+            .job_repo
+            .update_status("job_timeline", JobStatus::Running)
+            .await
+            .unwrap();
+        let app = routes().with_state(state);
+
+        let (status, body) = get_json(app, "/job_timeline/timeline").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["container_events_available"], true);
+        let events: Vec<(String, String)> = body["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                (
+                    e["source"].as_str().unwrap().to_string(),
+                    e["event"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        let expected = [
+            ("job", "pending"),
+            ("job", "running"),
+            ("container", "create"),
+            ("container", "start"),
+            ("container", "died"),
+        ];
+        assert_eq!(
+            events,
+            expected.map(|(s, e)| (s.to_string(), e.to_string()))
+        );
+        assert_eq!(body["events"][4]["detail"], "exit code 3");
+    }
+
+    #[test]
+    fn test_kill_grace_defaults_to_config() {
+        let config = JobConfig {
+            kill_grace_seconds: 45,
+            ..JobConfig::default()
+        };
+
+        assert_eq!(kill_grace_seconds(None, &config), 45);
+        assert_eq!(kill_grace_seconds(Some(3), &config), 3);
+        assert_eq!(kill_grace_seconds(Some(0), &config), 0);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_unknown_fields() {
+        let state = AppState {
+            job_config: JobConfig {
+                strict_requests: true,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "cpu": 4}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "unknown_fields");
+        assert_eq!(body["fields"], serde_json::json!(["cpu"]));
+
+        // Known fields pass the strict check and reach normal validation
+        let (status, body) =
+            post_json(app, "/", serde_json::json!({"type": "robot", "cpus": 4})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_enum_value");
+    }
+
+    #[tokio::test]
+    async fn test_agent_concurrency_cap() {
+        let state = AppState {
+            job_config: JobConfig {
+                max_concurrent_agents: Some(1),
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        assert!(check_agent_limit(&state).await.is_ok());
+
+        let agent = Job {
+            job_type: JobType::Agent,
+            ..sample_job("job_agent1", JobStatus::Pending)
+        };
+        state.job_repo.create(&agent, None).await.unwrap();
+        state.job_repo.update_status("job_agent1", JobStatus::Running).await.unwrap();
+
+        let (status, body) = post_json(
+            routes().with_state(state.clone()),
+            "/",
+            serde_json::json!({"type": "agent", "task": "fix the bug", "git_branch": "fix"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["error"], "agent_limit_reached");
+
+        // Running workers don't count against the agent cap
+        state.job_repo.update_status("job_agent1", JobStatus::Completed).await.unwrap();
+        let worker = sample_job("job_worker1", JobStatus::Pending);
+        state.job_repo.create(&worker, None).await.unwrap();
+        state.job_repo.update_status("job_worker1", JobStatus::Running).await.unwrap();
+        assert!(check_agent_limit(&state).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_job_submissions_rate_limited_per_user() {
+        let state = AppState {
+            submission_limiter: std::sync::Arc::new(SubmissionLimiter::new(2)),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+        let scheduled = |client_job_id: &str| {
+            serde_json::json!({
+                "type": "worker",
+                "command": "make",
+                "start_after": (Utc::now() + chrono::Duration::hours(1)).to_rfc3339(),
+                "client_job_id": client_job_id
+            })
+        };
+
+        // Requests that validation rejects don't use up the limit
+        for _ in 0..3 {
+            let (status, body) =
+                post_json(app.clone(), "/", serde_json::json!({"type": "robot"})).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_enum_value");
+        }
+        for client_job_id in ["rate-1", "rate-2"] {
+            let (status, _) = post_json(app.clone(), "/", scheduled(client_job_id)).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+        let (status, body) = post_json(app.clone(), "/", scheduled("rate-3")).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["error"], "job_rate_limited");
+        assert!(body["retry_after_seconds"].as_u64().unwrap() >= 1);
+
+        // A retry of a job already submitted still gets that job back
+        let (status, body) = post_json(app.clone(), "/", scheduled("rate-1")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["created"], false);
+
+        // Other endpoints are unaffected
+        let (status, _) = get_json(app.clone(), "/").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_json(app, "/can-admit?cpus=1&memory_gb=1").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_validate_upload_reports_each_state() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            upload_config: crate::models::UploadConfig {
+                upload_dir: uploads.path().to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            ..crate::test_state().await
+        };
+        let repo = &state.upload_repo;
+        for id in ["upload_v_ready", "upload_v_busy", "upload_v_consumed", "upload_v_expired"] {
+            std::fs::create_dir(uploads.path().join(id)).unwrap();
+            repo.create(id, "default").await.unwrap();
+            repo.finalize(id, 10, 1).await.unwrap();
+        }
+        repo.create("upload_v_uploading", "default").await.unwrap();
+        repo.create("upload_v_gone", "default").await.unwrap();
+        repo.finalize("upload_v_gone", 10, 1).await.unwrap();
+        let agent = Job {
+            job_type: JobType::Agent,
+            files_id: Some("upload_v_busy".to_string()),
+            ..sample_job("job_v_agent", JobStatus::Running)
+        };
+        state.job_repo.create(&agent, None).await.unwrap();
+        repo.consume("upload_v_consumed", "job_v_agent").await.unwrap();
+        repo.expire_used("upload_v_expired").await.unwrap();
+        let app = routes().with_state(state);
+
+        let validate = |job_type: &str, files_id: &str| {
+            let body = serde_json::json!({
+                "type": job_type, "command": "make", "task": "fix", "files_id": files_id
+            });
+            post_json(app.clone(), "/?validate_upload=true", body)
+        };
+
+        let (status, body) = validate("worker", "upload_v_ready").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["usable"], true);
+        assert!(body["error"].is_null());
+        assert_eq!(body["state"], "finalized");
+        assert_eq!(body["mount"], "ro");
+        assert_eq!(body["size_bytes"], 10);
+        assert_eq!(body["on_disk"], true);
+        assert!(body["disk_usage"]["total_bytes"].as_i64().unwrap() >= 10);
+
+        for (job_type, files_id, error) in [
+            ("worker", "upload_v_missing", "upload_not_found"),
+            ("worker", "upload_v_uploading", "upload_not_finalized"),
+            ("worker", "upload_v_consumed", "upload_not_finalized"),
+            ("worker", "upload_v_expired", "upload_not_finalized"),
+            ("worker", "upload_v_gone", "upload_missing_on_disk"),
+            ("worker", "upload_v_busy", "upload_in_use"),
+            ("agent", "upload_v_busy", "upload_in_use"),
+        ] {
+            let (status, body) = validate(job_type, files_id).await;
+            assert_eq!(status, StatusCode::OK, "{}", files_id);
+            assert_eq!(body["usable"], false, "{}", files_id);
+            assert_eq!(body["error"], error, "{}", files_id);
+        }
+        let (_, body) = validate("agent", "upload_v_busy").await;
+        assert_eq!(body["mount"], "rw");
+        assert_eq!(body["in_use_by"], serde_json::json!(["job_v_agent"]));
+
+        // Nothing was created, and the same answer stops a real submission
+        let (_, body) = get_json(app.clone(), "/").await;
+        assert_eq!(body["jobs"].as_array().unwrap().len(), 1);
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "agent", "task": "fix", "files_id": "upload_v_busy"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "upload_in_use");
+    }
+
+    #[tokio::test]
+    async fn test_agent_upload_quota_is_rechecked_at_job_time() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            upload_config: crate::models::UploadConfig {
+                upload_dir: uploads.path().to_string_lossy().to_string(),
+                max_user_disk_bytes: 1000,
+                ..Default::default()
+            },
+            ..crate::test_state().await
+        };
+        // Each fit the quota when finalized; together they are over it
+        for id in ["upload_q_first", "upload_q_second"] {
+            std::fs::create_dir(uploads.path().join(id)).unwrap();
+            state.upload_repo.create(id, "default").await.unwrap();
+            state.upload_repo.finalize(id, 600, 1).await.unwrap();
+        }
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "agent", "task": "fix", "files_id": "upload_q_first"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::INSUFFICIENT_STORAGE);
+        assert_eq!(body["error"], "insufficient_storage");
+
+        // Workers only mount the upload read-only, so they aren't held to it
+        let (_, body) = post_json(
+            app,
+            "/?validate_upload=true",
+            serde_json::json!({"type": "worker", "command": "make", "files_id": "upload_q_first"}),
+        )
+        .await;
+        assert_eq!(body["usable"], true);
+
+        // With the re-check off, only finalize enforces the quota
+        let app = routes().with_state(AppState {
+            job_config: JobConfig {
+                recheck_upload_quota: false,
+                ..JobConfig::default()
+            },
+            ..state
+        });
+        let (_, body) = post_json(
+            app,
+            "/?validate_upload=true",
+            serde_json::json!({"type": "agent", "task": "fix", "files_id": "upload_q_first"}),
+        )
+        .await;
+        assert_eq!(body["usable"], true);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_validate_upload_is_a_json_error() {
+        let app = routes().with_state(crate::test_state().await);
+        let (status, body) = post_json(
+            app,
+            "/?validate_upload=yes",
+            serde_json::json!({"type": "worker", "command": "make", "files_id": "upload_x"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_query");
+    }
+
+    #[tokio::test]
+    async fn test_finalized_upload_missing_on_disk() {
+        let uploads = tempfile::TempDir::new().unwrap();
+        let podman_dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                podman_dir.path(),
+                r#"case "$1" in run) echo ctr_upload ;; esac"#,
+            )),
+            upload_config: crate::models::UploadConfig {
+                upload_dir: uploads.path().to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            ..crate::test_state().await
+        };
+        state.upload_repo.create("upload_gone1", "default").await.unwrap();
+        state.upload_repo.finalize("upload_gone1", 10, 1).await.unwrap();
+        let body = serde_json::json!({"type": "worker", "command": "make", "files_id": "upload_gone1"});
+
+        let (status, response) = post_json(routes().with_state(state.clone()), "/", body.clone()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(response["error"], "upload_missing_on_disk");
+
+        // Operators can opt back into starting with an empty /work
+        let lenient = AppState {
+            job_config: JobConfig {
+                require_upload_on_disk: false,
+                ..JobConfig::default()
+            },
+            ..state.clone()
+        };
+        let (status, _) = post_json(routes().with_state(lenient), "/", body.clone()).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // With the directory back in place the check passes
+        std::fs::create_dir(uploads.path().join("upload_gone1")).unwrap();
+        let (status, _) = post_json(routes().with_state(state), "/", body).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_command_rejected() {
+        let state = AppState {
+            command_denylist: std::sync::Arc::new(
+                CommandDenylist::new(&["rm -rf /".to_string()]).unwrap(),
+            ),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state);
+
+        for body in [
+            serde_json::json!({"type": "worker", "command": "rm -rf /"}),
+            serde_json::json!({"type": "worker", "commands": ["make", "rm -rf / --no-preserve-root"]}),
+        ] {
+            let (status, body) = post_json(app.clone(), "/", body).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            assert_eq!(body["error"], "command_forbidden");
+        }
+    }
+
+    #[test]
+    fn test_lenient_mode_collects_unknown_fields() {
+        let req: CreateJobRequest =
+            serde_json::from_value(serde_json::json!({"type": "worker", "command": "make", "cpu": 4}))
+                .unwrap();
+        assert_eq!(req.cpus, 2);
+        assert_eq!(req.unknown_fields.keys().collect::<Vec<_>>(), ["cpu"]);
+    }
+
+    #[test]
+    fn test_worker_commands_validation() {
+        let request = |body: serde_json::Value| -> CreateJobRequest {
+            serde_json::from_value(body).unwrap()
+        };
+
+        let single = request(serde_json::json!({"type": "worker", "command": "make"}));
+        assert!(check_worker_commands(&single).is_ok());
+
+        let multi = request(serde_json::json!({"type": "worker", "commands": ["make", "make test"]}));
+        assert!(check_worker_commands(&multi).is_ok());
+
+        let both = request(serde_json::json!({"type": "worker", "command": "make", "commands": ["make"]}));
+        assert_eq!(check_worker_commands(&both).unwrap_err().0, "conflicting_commands");
+
+        let neither = request(serde_json::json!({"type": "worker"}));
+        assert_eq!(check_worker_commands(&neither).unwrap_err().0, "missing_command");
+
+        let empty = request(serde_json::json!({"type": "worker", "commands": []}));
+        assert_eq!(check_worker_commands(&empty).unwrap_err().0, "invalid_commands");
+
+        let blank = request(serde_json::json!({"type": "worker", "commands": ["make", " "]}));
+        assert_eq!(check_worker_commands(&blank).unwrap_err().0, "invalid_commands");
+    }
+
+    #[tokio::test]
+    async fn test_pending_reason_reported_for_pending_jobs() {
+        let state = crate::test_state().await;
+        let reasons = [
+            ("job_pr_res", PendingReason::Resources, "resources"),
+            ("job_pr_dep", PendingReason::Dependency, "dependency"),
+            ("job_pr_pause", PendingReason::Paused, "paused"),
+        ];
+        for (id, reason, _) in reasons {
+            state
+                .job_repo
+                .create(&sample_job(id, JobStatus::Pending), None)
+                .await
+                .unwrap();
+            state.job_repo.set_pending_reason(id, Some(reason)).await.unwrap();
+        }
+
+        let app = routes().with_state(state.clone());
+        for (id, _, expected) in reasons {
+            let (status, body) = get_json(app.clone(), &format!("/{}", id)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["pending_reason"], expected);
+        }
+
+        // A stale reason is hidden once the job has left Pending
+        state
+            .job_repo
+            .update_status("job_pr_res", JobStatus::Running)
+            .await
+            .unwrap();
+        let (_, body) = get_json(app, "/job_pr_res").await;
+        assert!(body.get("pending_reason").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_input_job_validation() {
+        let artifacts_dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::with_paths(
+                "/tmp/uploads".to_string(),
+                artifacts_dir.path().display().to_string(),
+                "/tmp/spire.sock".to_string(),
+                "/tmp/token.sock".to_string(),
+            )),
+            ..crate::test_state().await
+        };
+        let caller = Caller::default();
+        let code = |r: Result<(), (StatusCode, Json<serde_json::Value>)>| {
+            let (status, Json(body)) = r.unwrap_err();
+            (status, body["error"].as_str().unwrap().to_string())
+        };
+
+        assert_eq!(
+            code(check_input_job(&state, "job_nope", &caller).await),
+            (StatusCode::NOT_FOUND, "job_not_found".to_string())
+        );
+
+        for (id, status) in [("job_in_run", JobStatus::Running), ("job_in_done", JobStatus::Completed)] {
+            state.job_repo.create(&sample_job(id, status), None).await.unwrap();
+        }
+        assert_eq!(
+            code(check_input_job(&state, "job_in_run", &caller).await),
+            (StatusCode::CONFLICT, "input_job_not_completed".to_string())
+        );
+        assert_eq!(
+            code(check_input_job(&state, "job_in_done", &caller).await),
+            (StatusCode::CONFLICT, "input_artifacts_missing".to_string())
+        );
+
+        std::fs::create_dir(artifacts_dir.path().join("job_in_done")).unwrap();
+        assert!(check_input_job(&state, "job_in_done", &caller).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_node_selector_matched_against_node_labels() {
+        let node_labels: BTreeMap<String, String> =
+            [("zone", "lab"), ("arch", "amd64")].map(|(k, v)| (k.to_string(), v.to_string())).into();
+        let selector = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        assert!(unmatched_node_selector(&selector(&[]), &node_labels).is_empty());
+        assert!(unmatched_node_selector(&selector(&[("zone", "lab")]), &node_labels).is_empty());
+        let mismatched = selector(&[("zone", "cloud"), ("gpu", "a100"), ("arch", "amd64")]);
+        assert_eq!(unmatched_node_selector(&mismatched, &node_labels), ["gpu", "zone"]);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_selector ;; esac"#,
+            )),
+            job_config: JobConfig {
+                node_labels,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "node_selector": {"gpu": "a100"}}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "node_selector_mismatch");
+        assert_eq!(body["unmatched"], serde_json::json!(["gpu"]));
+
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "node_selector": {"zone": "lab"}}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let job = state.job_repo.get(body["job_id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.node_selector.get("zone").map(String::as_str), Some("lab"));
+    }
+
+    #[test]
+    fn test_check_secret_refs() {
+        let refs = |pairs: &[(&str, &str)]| -> Vec<SecretRef> {
+            pairs
+                .iter()
+                .map(|(env_name, reference)| SecretRef {
+                    env_name: env_name.to_string(),
+                    reference: reference.to_string(),
+                })
+                .collect()
+        };
+
+        assert!(check_secret_refs(&refs(&[("API_TOKEN", "ci/token"), ("_X1", "x")])).is_ok());
+        for bad in [
+            refs(&[("1TOKEN", "t")]),
+            refs(&[("API-TOKEN", "t")]),
+            refs(&[("", "t")]),
+            refs(&[("FLASHPODS_JOB_ID", "t")]),
+            refs(&[("TOKEN", "a"), ("TOKEN", "b")]),
+            refs(&[("TOKEN", "")]),
+        ] {
+            assert!(check_secret_refs(&bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_env_lists_keys_and_masks_secrets() {
+        let state = crate::test_state().await;
+        let agent = Job {
+            job_type: JobType::Agent,
+            command: None,
+            task: Some("fix the build".to_string()),
+            git_branch: Some("main".to_string()),
+            secret_refs: vec![SecretRef {
+                env_name: "API_TOKEN".to_string(),
+                reference: "ci/token".to_string(),
+            }],
+            ..sample_job("job_env_agent", JobStatus::Running)
+        };
+        state.job_repo.create(&agent, None).await.unwrap();
+        state
+            .job_repo
+            .create(&sample_job("job_env_worker", JobStatus::Running), None)
+            .await
+            .unwrap();
+        let app = routes().with_state(state);
+
+        let (status, body) = get_json(app.clone(), "/job_env_agent/env").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["env"],
+            serde_json::json!({
+                "API_TOKEN": "[redacted]",
+                "FLASHPODS_GIT_BRANCH": "main",
+                "FLASHPODS_JOB_ID": "job_env_agent",
+                "FLASHPODS_TASK": "fix the build"
+            })
+        );
+        assert_eq!(body["redacted"], serde_json::json!(["API_TOKEN"]));
+        assert!(!body.to_string().contains("ci/token"));
+
+        let (_, body) = get_json(app.clone(), "/job_env_worker/env").await;
+        assert_eq!(body["env"], serde_json::json!({}));
+
+        let (status, _) = get_json(app, "/job_env_missing/env").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_secret_refs_resolved_at_start_and_not_stored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let secrets_dir = dir.path().join("secrets");
+        std::fs::create_dir(&secrets_dir).unwrap();
+        std::fs::write(secrets_dir.join("token"), "s3cret\n").unwrap();
+        let env_out = dir.path().join("env.out");
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                &format!(
+                    r#"case "$1" in run)
+  while [ $# -gt 0 ]; do
+    if [ "$1" = --env-file ]; then stat -c %a "$2" > {out}; cat "$2" >> {out}; echo "$2" >> {out}; fi
+    shift
+  done
+  echo "$API_TOKEN" >> {out}
+  echo ctr_secret ;;
+esac"#,
+                    out = env_out.display()
+                ),
+            )),
+            secrets: std::sync::Arc::new(crate::secrets::FileSecretProvider::new(&secrets_dir)),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({
+                "type": "worker",
+                "command": "make deploy",
+                "secret_refs": [{"env_name": "API_TOKEN", "ref": "token"}]
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // The value reaches podman in a private env file that is gone once
+        // the container exists, and never podman's argv, environment or the DB
+        let seen = std::fs::read_to_string(&env_out).unwrap();
+        let seen: Vec<&str> = seen.lines().collect();
+        assert_eq!(seen[..2], ["600", "API_TOKEN=s3cret"]);
+        assert!(!std::path::Path::new(seen[2]).exists());
+        assert_eq!(seen[3], "");
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.contains("--env-file"));
+        assert!(!calls.contains("s3cret"));
+        let job = state
+            .job_repo
+            .get(body["job_id"].as_str().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.secret_refs[0].reference, "token");
+
+        // A ref that doesn't resolve fails the job at start
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({
+                "type": "worker",
+                "command": "make deploy",
+                "secret_refs": [{"env_name": "API_TOKEN", "ref": "missing"}]
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"], "container_start_failed");
+        assert!(body["message"].as_str().unwrap().contains("Secret not found: missing"));
+    }
+
+    #[tokio::test]
+    async fn test_start_failures_map_to_specific_statuses() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1 $*" in
+                    run*nosuch*) echo "Error: docker.io/library/nosuch:latest: image not known" >&2; exit 125 ;;
+                    run*private*) echo "Error: reading manifest: unauthorized: authentication required" >&2; exit 125 ;;
+                    run*) echo "Error: crun: fork: Cannot allocate memory" >&2; exit 126 ;;
+                esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        for (image, status, code) in [
+            ("nosuch:latest", StatusCode::BAD_REQUEST, "image_not_found"),
+            ("acme/private:1", StatusCode::FORBIDDEN, "image_pull_unauthorized"),
+            ("ubuntu:22.04", StatusCode::SERVICE_UNAVAILABLE, "insufficient_memory"),
+        ] {
+            let (got, body) = post_json(
+                app.clone(),
+                "/",
+                serde_json::json!({"type": "worker", "command": "make", "image": image}),
+            )
+            .await;
+            assert_eq!((got, body["error"].as_str().unwrap()), (status, code), "{}", image);
+        }
+
+        // The failed job keeps the actionable message
+        let jobs = state.job_repo.list(Some("failed"), 10).await.unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert!(jobs
+            .iter()
+            .any(|job| job.error.as_deref().unwrap().starts_with("Image not found, check the image name and tag")));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_categorized_and_permanent_ones_dead_lettered() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1 $*" in
+                    run*nosuch*) echo "Error: nosuch:latest: image not known" >&2; exit 125 ;;
+                    run*busy*) echo "Error: crun: Cannot allocate memory" >&2; exit 126 ;;
+                    run*) echo ctr_crash ;;
+                    inspect*) echo '[{"Id":"ctr_crash","State":{"Status":"exited","ExitCode":2}}]' ;;
+                esac"#,
+            )),
+            job_config: JobConfig {
+                startup_confirm_ms: 10,
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        for (image, command, secret_refs) in [
+            ("nosuch:latest", "make", serde_json::json!([])),
+            ("busy:1", "make", serde_json::json!([])),
+            ("ubuntu:22.04", "false", serde_json::json!([])),
+            ("ubuntu:22.04", "make", serde_json::json!([{"env_name": "TOKEN", "ref": "missing"}])),
+        ] {
+            post_json(
+                app.clone(),
+                "/",
+                serde_json::json!({
+                    "type": "worker",
+                    "command": command,
+                    "image": image,
+                    "secret_refs": secret_refs
+                }),
+            )
+            .await;
+        }
+
+        // Start failures don't return the job id; find each job by what it ran
+        let failed = state.job_repo.list(Some("failed"), 10).await.unwrap();
+        assert_eq!(failed.len(), 4);
+        let find = |pred: &dyn Fn(&Job) -> bool| failed.iter().find(|job| pred(job)).unwrap().clone();
+        let image = find(&|job| job.image == "nosuch:latest");
+        let busy = find(&|job| job.image == "busy:1");
+        let crash = find(&|job| job.command.as_deref() == Some("false"));
+        let secret = find(&|job| !job.secret_refs.is_empty());
+        assert_eq!(image.failure_category, Some(FailureCategory::Image));
+        assert_eq!(busy.failure_category, Some(FailureCategory::Resources));
+        assert_eq!(crash.failure_category, Some(FailureCategory::ExitCode));
+        assert_eq!(secret.failure_category, Some(FailureCategory::Secret));
+
+        let (_, body) = get_json(app.clone(), "/?dead_letter=true").await;
+        let mut dead: Vec<(&str, &str)> = body["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|job| (job["id"].as_str().unwrap(), job["failure_category"].as_str().unwrap()))
+            .collect();
+        dead.sort_by_key(|(_, category)| *category);
+        assert_eq!(dead, [(image.id.as_str(), "image"), (secret.id.as_str(), "secret")]);
+
+        // Only permanent failures are flagged on the job itself
+        let (_, body) = get_json(app.clone(), &format!("/{}", image.id)).await;
+        assert_eq!(body["dead_letter"], true);
+        let (_, body) = get_json(app, &format!("/{}", busy.id)).await;
+        assert_eq!(body["failure_category"], "resources");
+        assert!(body.get("dead_letter").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_output_source_follows_job_status() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let logs_dir = dir.path().join("logs");
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in
+  logs) echo 2024-01-15T10:00:00.000Z live1; echo 2024-01-15T10:00:01.000Z live2 ;;
+esac"#,
+            )),
+            job_config: JobConfig {
+                logs_dir: logs_dir.display().to_string(),
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+
+        let mut running = sample_job("job_out_run", JobStatus::Running);
+        running.container_id = Some("ctr_run".to_string());
+        state.job_repo.create(&running, None).await.unwrap();
+        state.job_repo.set_container_id("job_out_run", "ctr_run").await.unwrap();
+        for (id, status) in [
+            ("job_out_done", JobStatus::Completed),
+            ("job_out_pending", JobStatus::Pending),
+            ("job_out_cleaned", JobStatus::Cleaned),
+        ] {
+            state.job_repo.create(&sample_job(id, status), None).await.unwrap();
+        }
+        std::fs::create_dir_all(&logs_dir).unwrap();
+        std::fs::write(
+            logs_dir.join("job_out_run.log"),
+            "[10:00:00.000] live1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            logs_dir.join("job_out_done.log"),
+            "[10:00:00.000] one\n[10:00:00.001] two\n[10:00:00.002] three\n",
+        )
+        .unwrap();
+
+        let app = routes().with_state(state);
+
+        // Live container: podman only, the partial capture isn't appended
+        let (status, body) = get_json(app.clone(), "/job_out_run/output").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["source"], "podman");
+        assert_eq!(body["output"], "[10:00:00.000] live1\n[10:00:01.000] live2");
+        assert_eq!(body["total_bytes"], 42);
+        assert!(std::fs::read_to_string(dir.path().join("calls.log"))
+            .unwrap()
+            .contains("logs --timestamps --tail 100 ctr_run"));
+
+        let (status, body) = get_json(app.clone(), "/job_out_done/output?tail=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["source"], "file");
+        assert_eq!(body["lines"], 2);
+        assert_eq!(body["output"], "[10:00:00.001] two\n[10:00:00.002] three");
+        assert_eq!(body["total_bytes"], 59);
+
+        let (status, body) = get_json(app.clone(), "/job_out_pending/output").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "logs_not_available");
+
+        let (status, body) = get_json(app, "/job_out_cleaned/output").await;
+        assert_eq!(status, StatusCode::GONE);
+        assert_eq!(body["error"], "logs_deleted");
+    }
+
+    #[tokio::test]
+    async fn test_output_grep_filters_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let logs_dir = dir.path().join("logs");
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in logs) printf 'ok\nerror: a\nok\nerror: b\n' ;; esac"#,
+            )),
+            job_config: JobConfig {
+                logs_dir: logs_dir.display().to_string(),
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let mut running = sample_job("job_grep_run", JobStatus::Running);
+        running.container_id = Some("ctr_grep".to_string());
+        state.job_repo.create(&running, None).await.unwrap();
+        state.job_repo.set_container_id("job_grep_run", "ctr_grep").await.unwrap();
+        state
+            .job_repo
+            .create(&sample_job("job_grep_done", JobStatus::Completed), None)
+            .await
+            .unwrap();
+        std::fs::create_dir_all(&logs_dir).unwrap();
+        std::fs::write(
+            logs_dir.join("job_grep_done.log"),
+            "[10:00:00.000] compiling\n[10:00:01.000] warning: unused\n[10:00:02.000] done\n",
+        )
+        .unwrap();
+        let app = routes().with_state(state);
+
+        // Live logs are searched in full, with `tail` applied to the matches
+        let (status, body) = get_json(app.clone(), "/job_grep_run/output?grep=error&tail=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["output"], "error: b");
+        assert_eq!(body["matches"], serde_json::json!([{"line": 4, "text": "error: b"}]));
+        assert_eq!(body["match_count"], 2);
+        assert!(std::fs::read_to_string(dir.path().join("calls.log"))
+            .unwrap()
+            .contains("logs --timestamps ctr_grep"));
+
+        let (status, body) =
+            get_json(app.clone(), "/job_grep_done/output?grep=re:%5E%5C%5B%5B0-9:.%5D%2B%5D%20(warning%7Cdone)").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["source"], "file");
+        assert_eq!(body["match_count"], 2);
+        assert_eq!(body["matches"][0]["line"], 2);
+
+        let (status, body) = get_json(app, "/job_grep_done/output?grep=re:(oops").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_grep_pattern");
+    }
+
+    #[tokio::test]
+    async fn test_output_stream_subscriber_cap() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in logs) echo line1; sleep 5 ;; esac"#,
+            )),
+            log_streams: std::sync::Arc::new(crate::logs::LogStreams::new(1)),
+            ..crate::test_state().await
+        };
+        let mut running = sample_job("job_stream", JobStatus::Running);
+        running.container_id = Some("ctr_stream".to_string());
+        state.job_repo.create(&running, None).await.unwrap();
+        state.job_repo.set_container_id("job_stream", "ctr_stream").await.unwrap();
+        state
+            .job_repo
+            .create(&sample_job("job_stream_done", JobStatus::Completed), None)
+            .await
+            .unwrap();
+        let app = routes().with_state(state);
+
+        let get = |uri: &'static str| {
+            app.clone().oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        // The first subscriber holds the only slot while its response is open
+        let first = get("/job_stream/output/stream").await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let second = get("/job_stream/output/stream").await.unwrap();
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let finished = get("/job_stream_done/output/stream").await.unwrap();
+        assert_eq!(finished.status(), StatusCode::CONFLICT);
+        drop(first);
+    }
+
+    #[tokio::test]
+    async fn test_output_stream_needs_a_background_process_slot() {
+        let state = AppState {
+            processes: std::sync::Arc::new(crate::podman::ProcessSupervisor::new(1)),
+            ..crate::test_state().await
+        };
+        let mut running = sample_job("job_stream", JobStatus::Running);
+        running.container_id = Some("ctr_stream".to_string());
+        state.job_repo.create(&running, None).await.unwrap();
+        state.job_repo.set_container_id("job_stream", "ctr_stream").await.unwrap();
+        // e.g. another job's log capture
+        let _held = state.processes.try_acquire().unwrap();
+
+        let (status, body) = get_json(routes().with_state(state), "/job_stream/output/stream").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error"], "too_many_background_processes");
+    }
+
+    #[tokio::test]
+    async fn test_can_admit_reflects_usage() {
+        let state = crate::test_state().await;
+        let mut running = sample_job("job_admit", JobStatus::Running);
+        running.cpus = 12;
+        running.memory_gb = 20;
+        state.job_repo.create(&running, None).await.unwrap();
+        let app = routes().with_state(state);
+
+        let (status, body) = get_json(app.clone(), "/can-admit?cpus=4&memory_gb=8").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["admit"], true);
+        assert!(body["reason"].is_null());
+        assert_eq!(body["available"], serde_json::json!({"cpus": 4, "memory_gb": 12}));
+        assert_eq!(body["running_jobs"], 1);
+
+        let (_, body) = get_json(app.clone(), "/can-admit?cpus=5&memory_gb=8").await;
+        assert_eq!(body["admit"], false);
+        assert!(body["reason"].as_str().unwrap().contains("Insufficient CPU"));
+
+        // Sizes are clamped to the job type's limits, as on create
+        let (_, body) = get_json(app.clone(), "/can-admit?cpus=1&memory_gb=25").await;
+        assert_eq!(body["admit"], false);
+        assert_eq!(body["requested"], serde_json::json!({"cpus": 1, "memory_gb": 16}));
+        assert!(body["reason"].as_str().unwrap().contains("Insufficient memory"));
+
+        let (_, body) = get_json(app.clone(), "/can-admit?type=agent&cpus=8&memory_gb=-2147483648").await;
+        assert_eq!(body["admit"], true);
+        assert_eq!(body["requested"], serde_json::json!({"cpus": 4, "memory_gb": 1}));
+
+        let (status, body) = get_json(app, "/can-admit?type=robot").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_enum_value");
+    }
+
+    #[test]
+    fn test_check_capacity_does_not_overflow() {
+        let usage = ResourceUsage {
+            used_cpus: 1,
+            used_memory_gb: 1,
+            running_jobs: 1,
+        };
+        assert!(check_capacity(&usage, i32::MAX, 1).is_err());
+        assert!(check_capacity(&usage, 1, i32::MAX).is_err());
+        assert!(check_capacity(&usage, 1, 1).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_artifact_info_response_shape() {
+        let state = crate::test_state().await;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("myapp");
+        std::fs::write(&path, "hello").unwrap();
+
+        state
+            .job_repo
+            .create(&sample_job("job_art1", JobStatus::Completed), None)
+            .await
+            .unwrap();
+        state
+            .artifact_repo
+            .create("job_art1", "myapp", path.to_str().unwrap(), None, 5)
+            .await
+            .unwrap();
+
+        let app = routes().with_state(state);
+        let (status, body) = get_json(app.clone(), "/job_art1/artifacts/myapp/info").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "myapp");
+        assert_eq!(body["size_bytes"], 5);
+        assert_eq!(
+            body["sha256"],
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert!(body["created_at"].is_string());
+
+        let (status, body) = get_json(app, "/job_art1/artifacts/missing/info").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "artifact_not_found");
+    }
+
+    #[tokio::test]
+    async fn test_artifact_paths_limit_collection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), "")),
+            job_config: JobConfig {
+                artifact_paths: vec!["reports".to_string()],
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        let own = Job {
+            artifact_paths: vec!["output".to_string()],
+            ..sample_job("job_allow_own", JobStatus::Completed)
+        };
+        state.job_repo.create(&own, None).await.unwrap();
+        let default = sample_job("job_allow_default", JobStatus::Completed);
+        state.job_repo.create(&default, None).await.unwrap();
+        for job_id in ["job_allow_own", "job_allow_default"] {
+            let artifacts = std::path::PathBuf::from(state.podman.artifacts_path(job_id));
+            for path in ["output/app", "reports/junit.xml", "scratch/cache.bin"] {
+                std::fs::create_dir_all(artifacts.join(path).parent().unwrap()).unwrap();
+                std::fs::write(artifacts.join(path), "x").unwrap();
+            }
+        }
+        let app = routes().with_state(state);
+
+        let names = |body: serde_json::Value| -> Vec<String> {
+            body["artifacts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|a| a["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let (_, body) = get_json(app.clone(), "/job_allow_own/artifacts").await;
+        assert_eq!(names(body), ["output_app"]);
+        let (_, body) = get_json(app.clone(), "/job_allow_default/artifacts").await;
+        assert_eq!(names(body), ["reports_junit.xml"]);
+
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "artifact_paths": ["../etc"]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_artifact_paths");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_first_listings_agree() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), "")),
+            ..crate::test_state().await
+        };
+        state
+            .job_repo
+            .create(&sample_job("job_list_race", JobStatus::Completed), None)
+            .await
+            .unwrap();
+        let artifacts = std::path::PathBuf::from(state.podman.artifacts_path("job_list_race"));
+        std::fs::create_dir_all(&artifacts).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(artifacts.join(name), name).unwrap();
+        }
+        let app = routes().with_state(state.clone());
+
+        // Both listings find no rows yet and record the same files
+        let listings = futures_util::future::join_all(
+            (0..4).map(|_| get_json(app.clone(), "/job_list_race/artifacts")),
+        )
+        .await;
+        for (status, body) in listings {
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            assert_eq!(body["artifacts"].as_array().unwrap().len(), 3);
+        }
+        assert_eq!(state.artifact_repo.list("job_list_race").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_job_skips_artifacts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_ephemeral ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let app = routes().with_state(state.clone());
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/",
+            serde_json::json!({"type": "worker", "command": "make", "ephemeral": true}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let job_id = body["job_id"].as_str().unwrap().to_string();
+        let job = state.job_repo.get(&job_id).await.unwrap().unwrap();
+        assert!(job.ephemeral);
+
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        let run = calls.lines().find(|l| l.starts_with("run ")).unwrap();
+        assert!(!run.contains(":/artifacts:"));
+        let artifacts = std::path::PathBuf::from(state.podman.artifacts_path(&job_id));
+        assert!(!artifacts.exists());
+
+        // Even if something lands there, nothing is collected
+        state.job_repo.update_status(&job_id, JobStatus::Completed).await.unwrap();
+        std::fs::create_dir_all(&artifacts).unwrap();
+        std::fs::write(artifacts.join("stray.txt"), "x").unwrap();
+        let (status, body) = get_json(app.clone(), &format!("/{}/artifacts", job_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["artifacts"], serde_json::json!([]));
+
+        let (status, body) = post_json(
+            app,
+            "/",
+            serde_json::json!({"type": "agent", "task": "fix", "ephemeral": true}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "ephemeral_not_supported");
+    }
+
+    #[tokio::test]
+    async fn test_promoted_log_is_listed_as_an_artifact() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let logs_dir = dir.path().join("logs");
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(dir.path(), "")),
+            job_config: JobConfig {
+                logs_dir: logs_dir.display().to_string(),
+                ..JobConfig::default()
+            },
+            ..crate::test_state().await
+        };
+        std::fs::create_dir_all(&logs_dir).unwrap();
+        let mut jobs = Vec::new();
+        for (job_id, promote) in [("job_promote", true), ("job_promote_clash", true), ("job_keep", false)] {
+            let job = Job {
+                promote_log_to_artifact: promote,
+                ..sample_job(job_id, JobStatus::Completed)
+            };
+            state.job_repo.create(&job, None).await.unwrap();
+            std::fs::write(logs_dir.join(format!("{}.log", job_id)), "hello").unwrap();
+            let artifacts = std::path::PathBuf::from(state.podman.artifacts_path(job_id));
+            std::fs::create_dir_all(&artifacts).unwrap();
+            std::fs::write(artifacts.join("report.txt"), "ok").unwrap();
+            jobs.push(job);
+        }
+        // The job wrote a job.log of its own
+        let clash = std::path::PathBuf::from(state.podman.artifacts_path("job_promote_clash"));
+        std::fs::write(clash.join("job.log"), "mine").unwrap();
+        let app = routes().with_state(state.clone());
+
+        let names = |body: &serde_json::Value| -> Vec<String> {
+            body["artifacts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|a| a["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+        // Listed before capture ends, the job's files are recorded without
+        // the log; capture adds it once the log is complete
+        let (status, body) = get_json(app.clone(), "/job_promote/artifacts").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(names(&body), ["report.txt"]);
+        promote_job_log(&state, &jobs[0]).await;
+        let (_, body) = get_json(app.clone(), "/job_promote/artifacts").await;
+        assert_eq!(names(&body), ["job.log", "report.txt"]);
+        assert_eq!(body["total_size_bytes"], 7);
+        assert!(body["artifacts"][0].get("original_path").is_none());
+
+        // Served from a copy outside the container's mounts, so it outlives
+        // the log file
+        std::fs::remove_file(logs_dir.join("job_promote.log")).unwrap();
+        let (status, body) = get_json(app.clone(), "/job_promote/artifacts/job.log/info").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["size_bytes"], 5);
+        assert_eq!(
+            body["sha256"],
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+
+        promote_job_log(&state, &jobs[1]).await;
+        let (_, body) = get_json(app.clone(), "/job_promote_clash/artifacts").await;
+        let listed = names(&body);
+        assert_eq!(listed.len(), 3);
+        assert!(listed.contains(&"job.log".to_string()));
+        let promoted = listed.iter().find(|n| n.starts_with("job~")).unwrap();
+        let (_, body) = get_json(app.clone(), &format!("/job_promote_clash/artifacts/{}/info", promoted)).await;
+        assert_eq!(body["size_bytes"], 5);
+
+        let (_, body) = get_json(app.clone(), "/job_keep/artifacts").await;
+        assert_eq!(names(&body), ["report.txt"]);
+
+        // The copy goes with the artifacts
+        let promoted = crate::logs::promoted_log_path(&state.job_config.logs_dir, "job_promote");
+        assert!(promoted.exists());
+        assert!(cleanup::clean_job(&state, &jobs[0]).await.unwrap());
+        assert!(!promoted.exists());
+
+        // Nothing to promote without log capture or artifacts
+        for body in [
+            serde_json::json!({"type": "worker", "command": "make", "promote_log_to_artifact": true}),
+            serde_json::json!({"type": "worker", "command": "make", "promote_log_to_artifact": true, "ephemeral": true}),
+        ] {
+            let (status, body) = post_json(app.clone(), "/", body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "log_promotion_unavailable");
+        }
+    }
 
     #[test]
     fn test_resource_limits_clamp() {
@@ -447,4 +4416,18 @@ mod tests {
         assert_eq!(cpus, 4); // max for agent
         assert_eq!(mem, 8); // max for agent
     }
+
+    #[test]
+    fn test_agent_timeout_floor() {
+        let agent = ResourceLimits::for_job_type(JobType::Agent);
+        assert_eq!(agent.clamp(2, 4, 1).2, 10);
+        assert_eq!(agent.clamp(2, 4, 0).2, 10);
+        assert_eq!(agent.clamp(2, 4, 10).2, 10);
+        assert_eq!(agent.clamp(2, 4, 45).2, 45);
+
+        // Workers may still run for a single minute
+        let worker = ResourceLimits::for_job_type(JobType::Worker);
+        assert_eq!(worker.clamp(2, 4, 1).2, 1);
+        assert_eq!(worker.clamp(2, 4, 0).2, 1);
+    }
 }
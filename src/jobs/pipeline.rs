@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounds how many upload finalizations and container starts run at once.
+///
+/// Both stages hit the disk and podman hard, so a burst of finalize +
+/// create pairs is throttled here whatever the HTTP request rate. Callers
+/// beyond the depth wait for a slot rather than failing.
+pub struct PipelineGate {
+    slots: Arc<Semaphore>,
+}
+
+impl PipelineGate {
+    /// A depth of 0 leaves the pipeline unbounded
+    pub fn new(depth: usize) -> Self {
+        let permits = if depth == 0 { Semaphore::MAX_PERMITS } else { depth };
+        Self {
+            slots: Arc::new(Semaphore::new(permits)),
+        }
+    }
+
+    /// Wait for a slot; it is released when the permit is dropped
+    pub async fn enter(&self) -> OwnedSemaphorePermit {
+        self.slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("pipeline semaphore is never closed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pipeline_concurrency_is_bounded() {
+        let gate = Arc::new(PipelineGate::new(2));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (gate, active, peak) = (gate.clone(), active.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _slot = gate.enter().await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_depth_is_unbounded() {
+        let gate = PipelineGate::new(0);
+        let mut held = Vec::new();
+        for _ in 0..100 {
+            held.push(gate.enter().await);
+        }
+        assert_eq!(held.len(), 100);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Window over which job submissions are counted
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Per-user limit on `POST /jobs`, on top of the general API rate limit.
+///
+/// Each user may submit `max_per_minute` jobs in any sliding one-minute
+/// window. A limit of 0 disables the check.
+pub struct SubmissionLimiter {
+    max_per_minute: usize,
+    submissions: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl SubmissionLimiter {
+    pub fn new(max_per_minute: usize) -> Self {
+        Self {
+            max_per_minute,
+            submissions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn max_per_minute(&self) -> usize {
+        self.max_per_minute
+    }
+
+    /// Record a submission by `user_id`. Returns how long until the user may
+    /// submit again if they are over the limit; rejected attempts don't count.
+    pub fn check(&self, user_id: &str) -> Result<(), Duration> {
+        self.check_at(user_id, Instant::now())
+    }
+
+    fn check_at(&self, user_id: &str, now: Instant) -> Result<(), Duration> {
+        if self.max_per_minute == 0 {
+            return Ok(());
+        }
+
+        let mut submissions = self.submissions.lock().unwrap();
+        // Forget users whose window has emptied so the map doesn't grow forever
+        submissions.retain(|_, times| {
+            while times.front().is_some_and(|t| now.duration_since(*t) >= WINDOW) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = submissions.entry(user_id.to_string()).or_default();
+        if times.len() >= self.max_per_minute {
+            let oldest = *times.front().unwrap();
+            return Err(WINDOW - now.duration_since(oldest));
+        }
+        times.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_is_per_user_and_slides() {
+        let limiter = SubmissionLimiter::new(2);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("alice", start).is_ok());
+        assert!(limiter.check_at("alice", start + Duration::from_secs(10)).is_ok());
+        let retry = limiter
+            .check_at("alice", start + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(retry, Duration::from_secs(40));
+        // Other users have their own budget
+        assert!(limiter.check_at("bob", start + Duration::from_secs(20)).is_ok());
+
+        // The first submission has left the window
+        assert!(limiter.check_at("alice", start + Duration::from_secs(60)).is_ok());
+        assert!(limiter.check_at("alice", start + Duration::from_secs(61)).is_err());
+    }
+
+    #[test]
+    fn test_zero_disables_limit() {
+        let limiter = SubmissionLimiter::new(0);
+        for _ in 0..100 {
+            assert!(limiter.check("alice").is_ok());
+        }
+    }
+}
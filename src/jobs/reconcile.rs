@@ -0,0 +1,460 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use super::{spawn_log_capture, start_container};
+use crate::models::{FailureCategory, Job, JobStatus, JobType};
+use crate::podman::ContainerState;
+use crate::AppState;
+
+/// What startup reconciliation did with the jobs the DB believed were active
+#[derive(Debug, Default, PartialEq)]
+pub struct ReconcileSummary {
+    /// Container still present, left alone
+    pub alive: usize,
+    /// Container gone, re-created because the job is restartable
+    pub restarted: usize,
+    /// Container gone, job marked failed
+    pub failed: usize,
+    /// Container exited (or its exit was already recorded), job completed or
+    /// failed by its `success_exit_codes`
+    pub exited: usize,
+    /// Container state couldn't be determined, left for a later pass
+    pub skipped: usize,
+}
+
+/// Whether a job whose container vanished should be re-created rather than
+/// failed: it must be an opted-in worker that was running recently.
+pub fn should_restart(job: &Job, now: DateTime<Utc>, window: Duration) -> bool {
+    let last_active = job.started_at.unwrap_or(job.created_at);
+    job.restartable && job.job_type == JobType::Worker && now - last_active <= window
+}
+
+/// Whether a job has used up its `max_total_runtime_minutes` across attempts
+pub fn runtime_budget_exhausted(job: &Job, total_seconds: i64) -> bool {
+    job.max_total_runtime_minutes
+        .is_some_and(|max| total_seconds >= i64::from(max) * 60)
+}
+
+/// Reconcile jobs recorded as starting/running against podman after the API
+/// (and possibly the host) restarted.
+pub async fn reconcile_after_restart(state: &AppState) -> ReconcileSummary {
+    let mut summary = ReconcileSummary::default();
+
+    let jobs = match state.job_repo.get_active_jobs().await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            warn!("Skipping startup reconciliation: {}", e);
+            return summary;
+        }
+    };
+
+    let window = Duration::minutes(state.job_config.restart_window_minutes);
+    for job in jobs {
+        // A finished job's container may be long gone (e.g. autoremoved), so
+        // an exit we already recorded wins over whatever podman reports
+        if job.exit_code.is_some() || job.completed_at.is_some() {
+            finish_exited_job(state, &job, job.exit_code.unwrap_or(-1)).await;
+            summary.exited += 1;
+            continue;
+        }
+
+        let container = match &job.container_id {
+            Some(container_id) => state.podman.inspect_job_container(&job.id, container_id),
+            None => Ok(None),
+        };
+
+        match container {
+            Ok(Some(info)) if info.state == ContainerState::Exited => {
+                // No exit code reported: it still finished, just not cleanly
+                finish_exited_job(state, &job, info.exit_code.unwrap_or(-1)).await;
+                summary.exited += 1;
+            }
+            Ok(Some(_)) => summary.alive += 1,
+            Ok(None) if should_restart(&job, Utc::now(), window) => {
+                if restart_job(state, &job).await {
+                    summary.restarted += 1;
+                } else {
+                    summary.failed += 1;
+                }
+            }
+            Ok(None) => {
+                fail_job(
+                    state,
+                    &job.id,
+                    "Container lost while the job was active",
+                    FailureCategory::ContainerLost,
+                )
+                .await;
+                promote_log(state, &job).await;
+                summary.failed += 1;
+            }
+            Err(e) => {
+                warn!("Could not inspect container for job {}: {}", job.id, e);
+                summary.skipped += 1;
+            }
+        }
+    }
+
+    summary
+}
+
+/// Re-create a lost container. Returns `false` (and fails the job) if its
+/// runtime budget is used up or podman refuses.
+async fn restart_job(state: &AppState, job: &Job) -> bool {
+    // The lost attempt counts until now: we can't tell when it actually died
+    let attempt = job
+        .started_at
+        .map(|started| (Utc::now() - started).num_seconds())
+        .unwrap_or(0);
+    let total = match state.job_repo.add_runtime(&job.id, attempt).await {
+        Ok(total) => total,
+        Err(e) => {
+            warn!("Failed to record runtime for job {}: {}", job.id, e);
+            job.runtime_seconds + attempt
+        }
+    };
+    if runtime_budget_exhausted(job, total) {
+        let error = format!(
+            "Runtime budget exhausted: {} of {} minutes used across attempts",
+            total / 60,
+            job.max_total_runtime_minutes.unwrap_or_default()
+        );
+        fail_job(state, &job.id, &error, FailureCategory::RuntimeBudget).await;
+        return false;
+    }
+
+    let started = {
+        let (state, job) = (state.clone(), job.clone());
+        tokio::task::spawn_blocking(move || start_container(&state, &job))
+            .await
+            .map_err(|e| crate::podman::PodmanError::Command(e.to_string()))
+            .and_then(|result| result)
+    };
+    match started {
+        Ok(container_id) => {
+            info!("Restarted job {} in container {}", job.id, container_id);
+            if let Err(e) = state.job_repo.set_container_id(&job.id, &container_id).await {
+                warn!("Failed to set container ID: {}", e);
+            }
+            if let Err(e) = state.job_repo.update_status(&job.id, JobStatus::Running).await {
+                warn!("Failed to update job status: {}", e);
+            }
+            if state.job_config.capture_logs {
+                spawn_log_capture(state, job, container_id);
+            }
+            true
+        }
+        Err(e) => {
+            let error = format!("Failed to restart lost container: {}", e);
+            fail_job(state, &job.id, &error, super::start_failure_category(&e)).await;
+            false
+        }
+    }
+}
+
+/// Record the exit of a job whose container finished while nobody watched it
+async fn finish_exited_job(state: &AppState, job: &Job, exit_code: i32) {
+    let status = job.status_for_exit(exit_code);
+    info!("Job {} exited with code {}: {}", job.id, exit_code, status);
+    if let Err(e) = state.job_repo.set_exit_code(&job.id, exit_code).await {
+        warn!("Failed to set exit code: {}", e);
+    }
+    let failed = status == JobStatus::Failed;
+    if let Err(e) = state.job_repo.update_status(&job.id, status).await {
+        warn!("Failed to update job status: {}", e);
+    }
+    if failed {
+        super::record_failure_category(state, &job.id, FailureCategory::ExitCode).await;
+    }
+    promote_log(state, job).await;
+}
+
+/// Promote the log of a job that finished with no capture left to do it
+async fn promote_log(state: &AppState, job: &Job) {
+    if job.promote_log_to_artifact {
+        super::promote_job_log(state, job).await;
+    }
+}
+
+async fn fail_job(state: &AppState, job_id: &str, error: &str, category: FailureCategory) {
+    warn!("Failing job {}: {}", job_id, error);
+    if let Err(e) = state.job_repo.update_status(job_id, JobStatus::Failed).await {
+        warn!("Failed to update job status: {}", e);
+    }
+    if let Err(e) = state.job_repo.set_error(job_id, error).await {
+        warn!("Failed to set job error: {}", e);
+    }
+    super::record_failure_category(state, job_id, category).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JobRepository;
+    use crate::podman::PodmanService;
+    use std::sync::Arc;
+
+    fn job(restartable: bool, job_type: JobType, started_minutes_ago: i64) -> Job {
+        let started_at = Utc::now() - Duration::minutes(started_minutes_ago);
+        Job {
+            job_type,
+            restartable,
+            container_id: Some("ctr_old".to_string()),
+            created_at: started_at,
+            started_at: Some(started_at),
+            ..Job::sample(&JobRepository::generate_id(), "default", JobStatus::Running)
+        }
+    }
+
+    #[test]
+    fn test_should_restart() {
+        let window = Duration::minutes(60);
+        let now = Utc::now();
+
+        assert!(should_restart(&job(true, JobType::Worker, 5), now, window));
+        assert!(!should_restart(&job(false, JobType::Worker, 5), now, window));
+        assert!(!should_restart(&job(true, JobType::Agent, 5), now, window));
+        assert!(!should_restart(&job(true, JobType::Worker, 120), now, window));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_restarts_restartable_job() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // Every container is gone after the "reboot"; run creates a new one
+        let podman = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) echo "Error: no such container $4" >&2; exit 125 ;;
+  run) echo ctr_new ;;
+esac"#,
+        );
+        let state = AppState {
+            podman: Arc::new(podman),
+            ..crate::test_state().await
+        };
+
+        let restartable = job(true, JobType::Worker, 5);
+        let one_shot = job(false, JobType::Worker, 5);
+        for job in [&restartable, &one_shot] {
+            state.job_repo.create(job, None).await.unwrap();
+            state.job_repo.update_status(&job.id, JobStatus::Running).await.unwrap();
+            state.job_repo.set_container_id(&job.id, "ctr_old").await.unwrap();
+        }
+
+        let summary = reconcile_after_restart(&state).await;
+        assert_eq!(
+            summary,
+            ReconcileSummary {
+                restarted: 1,
+                failed: 1,
+                ..Default::default()
+            }
+        );
+
+        let restarted = state.job_repo.get(&restartable.id).await.unwrap().unwrap();
+        assert_eq!(restarted.status, JobStatus::Running);
+        assert_eq!(restarted.container_id.as_deref(), Some("ctr_new"));
+
+        let failed = state.job_repo.get(&one_shot.id).await.unwrap().unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert!(failed.error.is_some());
+        assert_eq!(failed.failure_category, Some(FailureCategory::ContainerLost));
+    }
+
+    #[tokio::test]
+    async fn test_restarted_job_output_is_captured() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let podman = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) echo "Error: no such container $4" >&2; exit 125 ;;
+  run) echo ctr_new ;;
+  logs) echo "second attempt" ;;
+esac"#,
+        );
+        let logs_dir = dir.path().join("logs");
+        let state = AppState {
+            podman: Arc::new(podman),
+            job_config: crate::models::JobConfig {
+                capture_logs: true,
+                logs_dir: logs_dir.display().to_string(),
+                ..Default::default()
+            },
+            ..crate::test_state().await
+        };
+        let restartable = job(true, JobType::Worker, 5);
+        state.job_repo.create(&restartable, None).await.unwrap();
+        state.job_repo.update_status(&restartable.id, JobStatus::Running).await.unwrap();
+
+        assert_eq!(reconcile_after_restart(&state).await.restarted, 1);
+
+        let log = crate::logs::log_path(&state.job_config.logs_dir, &restartable.id);
+        for _ in 0..50 {
+            if std::fs::read_to_string(&log).is_ok_and(|text| text.contains("second attempt")) {
+                let job = state.job_repo.get(&restartable.id).await.unwrap().unwrap();
+                assert!(job.log_created_at.is_some());
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("restarted job's output was never captured");
+    }
+
+    #[tokio::test]
+    async fn test_runtime_budget_stops_retries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let podman = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) echo "Error: no such container $4" >&2; exit 125 ;;
+  run) echo ctr_new ;;
+esac"#,
+        );
+        let state = AppState {
+            podman: Arc::new(podman),
+            ..crate::test_state().await
+        };
+        let budgeted = Job {
+            max_total_runtime_minutes: Some(25),
+            ..job(true, JobType::Worker, 0)
+        };
+        state.job_repo.create(&budgeted, None).await.unwrap();
+        state.job_repo.update_status(&budgeted.id, JobStatus::Running).await.unwrap();
+
+        // Each attempt runs ten minutes before its container is lost
+        let lose_attempt = || async {
+            sqlx::query("UPDATE jobs SET started_at = ? WHERE id = ?")
+                .bind((Utc::now() - Duration::minutes(10)).to_rfc3339())
+                .bind(&budgeted.id)
+                .execute(state.db.inner())
+                .await
+                .unwrap();
+            reconcile_after_restart(&state).await
+        };
+
+        for attempt in 1..=2 {
+            assert_eq!(lose_attempt().await.restarted, 1);
+            let job = state.job_repo.get(&budgeted.id).await.unwrap().unwrap();
+            assert_eq!(job.status, JobStatus::Running);
+            assert!((attempt * 600..attempt * 600 + 5).contains(&job.runtime_seconds));
+        }
+
+        // The third lost attempt takes the total past 25 minutes
+        assert_eq!(lose_attempt().await.failed, 1);
+        let job = state.job_repo.get(&budgeted.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert!(job.is_dead_letter());
+        assert!(job.error.unwrap().starts_with("Runtime budget exhausted"));
+        assert!(job.runtime_seconds >= 1800);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_finishes_exited_jobs_by_success_exit_codes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let podman = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) echo '[{"Id":"ctr_done","State":{"Status":"exited","ExitCode":3}}]' ;;
+esac"#,
+        );
+        let state = AppState {
+            podman: Arc::new(podman),
+            ..crate::test_state().await
+        };
+
+        // Exit code 3 is a success only for the job that lists it
+        let tolerant = Job {
+            success_exit_codes: vec![0, 3],
+            ..job(false, JobType::Worker, 5)
+        };
+        let strict = job(false, JobType::Worker, 5);
+        for job in [&tolerant, &strict] {
+            state.job_repo.create(job, None).await.unwrap();
+            state.job_repo.update_status(&job.id, JobStatus::Running).await.unwrap();
+            state.job_repo.set_container_id(&job.id, "ctr_done").await.unwrap();
+        }
+
+        let summary = reconcile_after_restart(&state).await;
+        assert_eq!(
+            summary,
+            ReconcileSummary {
+                exited: 2,
+                ..Default::default()
+            }
+        );
+
+        let completed = state.job_repo.get(&tolerant.id).await.unwrap().unwrap();
+        assert_eq!(completed.status, JobStatus::Completed);
+        assert_eq!(completed.exit_code, Some(3));
+        assert!(completed.completed_at.is_some());
+
+        let failed = state.job_repo.get(&strict.id).await.unwrap().unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.exit_code, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_trusts_recorded_exits_and_exits_without_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // The recorded job's container was autoremoved; the other one exited
+        // without podman reporting a code
+        let podman = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) case "$4" in
+    ctr_nocode) echo '[{"Id":"ctr_nocode","State":{"Status":"exited"}}]' ;;
+    *) echo "Error: no such container $4" >&2; exit 125 ;;
+  esac ;;
+  run) echo ctr_new ;;
+esac"#,
+        );
+        let state = AppState {
+            podman: Arc::new(podman),
+            ..crate::test_state().await
+        };
+
+        let recorded = job(true, JobType::Worker, 5);
+        let no_code = job(true, JobType::Worker, 5);
+        for (job, container_id) in [(&recorded, "ctr_gone"), (&no_code, "ctr_nocode")] {
+            state.job_repo.create(job, None).await.unwrap();
+            state.job_repo.update_status(&job.id, JobStatus::Running).await.unwrap();
+            state.job_repo.set_container_id(&job.id, container_id).await.unwrap();
+        }
+        state.job_repo.set_exit_code(&recorded.id, 0).await.unwrap();
+
+        let summary = reconcile_after_restart(&state).await;
+        assert_eq!(
+            summary,
+            ReconcileSummary {
+                exited: 2,
+                ..Default::default()
+            }
+        );
+
+        let completed = state.job_repo.get(&recorded.id).await.unwrap().unwrap();
+        assert_eq!(completed.status, JobStatus::Completed);
+        assert_eq!(completed.container_id.as_deref(), Some("ctr_gone"));
+        assert_eq!(completed.failure_category, None);
+
+        let failed = state.job_repo.get(&no_code.id).await.unwrap().unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.exit_code, Some(-1));
+        assert_eq!(failed.failure_category, Some(FailureCategory::ExitCode));
+
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(!calls.lines().any(|l| l.starts_with("run")));
+    }
+
+    #[test]
+    fn test_runtime_budget_exhausted() {
+        let unlimited = job(true, JobType::Worker, 0);
+        assert!(!runtime_budget_exhausted(&unlimited, i64::MAX));
+
+        let budgeted = Job {
+            max_total_runtime_minutes: Some(10),
+            ..unlimited
+        };
+        assert!(!runtime_budget_exhausted(&budgeted, 599));
+        assert!(runtime_budget_exhausted(&budgeted, 600));
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use super::{check_capacity, start_job};
+use crate::db::ResourceUsage;
+use crate::models::{Job, PendingReason};
+use crate::AppState;
+
+/// Why a pending `job` can't start yet, or `None` if it can. Every pending
+/// reason comes from here and is recomputed on each pass, so it can't be
+/// left over from an earlier one.
+pub fn pending_reason(job: &Job, usage: &ResourceUsage, now: DateTime<Utc>) -> Option<PendingReason> {
+    if job.start_after.is_some_and(|start_after| start_after > now) {
+        Some(PendingReason::Scheduled)
+    } else if check_capacity(usage, job.cpus, job.memory_gb).is_err() {
+        Some(PendingReason::Resources)
+    } else {
+        None
+    }
+}
+
+/// Recompute and store why `job` is still pending; `None` means it can
+/// start now. Leaving `Pending` clears the stored reason.
+pub async fn update_pending_reason(state: &AppState, job: &Job) -> Option<PendingReason> {
+    let reason = match state.job_repo.get_resource_usage().await {
+        Ok(usage) => pending_reason(job, &usage, Utc::now()),
+        Err(e) => {
+            tracing::error!("Failed to get resource usage: {}", e);
+            Some(PendingReason::Resources)
+        }
+    };
+    if let Err(e) = state.job_repo.set_pending_reason(&job.id, reason).await {
+        tracing::error!("Failed to set pending reason for {}: {}", job.id, e);
+    }
+    reason
+}
+
+/// Start scheduled jobs whose `start_after` has passed, as far as free
+/// resources allow. Jobs that don't fit stay `Pending` with reason
+/// `resources` and are retried on the next pass. Returns how many were
+/// started.
+pub async fn promote_due_jobs(state: &AppState) -> usize {
+    let due = match state.job_repo.get_due_scheduled(Utc::now()).await {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::error!("Failed to load scheduled jobs: {}", e);
+            return 0;
+        }
+    };
+
+    let mut started = 0;
+    for job in due {
+        // Re-read usage per job: each start changes it
+        if update_pending_reason(state, &job).await.is_some() {
+            continue;
+        }
+
+        tracing::info!("Starting scheduled job {}", job.id);
+        let job_id = job.id.clone();
+        state.startups.begin(&job_id);
+        match start_job(state.clone(), job).await {
+            Ok(_) => started += 1,
+            Err((_, body)) => tracing::error!("Scheduled job {} failed to start: {}", job_id, body.0),
+        }
+    }
+    started
+}
+
+/// Periodically start scheduled jobs that have come due
+pub async fn scheduler_loop(state: AppState, interval_secs: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        promote_due_jobs(&state).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JobRepository;
+    use crate::models::{Job, JobStatus};
+
+    fn scheduled_job(start_after: chrono::DateTime<Utc>) -> Job {
+        Job {
+            cpus: 2,
+            memory_gb: 4,
+            start_after: Some(start_after),
+            pending_reason: Some(PendingReason::Scheduled),
+            ..Job::sample(&JobRepository::generate_id(), "default", JobStatus::Pending)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_due_jobs_are_started() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = AppState {
+            podman: std::sync::Arc::new(crate::podman::PodmanService::scripted(
+                dir.path(),
+                r#"case "$1" in run) echo ctr_sched ;; esac"#,
+            )),
+            ..crate::test_state().await
+        };
+        let due = scheduled_job(Utc::now() - chrono::Duration::seconds(1));
+        let later = scheduled_job(Utc::now() + chrono::Duration::hours(1));
+        state.job_repo.create(&due, None).await.unwrap();
+        state.job_repo.create(&later, None).await.unwrap();
+        state.job_repo.set_pending_reason(&due.id, Some(PendingReason::Scheduled)).await.unwrap();
+
+        assert_eq!(promote_due_jobs(&state).await, 1);
+        let due = state.job_repo.get(&due.id).await.unwrap().unwrap();
+        assert_eq!(due.status, JobStatus::Running);
+        assert_eq!(due.container_id.as_deref(), Some("ctr_sched"));
+        // Starting cleared the reason it was pending for
+        assert_eq!(due.pending_reason, None);
+        let later = state.job_repo.get(&later.id).await.unwrap().unwrap();
+        assert_eq!(later.status, JobStatus::Pending);
+
+        // A second pass finds nothing new to start
+        assert_eq!(promote_due_jobs(&state).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_due_job_waits_for_resources() {
+        let state = crate::test_state().await;
+        let mut big = scheduled_job(Utc::now() - chrono::Duration::seconds(1));
+        big.cpus = 64;
+        state.job_repo.create(&big, None).await.unwrap();
+
+        assert_eq!(promote_due_jobs(&state).await, 0);
+        let big = state.job_repo.get(&big.id).await.unwrap().unwrap();
+        assert_eq!(big.status, JobStatus::Pending);
+        assert_eq!(big.pending_reason, Some(PendingReason::Resources));
+    }
+
+    #[test]
+    fn test_pending_reason_follows_time_and_capacity() {
+        let now = Utc::now();
+        let usage = |used_cpus| ResourceUsage {
+            used_cpus,
+            used_memory_gb: 0,
+            running_jobs: 0,
+        };
+        let job = scheduled_job(now + chrono::Duration::minutes(5));
+        assert_eq!(pending_reason(&job, &usage(0), now), Some(PendingReason::Scheduled));
+
+        // Once due, only capacity matters, and freed capacity clears it
+        let later = now + chrono::Duration::minutes(10);
+        let full = usage(crate::jobs::HOST_MAX_CPUS);
+        assert_eq!(pending_reason(&job, &full, later), Some(PendingReason::Resources));
+        assert_eq!(pending_reason(&job, &usage(0), later), None);
+    }
+}
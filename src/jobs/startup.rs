@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Tracks jobs whose container is being started so that a cancel arriving
+/// mid-start is never lost.
+///
+/// `create_job` registers the job before calling podman and reports the
+/// container once it exists; `kill_job` requests cancellation. Both sides go
+/// through the same lock, so exactly one of them observes the other and is
+/// responsible for stopping the container.
+#[derive(Default)]
+pub struct StartupTracker {
+    jobs: Mutex<HashMap<String, StartupState>>,
+    /// Jobs cancelled because the request that started them went away
+    abandoned: Mutex<HashSet<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StartupState {
+    Starting,
+    Started(String),
+    Cancelled,
+}
+
+/// Result of a cancel request against the tracker
+#[derive(Debug, Clone, PartialEq)]
+pub enum CancelOutcome {
+    /// The job was still starting; the starter will stop any container it creates
+    BeforeStart,
+    /// The container already exists and the caller must stop it
+    Started(String),
+    /// The job is not currently starting
+    NotTracked,
+}
+
+impl StartupTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job that is about to start its container
+    pub fn begin(&self, job_id: &str) {
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(job_id.to_string(), StartupState::Starting);
+    }
+
+    /// Whether a cancel has been requested for a starting job
+    pub fn is_cancelled(&self, job_id: &str) -> bool {
+        matches!(
+            self.jobs.lock().unwrap().get(job_id),
+            Some(StartupState::Cancelled)
+        )
+    }
+
+    /// Record the started container. Returns `false` if the job was cancelled
+    /// in the meantime, in which case the caller must stop the container.
+    pub fn mark_started(&self, job_id: &str, container_id: &str) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get(job_id) {
+            Some(StartupState::Cancelled) => false,
+            _ => {
+                jobs.insert(
+                    job_id.to_string(),
+                    StartupState::Started(container_id.to_string()),
+                );
+                true
+            }
+        }
+    }
+
+    /// Request cancellation of a job that may still be starting
+    pub fn request_cancel(&self, job_id: &str) -> CancelOutcome {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get(job_id).cloned() {
+            Some(StartupState::Starting) | Some(StartupState::Cancelled) => {
+                jobs.insert(job_id.to_string(), StartupState::Cancelled);
+                CancelOutcome::BeforeStart
+            }
+            Some(StartupState::Started(container_id)) => CancelOutcome::Started(container_id),
+            None => CancelOutcome::NotTracked,
+        }
+    }
+
+    /// Cancel a job whose creating request went away, remembering why so
+    /// the cancellation can free its idempotency key
+    pub fn abandon(&self, job_id: &str) -> CancelOutcome {
+        let outcome = self.request_cancel(job_id);
+        if outcome == CancelOutcome::BeforeStart {
+            self.abandoned.lock().unwrap().insert(job_id.to_string());
+        }
+        outcome
+    }
+
+    /// Whether the job's startup was cancelled by `abandon`; forgets it
+    pub fn take_abandoned(&self, job_id: &str) -> bool {
+        self.abandoned.lock().unwrap().remove(job_id)
+    }
+
+    /// Stop tracking a job once its startup has been fully recorded
+    pub fn finish(&self, job_id: &str) {
+        self.jobs.lock().unwrap().remove(job_id);
+    }
+}
+
+/// Cancels a job's startup when dropped, unless disarmed first.
+///
+/// Held by the request that began the startup, so a request dropped on client
+/// disconnect (or abandoned at its deadline) cancels the work it started.
+pub struct CancelOnDrop {
+    tracker: Arc<StartupTracker>,
+    job_id: Option<String>,
+}
+
+impl CancelOnDrop {
+    pub fn new(tracker: Arc<StartupTracker>, job_id: String) -> Self {
+        Self {
+            tracker,
+            job_id: Some(job_id),
+        }
+    }
+
+    /// The startup finished while the request was still waiting on it
+    pub fn disarm(mut self) {
+        self.job_id = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(job_id) = self.job_id.take() {
+            if self.tracker.abandon(&job_id) == CancelOutcome::BeforeStart {
+                tracing::info!("Request for job {} went away, cancelling startup", job_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_before_start_blocks_start() {
+        let tracker = StartupTracker::new();
+        tracker.begin("job_a");
+
+        assert_eq!(tracker.request_cancel("job_a"), CancelOutcome::BeforeStart);
+        assert!(tracker.is_cancelled("job_a"));
+        assert!(!tracker.mark_started("job_a", "ctr1"));
+    }
+
+    #[test]
+    fn test_cancel_after_start_returns_container() {
+        let tracker = StartupTracker::new();
+        tracker.begin("job_b");
+        assert!(tracker.mark_started("job_b", "ctr2"));
+
+        assert_eq!(
+            tracker.request_cancel("job_b"),
+            CancelOutcome::Started("ctr2".to_string())
+        );
+        tracker.finish("job_b");
+        assert_eq!(tracker.request_cancel("job_b"), CancelOutcome::NotTracked);
+    }
+
+    #[test]
+    fn test_cancel_on_drop() {
+        let tracker = Arc::new(StartupTracker::new());
+        tracker.begin("job_c");
+        tracker.begin("job_d");
+
+        drop(CancelOnDrop::new(tracker.clone(), "job_c".to_string()));
+        CancelOnDrop::new(tracker.clone(), "job_d".to_string()).disarm();
+
+        assert!(tracker.is_cancelled("job_c"));
+        assert!(!tracker.is_cancelled("job_d"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_create_cancel_race_has_single_owner() {
+        for i in 0..200 {
+            let tracker = Arc::new(StartupTracker::new());
+            let job_id = format!("job_{}", i);
+            tracker.begin(&job_id);
+
+            let starter = {
+                let tracker = tracker.clone();
+                let job_id = job_id.clone();
+                tokio::spawn(async move { tracker.mark_started(&job_id, "ctr") })
+            };
+            let canceller = {
+                let tracker = tracker.clone();
+                let job_id = job_id.clone();
+                tokio::spawn(async move { tracker.request_cancel(&job_id) })
+            };
+
+            let started = starter.await.unwrap();
+            let outcome = canceller.await.unwrap();
+
+            // Exactly one side must end up responsible for stopping the container
+            match outcome {
+                CancelOutcome::BeforeStart => assert!(!started),
+                CancelOutcome::Started(ref id) => {
+                    assert!(started);
+                    assert_eq!(id, "ctr");
+                }
+                CancelOutcome::NotTracked => panic!("job should be tracked"),
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use chrono::Duration;
+
+use crate::models::JobEvent;
+
+/// Entries from the same source with the same event this close together are
+/// the same occurrence seen twice, e.g. when podman replays its event log
+const DUPLICATE_WINDOW_MS: i64 = 1000;
+
+/// Merge the job's status transitions with its container events into one
+/// time-ordered timeline, dropping duplicates. Ties keep job events first.
+pub fn merge_timeline(
+    job_events: Vec<JobEvent>,
+    container_events: Vec<JobEvent>,
+) -> Vec<JobEvent> {
+    let mut events: Vec<JobEvent> = job_events.into_iter().chain(container_events).collect();
+    events.sort_by_key(|e| e.at);
+
+    let window = Duration::milliseconds(DUPLICATE_WINDOW_MS);
+    let mut merged: Vec<JobEvent> = Vec::with_capacity(events.len());
+    for event in events {
+        let duplicate = merged
+            .iter()
+            .rev()
+            .take_while(|kept| event.at - kept.at <= window)
+            .any(|kept| kept.source == event.source && kept.event == event.event);
+        if !duplicate {
+            merged.push(event);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EventSource;
+    use chrono::{DateTime, Utc};
+
+    fn event(at: DateTime<Utc>, source: EventSource, name: &str) -> JobEvent {
+        JobEvent {
+            at,
+            source,
+            event: name.to_string(),
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_orders_and_dedups() {
+        let t0 = Utc::now();
+        let ms = Duration::milliseconds;
+        let app = vec![
+            event(t0, EventSource::Job, "pending"),
+            event(t0 + ms(100), EventSource::Job, "starting"),
+            event(t0 + ms(900), EventSource::Job, "running"),
+            event(t0 + ms(5000), EventSource::Job, "completed"),
+        ];
+        let container = vec![
+            event(t0 + ms(300), EventSource::Container, "create"),
+            event(t0 + ms(600), EventSource::Container, "start"),
+            // Replayed by podman
+            event(t0 + ms(650), EventSource::Container, "start"),
+            event(t0 + ms(4900), EventSource::Container, "died"),
+            // A restart well after the first start is kept
+            event(t0 + ms(7000), EventSource::Container, "start"),
+        ];
+
+        let merged = merge_timeline(app, container);
+        let merged: Vec<(EventSource, &str)> =
+            merged.iter().map(|e| (e.source, e.event.as_str())).collect();
+        assert_eq!(
+            merged,
+            vec![
+                (EventSource::Job, "pending"),
+                (EventSource::Job, "starting"),
+                (EventSource::Container, "create"),
+                (EventSource::Container, "start"),
+                (EventSource::Job, "running"),
+                (EventSource::Container, "died"),
+                (EventSource::Job, "completed"),
+                (EventSource::Container, "start"),
+            ]
+        );
+    }
+}
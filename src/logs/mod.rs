@@ -0,0 +1,410 @@
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::models::JobStatus;
+
+mod stream;
+
+pub use stream::{LogStreams, StreamError};
+
+/// Prefix of the line appended when a log hits its size cap
+pub const TRUNCATION_MARKER: &str = "[TRUNCATED - log exceeded";
+
+/// Where `GET /jobs/:id/output` reads a job's output from.
+///
+/// The two sources are never merged: the captured file mirrors everything
+/// podman logs, so serving one or the other avoids duplicate lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogSource {
+    /// The on-disk capture, complete once the container has exited
+    File,
+    /// `podman logs` on the live container
+    Podman,
+    /// Nothing to read yet
+    None,
+}
+
+/// Pick the log source for a job.
+///
+/// Running jobs prefer podman so clients see output the capture hasn't
+/// flushed yet; once the job is over the container is removed, by `--rm` or
+/// the cleanup sweep, and the file is the only complete record.
+pub fn select_source(status: &JobStatus, has_container: bool, file_exists: bool) -> LogSource {
+    match status {
+        JobStatus::Pending => LogSource::None,
+        JobStatus::Starting | JobStatus::Running if has_container => LogSource::Podman,
+        _ if file_exists => LogSource::File,
+        _ => LogSource::None,
+    }
+}
+
+/// Path of a job's captured log under `logs_dir`
+pub fn log_path(logs_dir: &str, job_id: &str) -> PathBuf {
+    Path::new(logs_dir).join(format!("{}.log", job_id))
+}
+
+/// Artifact name a promoted log is listed under
+pub const PROMOTED_LOG_ARTIFACT: &str = "job.log";
+
+/// Copy of a job's captured log kept for `promote_log_to_artifact`. It lives
+/// under `logs_dir`, out of the container's reach, and is deleted with the
+/// job's artifacts rather than by log retention.
+pub fn promoted_log_path(logs_dir: &str, job_id: &str) -> PathBuf {
+    Path::new(logs_dir).join("promoted").join(format!("{}.log", job_id))
+}
+
+/// Copy the captured log at `log_path` to `promoted_path`, returning its
+/// size, or `None` if there is no log
+pub async fn promote_log(log_path: &Path, promoted_path: &Path) -> std::io::Result<Option<u64>> {
+    if !tokio::fs::try_exists(log_path).await? {
+        return Ok(None);
+    }
+    if let Some(dir) = promoted_path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::copy(log_path, promoted_path).await.map(Some)
+}
+
+/// Timestamp prefix of a captured log line, e.g. `[10:30:00.123]`
+fn line_timestamp(at: DateTime<Utc>) -> impl std::fmt::Display {
+    at.format("[%H:%M:%S%.3f]")
+}
+
+/// Rewrite `podman logs --timestamps` output in the captured file's format.
+///
+/// podman prints the container's stdout and stderr on its own two streams,
+/// which arrive concatenated; ordering by timestamp interleaves them as the
+/// capture did. Lines without a timestamp are kept after the line before.
+pub fn normalize_podman_logs(raw: &str) -> String {
+    let mut last = None;
+    let mut lines: Vec<(Option<DateTime<Utc>>, String)> = raw
+        .lines()
+        .map(|line| {
+            let parsed = line.split_once(' ').and_then(|(stamp, text)| {
+                DateTime::parse_from_rfc3339(stamp)
+                    .ok()
+                    .map(|at| (at.with_timezone(&Utc), text))
+            });
+            match parsed {
+                Some((at, text)) => {
+                    last = Some(at);
+                    (last, format!("{} {}", line_timestamp(at), text))
+                }
+                None => (last, line.to_string()),
+            }
+        })
+        .collect();
+    lines.sort_by_key(|(at, _)| *at);
+
+    let mut log = String::new();
+    for (_, line) in lines {
+        log.push_str(&line);
+        log.push('\n');
+    }
+    log
+}
+
+/// The last `n` lines of `text`, and how many were returned
+pub fn tail_lines(text: &str, n: usize) -> (String, usize) {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(n);
+    let tail = &lines[start..];
+    (tail.join("\n"), tail.len())
+}
+
+/// Longest `grep` pattern accepted by `LineFilter::parse`
+pub const MAX_GREP_PATTERN_LEN: usize = 256;
+
+/// Cap on a compiled `grep` regex. The regex engine matches in linear time,
+/// so bounding its size bounds the cost per line.
+const GREP_REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// Server-side line filter for `GET /jobs/:id/output?grep=`
+#[derive(Debug)]
+pub enum LineFilter {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl LineFilter {
+    /// Parse a `grep` pattern: a plain substring, or a regex when prefixed
+    /// with `re:` (as in the command denylist)
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("Pattern is empty".to_string());
+        }
+        if pattern.len() > MAX_GREP_PATTERN_LEN {
+            return Err(format!(
+                "Pattern is longer than {} bytes",
+                MAX_GREP_PATTERN_LEN
+            ));
+        }
+        match pattern.strip_prefix("re:") {
+            Some(re) => RegexBuilder::new(re)
+                .size_limit(GREP_REGEX_SIZE_LIMIT)
+                .build()
+                .map(LineFilter::Regex)
+                .map_err(|e| format!("Invalid regex: {}", e)),
+            None => Ok(LineFilter::Substring(pattern.to_string())),
+        }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            LineFilter::Substring(s) => line.contains(s.as_str()),
+            LineFilter::Regex(re) => re.is_match(line),
+        }
+    }
+}
+
+/// Lines of `text` matching `filter` with their 1-based line numbers, keeping
+/// the last `n`, and how many lines matched in total
+pub fn grep_lines<'a>(text: &'a str, filter: &LineFilter, n: usize) -> (Vec<(usize, &'a str)>, usize) {
+    let matches: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| filter.is_match(line))
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+    let total = matches.len();
+    let start = total.saturating_sub(n);
+    (matches[start..].to_vec(), total)
+}
+
+/// Whether a captured log ended in a truncation marker
+pub fn is_truncated(log: &str) -> bool {
+    log.lines()
+        .next_back()
+        .is_some_and(|line| line.contains(TRUNCATION_MARKER))
+}
+
+/// Outcome of capturing a container's output
+#[derive(Debug, PartialEq)]
+pub struct CaptureSummary {
+    pub bytes_written: u64,
+    pub truncated: bool,
+}
+
+/// Follow a container's output with `podman logs -f` and mirror it, with
+/// timestamps, into `log_path` until the container exits.
+///
+/// The container keeps its own log, so `podman logs` still works alongside
+/// the capture. Stops writing (but keeps draining) once `max_bytes` is
+/// reached, leaving a truncation marker as the last line. `slot` is the
+/// `ProcessSupervisor` slot the `podman logs -f` holds while it runs.
+pub async fn capture_logs(
+    podman: &crate::podman::PodmanService,
+    container_id: &str,
+    log_path: &Path,
+    max_bytes: u64,
+    slot: OwnedSemaphorePermit,
+) -> std::io::Result<CaptureSummary> {
+    if let Some(parent) = log_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .await?;
+
+    let mut follower = PodmanLogFollower::spawn(podman, container_id, slot)?;
+
+    let mut summary = CaptureSummary {
+        bytes_written: 0,
+        truncated: false,
+    };
+    while let Some(line) = follower.next_line().await {
+        if summary.truncated {
+            continue;
+        }
+
+        let timestamp = line_timestamp(Utc::now());
+        let log_line = format!("{} {}\n", timestamp, line);
+        if summary.bytes_written + log_line.len() as u64 > max_bytes {
+            let marker = format!(
+                "{} {} {} MB limit]\n",
+                timestamp,
+                TRUNCATION_MARKER,
+                max_bytes / (1024 * 1024)
+            );
+            file.write_all(marker.as_bytes()).await?;
+            summary.truncated = true;
+            continue;
+        }
+        file.write_all(log_line.as_bytes()).await?;
+        summary.bytes_written += log_line.len() as u64;
+    }
+
+    file.flush().await?;
+    follower.child.wait().await?;
+    Ok(summary)
+}
+
+/// A running `podman logs -f`, read line by line
+struct PodmanLogFollower {
+    child: tokio::process::Child,
+    stdout: Option<tokio::io::Lines<BufReader<tokio::process::ChildStdout>>>,
+    stderr: Option<tokio::io::Lines<BufReader<tokio::process::ChildStderr>>>,
+    /// Released when the follower (and so its killed child) is dropped
+    _slot: OwnedSemaphorePermit,
+}
+
+impl PodmanLogFollower {
+    fn spawn(
+        podman: &crate::podman::PodmanService,
+        container_id: &str,
+        slot: OwnedSemaphorePermit,
+    ) -> std::io::Result<Self> {
+        let mut child = podman
+            .async_command()
+            .args(["logs", "-f", container_id])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        Ok(Self {
+            stdout: child.stdout.take().map(|s| BufReader::new(s).lines()),
+            stderr: child.stderr.take().map(|s| BufReader::new(s).lines()),
+            child,
+            _slot: slot,
+        })
+    }
+
+    /// Next line from either stream, in the order lines arrive; `None` once
+    /// both have closed
+    async fn next_line(&mut self) -> Option<String> {
+        while self.stdout.is_some() || self.stderr.is_some() {
+            let line = tokio::select! {
+                line = next_line(&mut self.stdout), if self.stdout.is_some() => {
+                    if line.is_none() {
+                        self.stdout = None;
+                    }
+                    line
+                }
+                line = next_line(&mut self.stderr), if self.stderr.is_some() => {
+                    if line.is_none() {
+                        self.stderr = None;
+                    }
+                    line
+                }
+            };
+            if line.is_some() {
+                return line;
+            }
+        }
+        None
+    }
+}
+
+async fn next_line<R: tokio::io::AsyncBufRead + Unpin>(
+    lines: &mut Option<tokio::io::Lines<R>>,
+) -> Option<String> {
+    match lines {
+        Some(lines) => lines.next_line().await.ok().flatten(),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_source() {
+        // Nothing exists before the container starts
+        assert_eq!(select_source(&JobStatus::Pending, false, false), LogSource::None);
+        // Live containers are read through podman, even if a capture exists
+        assert_eq!(select_source(&JobStatus::Running, true, true), LogSource::Podman);
+        assert_eq!(select_source(&JobStatus::Starting, true, false), LogSource::Podman);
+        // Still starting without a container: whatever was captured so far
+        assert_eq!(select_source(&JobStatus::Starting, false, true), LogSource::File);
+        // Terminal jobs only have the file
+        assert_eq!(select_source(&JobStatus::Completed, true, true), LogSource::File);
+        assert_eq!(select_source(&JobStatus::Failed, false, true), LogSource::File);
+        assert_eq!(select_source(&JobStatus::Cancelled, false, false), LogSource::None);
+    }
+
+    #[test]
+    fn test_normalize_podman_logs() {
+        // stdout, then the stderr podman replays after it
+        let raw = "2024-01-15T10:30:00.100000000Z out1\n\
+                   2024-01-15T10:30:02.300000000Z out2\n\
+                   2024-01-15T12:30:01.200000000+02:00 err1\n\
+                   no timestamp\n";
+        assert_eq!(
+            normalize_podman_logs(raw),
+            "[10:30:00.100] out1\n[10:30:01.200] err1\nno timestamp\n[10:30:02.300] out2\n"
+        );
+        assert_eq!(normalize_podman_logs(""), "");
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), ("b\nc".to_string(), 2));
+        assert_eq!(tail_lines("a\nb", 10), ("a\nb".to_string(), 2));
+        assert_eq!(tail_lines("", 5), (String::new(), 0));
+    }
+
+    #[test]
+    fn test_grep_lines() {
+        let log = "build ok\nerror: disk\nwarn\nERROR: net\nerror: cpu\n";
+
+        let substring = LineFilter::parse("error").unwrap();
+        assert_eq!(grep_lines(log, &substring, 10), (vec![(2, "error: disk"), (5, "error: cpu")], 2));
+        // `n` keeps the last matches but the count covers them all
+        assert_eq!(grep_lines(log, &substring, 1), (vec![(5, "error: cpu")], 2));
+
+        let regex = LineFilter::parse("re:(?i)^error: (disk|net)").unwrap();
+        assert_eq!(grep_lines(log, &regex, 10), (vec![(2, "error: disk"), (4, "ERROR: net")], 2));
+        // Without the prefix, regex syntax is matched literally
+        assert_eq!(grep_lines(log, &LineFilter::parse("^error").unwrap(), 10).1, 0);
+
+        assert!(LineFilter::parse("re:(unclosed").is_err());
+        assert!(LineFilter::parse("").is_err());
+        assert!(LineFilter::parse(&"a".repeat(MAX_GREP_PATTERN_LEN + 1)).is_err());
+        // Patterns that compile to something huge are refused
+        assert!(LineFilter::parse(r"re:\w{1000}\w{1000}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_capture_logs_mirrors_and_truncates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let podman = crate::podman::PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in logs) echo out1; echo err1 >&2; echo out2 ;; esac"#,
+        );
+
+        let processes = crate::podman::ProcessSupervisor::new(1);
+
+        let path = dir.path().join("logs/job_a.log");
+        let slot = processes.try_acquire().unwrap();
+        let summary = capture_logs(&podman, "ctr", &path, 1024, slot)
+            .await
+            .unwrap();
+        assert!(!summary.truncated);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 3);
+        for line in ["out1", "err1", "out2"] {
+            assert!(log.lines().any(|l| l.starts_with('[') && l.ends_with(line)));
+        }
+        assert!(!is_truncated(&log));
+
+        // A cap smaller than two lines keeps the first and marks the rest
+        // The first capture gave its slot back when it finished
+        let path = dir.path().join("logs/job_b.log");
+        let slot = processes.try_acquire().unwrap();
+        let summary = capture_logs(&podman, "ctr", &path, 25, slot)
+            .await
+            .unwrap();
+        assert!(summary.truncated);
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(is_truncated(&log));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use super::PodmanLogFollower;
+use crate::podman::{ProcessSupervisor, SupervisorError};
+
+/// Lines buffered per job for subscribers that fall behind
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Most recent lines replayed to a subscriber joining a running follower
+const REPLAY_LINES: usize = 1024;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum StreamError {
+    #[error("Job already has {max} log stream subscribers")]
+    TooManySubscribers { max: usize },
+    #[error(transparent)]
+    NoProcessSlot(#[from] SupervisorError),
+}
+
+/// Live log streams, with one `podman logs -f` per job shared by all of its
+/// subscribers through a broadcast channel.
+///
+/// A follower exits on its own once its container stops or the last
+/// subscriber goes away (its next send finds no receivers).
+pub struct LogStreams {
+    max_subscribers: usize,
+    followers: Mutex<HashMap<String, Follower>>,
+}
+
+struct Follower {
+    sender: LineSender,
+    task: JoinHandle<()>,
+}
+
+impl Follower {
+    fn is_live(&self) -> bool {
+        !self.task.is_finished() && self.sender.sender.receiver_count() > 0
+    }
+}
+
+/// Feeds a follower's lines to its subscribers, keeping the last
+/// `REPLAY_LINES` for those who join later
+#[derive(Clone)]
+pub struct LineSender {
+    sender: broadcast::Sender<String>,
+    replay: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LineSender {
+    fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            replay: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Publish a line; fails once no subscriber is left
+    pub fn send(&self, line: String) -> Result<(), broadcast::error::SendError<String>> {
+        // Sent under the lock so a joiner sees each line exactly once, either
+        // in its replay or on its receiver
+        let mut replay = self.replay.lock().unwrap();
+        if replay.len() == REPLAY_LINES {
+            replay.pop_front();
+        }
+        replay.push_back(line.clone());
+        self.sender.send(line).map(|_| ())
+    }
+
+    fn subscribe(&self) -> Subscription {
+        let replay = self.replay.lock().unwrap();
+        Subscription {
+            replay: replay.iter().cloned().collect(),
+            receiver: self.sender.subscribe(),
+        }
+    }
+}
+
+/// A subscriber's view of a job's stream: the recent lines it missed, then
+/// everything sent from now on
+#[derive(Debug)]
+pub struct Subscription {
+    pub replay: Vec<String>,
+    pub receiver: broadcast::Receiver<String>,
+}
+
+impl LogStreams {
+    pub fn new(max_subscribers: usize) -> Self {
+        Self {
+            max_subscribers,
+            followers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Subscribe to a container's output, starting its follower if no one
+    /// else is watching. A new follower needs a free `processes` slot;
+    /// joining a live one doesn't.
+    pub fn subscribe_podman(
+        &self,
+        job_id: &str,
+        podman: std::sync::Arc<crate::podman::PodmanService>,
+        container_id: &str,
+        processes: &ProcessSupervisor,
+    ) -> Result<Subscription, StreamError> {
+        let container_id = container_id.to_string();
+        self.subscribe(job_id, move |sender| {
+            let slot = processes.try_acquire()?;
+            Ok(tokio::spawn(async move {
+                let mut follower = match PodmanLogFollower::spawn(&podman, &container_id, slot) {
+                    Ok(follower) => follower,
+                    Err(e) => {
+                        tracing::error!("Failed to follow logs for {}: {}", container_id, e);
+                        return;
+                    }
+                };
+                while let Some(line) = follower.next_line().await {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }))
+        })
+    }
+
+    /// Subscribe to a job's stream; `start` spawns the follower feeding the
+    /// channel and is only called when there isn't a live one already
+    pub fn subscribe(
+        &self,
+        job_id: &str,
+        start: impl FnOnce(LineSender) -> Result<JoinHandle<()>, StreamError>,
+    ) -> Result<Subscription, StreamError> {
+        let mut followers = self.followers.lock().unwrap();
+
+        if let Some(follower) = followers.get(job_id).filter(|f| f.is_live()) {
+            if follower.sender.sender.receiver_count() >= self.max_subscribers {
+                return Err(StreamError::TooManySubscribers {
+                    max: self.max_subscribers,
+                });
+            }
+            return Ok(follower.sender.subscribe());
+        }
+
+        // Drop followers nobody is listening to any more, including this job's
+        followers.retain(|_, f| {
+            let live = f.is_live();
+            if !live {
+                f.task.abort();
+            }
+            live
+        });
+
+        let sender = LineSender::new();
+        let subscription = sender.subscribe();
+        let task = start(sender.clone())?;
+        followers.insert(job_id.to_string(), Follower { sender, task });
+        Ok(subscription)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_subscribers_share_follower_and_are_capped() {
+        let streams = LogStreams::new(2);
+        let started = Arc::new(AtomicUsize::new(0));
+        let (release, released) = tokio::sync::watch::channel(false);
+        let start = || {
+            let (started, mut released) = (started.clone(), released.clone());
+            move |sender: LineSender| {
+                started.fetch_add(1, Ordering::SeqCst);
+                Ok(tokio::spawn(async move {
+                    let _ = released.wait_for(|r| *r).await;
+                    let _ = sender.send("hello".to_string());
+                }))
+            }
+        };
+
+        let mut first = streams.subscribe("job_a", start()).unwrap().receiver;
+        let mut second = streams.subscribe("job_a", start()).unwrap().receiver;
+        assert_eq!(
+            streams.subscribe("job_a", start()).unwrap_err(),
+            StreamError::TooManySubscribers { max: 2 }
+        );
+        // The cap is per job
+        let _other = streams.subscribe("job_b", start()).unwrap();
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+
+        // Both subscribers see the single follower's output
+        release.send(true).unwrap();
+        assert_eq!(first.recv().await.unwrap(), "hello");
+        assert_eq!(second.recv().await.unwrap(), "hello");
+
+        // Once everyone has left, the next subscriber starts a fresh follower
+        drop((first, second));
+        let _again = streams.subscribe("job_a", start()).unwrap();
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_late_subscriber_gets_recent_lines() {
+        let streams = LogStreams::new(4);
+        let (sent, mut sent_rx) = tokio::sync::mpsc::channel(1);
+        let (more, mut more_rx) = tokio::sync::mpsc::channel::<()>(1);
+        let first = streams
+            .subscribe("job_a", |sender: LineSender| {
+                Ok(tokio::spawn(async move {
+                    for i in 0..REPLAY_LINES + 2 {
+                        sender.send(format!("line {}", i)).unwrap();
+                    }
+                    sent.send(()).await.unwrap();
+                    more_rx.recv().await;
+                    let _ = sender.send("after".to_string());
+                }))
+            })
+            .unwrap();
+        assert!(first.replay.is_empty());
+        sent_rx.recv().await.unwrap();
+
+        // The joiner replays the newest lines, then follows along
+        let mut late = streams.subscribe("job_a", |_| unreachable!()).unwrap();
+        assert_eq!(late.replay.len(), REPLAY_LINES);
+        assert_eq!(late.replay[0], "line 2");
+        assert_eq!(late.replay.last().unwrap(), &format!("line {}", REPLAY_LINES + 1));
+
+        more.send(()).await.unwrap();
+        assert_eq!(late.receiver.recv().await.unwrap(), "after");
+        drop(first);
+    }
+}
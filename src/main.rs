@@ -1,8 +1,8 @@
 use axum::{
     extract::{Request, State},
-    http::HeaderValue,
-    middleware::{from_fn, Next},
-    response::IntoResponse,
+    http::{HeaderValue, StatusCode},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
@@ -13,19 +13,30 @@ use std::time::Instant;
 use tokio::net::TcpListener;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use uuid::Uuid;
 
+mod admin;
 mod artifacts;
 mod db;
 mod jobs;
+mod logs;
 mod middleware;
 mod models;
 mod podman;
+mod resources;
+mod secrets;
 mod uploads;
+mod users;
 
-use db::{Database, JobRepository, UploadRepository};
-use models::UploadConfig;
-use podman::PodmanService;
+use db::{
+    ArtifactRepository, Database, JobCache, JobRepository, ResourceSampleRepository, UploadRepository,
+};
+use models::{JobConfig, UploadConfig};
+use jobs::{CommandDenylist, PipelineGate, StartupTracker, SubmissionLimiter};
+use logs::LogStreams;
+use middleware::MaintenanceMode;
+use uploads::DirStatsCache;
+use podman::{PodmanInfo, PodmanService, ProcessCounts, ProcessSupervisor};
+use secrets::{FileSecretProvider, SecretProvider};
 
 /// Application state
 #[derive(Clone)]
@@ -33,8 +44,27 @@ pub struct AppState {
     pub db: Database,
     pub upload_repo: Arc<UploadRepository>,
     pub job_repo: Arc<JobRepository>,
+    pub artifact_repo: Arc<ArtifactRepository>,
+    pub resource_samples: Arc<ResourceSampleRepository>,
     pub upload_config: UploadConfig,
+    pub job_config: JobConfig,
     pub podman: Arc<PodmanService>,
+    pub command_denylist: Arc<CommandDenylist>,
+    /// Resolves `secret_refs` when containers start
+    pub secrets: Arc<dyn SecretProvider>,
+    pub startups: Arc<StartupTracker>,
+    /// Per-user cap on `POST /jobs`, separate from the API rate limit
+    pub submission_limiter: Arc<SubmissionLimiter>,
+    /// Shared bound on upload finalizations and container starts
+    pub pipeline: Arc<PipelineGate>,
+    pub log_streams: Arc<LogStreams>,
+    pub processes: Arc<ProcessSupervisor>,
+    /// Recent upload directory walks for `GET /uploads/:id/progress`
+    pub upload_progress: Arc<DirStatsCache>,
+    /// While on, only health checks and admins are served
+    pub maintenance: Arc<MaintenanceMode>,
+    /// Podman host facts captured at startup, if podman could be queried
+    pub podman_info: Option<PodmanInfo>,
     pub start_time: Instant,
 }
 
@@ -53,9 +83,23 @@ async fn main() -> anyhow::Result<()> {
     info!("Database initialized");
 
     let upload_repo = Arc::new(UploadRepository::new(db.inner().clone()));
-    let job_repo = Arc::new(JobRepository::new(db.inner().clone()));
-    let upload_config = UploadConfig::default();
-    let podman = Arc::new(PodmanService::new());
+    let job_config = JobConfig::from_env();
+    let job_repo = Arc::new(JobRepository::new(db.inner().clone()).with_cache(JobCache::new(
+        job_config.job_cache_capacity,
+        std::time::Duration::from_secs(job_config.job_cache_ttl_secs),
+        std::time::Duration::from_secs(job_config.job_cache_terminal_ttl_secs),
+    )));
+    let artifact_repo = Arc::new(ArtifactRepository::new(db.inner().clone()));
+    let resource_samples = Arc::new(ResourceSampleRepository::new(db.inner().clone()));
+    let upload_config = UploadConfig::from_env();
+    let podman = Arc::new(
+        PodmanService::new()
+            .with_name_template(&job_config.container_name_template)?
+            .with_autoremove(job_config.container_autoremove)
+            .with_cgroup_mode(job_config.cgroup_mode.clone()),
+    );
+    let command_denylist = Arc::new(CommandDenylist::new(&job_config.forbidden_commands)?);
+    let secrets: Arc<dyn SecretProvider> = Arc::new(FileSecretProvider::new(&job_config.secrets_dir));
     let start_time = Instant::now();
 
     // Check podman availability
@@ -66,33 +110,175 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!("Podman not available - container operations will fail");
     }
 
+    let podman_info = match podman.info() {
+        Ok(info) => {
+            info!(
+                "Podman storage driver: {}, rootless: {}",
+                info.storage_driver, info.rootless
+            );
+            if !info.rootless {
+                tracing::warn!("Podman is running rootful; --userns=keep-id has no effect");
+            }
+            if info.storage_driver == "vfs" {
+                tracing::warn!("Podman is using the vfs storage driver; container starts will be slow");
+            }
+            Some(info)
+        }
+        Err(e) => {
+            tracing::warn!("Could not query podman info: {}", e);
+            None
+        }
+    };
+
+    let prewarm_images = job_config.prewarm_images.clone();
+    let prewarm_concurrency = job_config.prewarm_concurrency;
+    let prewarm_podman = podman.clone();
+
+    if job_config.db_maintenance_interval_secs > 0 {
+        tokio::spawn(db_maintenance_loop(
+            db.clone(),
+            job_repo.clone(),
+            job_config.db_maintenance_interval_secs,
+            job_config.db_maintenance_max_active_jobs,
+        ));
+    }
+
     let state = AppState {
         db,
         upload_repo,
         job_repo,
+        artifact_repo,
+        resource_samples,
         upload_config,
+        job_config: job_config.clone(),
         podman,
+        command_denylist,
+        secrets,
+        startups: Arc::new(StartupTracker::new()),
+        submission_limiter: Arc::new(SubmissionLimiter::new(job_config.max_submissions_per_minute)),
+        pipeline: Arc::new(PipelineGate::new(job_config.max_pipeline_depth)),
+        log_streams: Arc::new(LogStreams::new(job_config.max_log_subscribers)),
+        processes: Arc::new(ProcessSupervisor::new(job_config.max_background_processes)),
+        upload_progress: Arc::new(DirStatsCache::new()),
+        maintenance: Arc::new(MaintenanceMode::new(job_config.maintenance_mode)),
+        podman_info,
         start_time,
     };
 
+    // Catch up on jobs whose containers disappeared while we were down
+    let summary = jobs::reconcile_after_restart(&state).await;
+    info!(
+        "Startup reconciliation: {} alive, {} exited, {} restarted, {} failed, {} skipped",
+        summary.alive, summary.exited, summary.restarted, summary.failed, summary.skipped
+    );
+
+    // Start delayed jobs as they come due
+    tokio::spawn(jobs::scheduler_loop(
+        state.clone(),
+        state.job_config.scheduler_interval_secs,
+    ));
+
+    // Clean finished jobs, the uploads they used and their logs once their
+    // retention expires
+    if state.job_config.cleanup_interval_secs > 0 {
+        tokio::spawn(jobs::cleanup_loop(
+            state.clone(),
+            state.job_config.cleanup_interval_secs,
+        ));
+    }
+
+    // Keep a usage history for capacity planning
+    if state.job_config.resource_sample_interval_secs > 0 {
+        tokio::spawn(resources::sample_loop(
+            state.clone(),
+            state.job_config.resource_sample_interval_secs,
+        ));
+    }
+
     let app = Router::new()
         .route("/health", get(health))
+        .route("/health/detailed", get(health_detailed))
         .nest("/uploads", uploads::routes())
         .nest("/jobs", jobs::routes())
         .nest("/artifacts", artifacts::routes())
-        .layer(from_fn(request_headers))
+        .nest("/resources", resources::routes())
+        .nest("/users", users::routes())
+        .nest("/admin", admin::routes())
+        .nest("/maintenance", middleware::maintenance::routes())
+        // Layers run bottom-up: auth identifies the caller for the maintenance check
+        .layer(from_fn_with_state(state.clone(), middleware::maintenance_middleware))
+        .layer(from_fn_with_state(state.clone(), request_headers))
         .layer(from_fn(middleware::auth_middleware))
-        .with_state(state);
+        .with_state(state.clone());
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
     info!("listening on {}", addr);
 
     let listener = TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+
+    // Warm the image cache in the background once we're accepting connections
+    if !prewarm_images.is_empty() {
+        let podman = prewarm_podman;
+        tokio::spawn(async move {
+            let summary = podman::warm_images(&prewarm_images, prewarm_concurrency, move |image| {
+                let podman = podman.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || podman.ensure_image(&image))
+                        .await
+                        .map_err(|e| podman::PodmanError::Command(e.to_string()))?
+                }
+            })
+            .await;
+            info!(
+                "Image warm-up finished: {} pulled, {} failed",
+                summary.pulled, summary.failed
+            );
+        });
+    }
+
+    // Connection info gives handlers the client's address
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    );
+    tokio::select! {
+        served = server => served?,
+        _ = shutdown_signal() => {
+            info!("Shutting down");
+            if state.job_config.stop_jobs_on_shutdown {
+                match jobs::stop_active_jobs(&state).await {
+                    Ok(summary) => info!(
+                        "Stopped job containers: {} stopped, {} failed",
+                        summary.stopped, summary.failed
+                    ),
+                    Err(e) => tracing::error!("Failed to stop job containers: {}", e),
+                }
+            }
+        }
+    }
 
     Ok(())
 }
 
+/// Resolve on Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
+
 /// Health endpoint - no auth required
 async fn health(State(state): State<AppState>) -> impl IntoResponse {
     Json(HealthResponse {
@@ -109,9 +295,95 @@ struct HealthResponse {
     uptime_seconds: u64,
 }
 
-/// Middleware to add X-Request-Id and rate limiting headers
-async fn request_headers(request: Request, next: Next) -> impl IntoResponse {
-    let request_id = Uuid::new_v4().to_string();
+/// Detailed health endpoint - authenticated, includes host details
+async fn health_detailed(State(state): State<AppState>) -> impl IntoResponse {
+    Json(DetailedHealthResponse {
+        status: "healthy".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_seconds: state.start_time.elapsed().as_secs(),
+        podman: state.podman_info.clone(),
+        background_processes: state.processes.counts(),
+    })
+}
+
+#[derive(Serialize)]
+struct DetailedHealthResponse {
+    status: String,
+    version: String,
+    uptime_seconds: u64,
+    podman: Option<PodmanInfo>,
+    background_processes: ProcessCounts,
+}
+
+/// Periodically compact the database, skipping runs while jobs are busy
+async fn db_maintenance_loop(
+    db: Database,
+    job_repo: Arc<JobRepository>,
+    interval_secs: u64,
+    max_active_jobs: usize,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+    // The first tick fires immediately; don't compact during startup
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match job_repo.get_active_jobs().await {
+            Ok(active) if active.len() > max_active_jobs => {
+                info!(
+                    "Skipping database maintenance: {} active jobs (limit {})",
+                    active.len(),
+                    max_active_jobs
+                );
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("Skipping database maintenance: {}", e);
+                continue;
+            }
+        }
+
+        if let Err(e) = db::run_maintenance(&db).await {
+            tracing::error!("Database maintenance failed: {}", e);
+        }
+    }
+}
+
+/// Middleware to add X-Request-Id and rate limiting headers, rejecting
+/// requests whose headers exceed `max_header_bytes`
+async fn request_headers(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let max_header_bytes = state.job_config.max_header_bytes;
+    if max_header_bytes > 0 {
+        let header_bytes: usize = request
+            .headers()
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if header_bytes > max_header_bytes {
+            return (
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                Json(serde_json::json!({
+                    "error": "headers_too_large",
+                    "message": format!(
+                        "Request headers are {} bytes, more than the {} allowed",
+                        header_bytes, max_header_bytes
+                    )
+                })),
+            )
+                .into_response();
+        }
+    }
+
+    // A valid caller-supplied id is kept so clients can correlate requests
+    let request_id = middleware::RequestId::from_headers(request.headers());
+    // Handlers record it, e.g. as a job's submission source
+    request.extensions_mut().insert(request_id.clone());
 
     // Run the handler
     let mut response = next.run(request).await;
@@ -119,8 +391,8 @@ async fn request_headers(request: Request, next: Next) -> impl IntoResponse {
     // Add headers to response
     let headers = response.headers_mut();
     headers.insert(
-        "X-Request-Id",
-        HeaderValue::from_str(&request_id).unwrap_or_else(|_| HeaderValue::from_static("unknown")),
+        middleware::request_id::REQUEST_ID_HEADER,
+        HeaderValue::from_str(&request_id.0).unwrap_or_else(|_| HeaderValue::from_static("unknown")),
     );
     headers.insert("X-RateLimit-Limit", HeaderValue::from_static("100"));
     headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("95"));
@@ -128,3 +400,88 @@ async fn request_headers(request: Request, next: Next) -> impl IntoResponse {
 
     response
 }
+
+/// Application state backed by a fresh in-memory database, for handler tests
+#[cfg(test)]
+pub async fn test_state() -> AppState {
+    let db = db::init_db(":memory:").await.expect("Failed to create test database");
+
+    AppState {
+        upload_repo: Arc::new(UploadRepository::new(db.inner().clone())),
+        job_repo: Arc::new(JobRepository::new(db.inner().clone())),
+        artifact_repo: Arc::new(ArtifactRepository::new(db.inner().clone())),
+        resource_samples: Arc::new(ResourceSampleRepository::new(db.inner().clone())),
+        db,
+        upload_config: UploadConfig::default(),
+        job_config: JobConfig::default(),
+        podman: Arc::new(PodmanService::new()),
+        command_denylist: Arc::new(CommandDenylist::default()),
+        secrets: Arc::new(FileSecretProvider::new("/run/flashpods/secrets")),
+        startups: Arc::new(StartupTracker::new()),
+        submission_limiter: Arc::new(SubmissionLimiter::new(
+            JobConfig::default().max_submissions_per_minute,
+        )),
+        pipeline: Arc::new(PipelineGate::new(JobConfig::default().max_pipeline_depth)),
+        log_streams: Arc::new(LogStreams::new(JobConfig::default().max_log_subscribers)),
+        processes: Arc::new(ProcessSupervisor::new(JobConfig::default().max_background_processes)),
+        upload_progress: Arc::new(DirStatsCache::new()),
+        maintenance: Arc::new(MaintenanceMode::default()),
+        podman_info: None,
+        start_time: Instant::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn app(job_config: JobConfig) -> Router {
+        let state = AppState {
+            job_config,
+            ..test_state().await
+        };
+        Router::new()
+            .route("/echo", get(|| async { "ok" }))
+            .layer(from_fn_with_state(state.clone(), request_headers))
+            .with_state(state)
+    }
+
+    async fn send(app: Router, headers: &[(&str, String)]) -> Response {
+        let mut request = Request::builder().uri("/echo");
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed_when_valid() {
+        let app = app(JobConfig::default()).await;
+
+        let incoming = "client-trace-0042".to_string();
+        let response = send(app.clone(), &[("X-Request-Id", incoming.clone())]).await;
+        assert_eq!(response.headers()["X-Request-Id"], incoming.as_str());
+
+        let response = send(app, &[("X-Request-Id", "not a valid id!".to_string())]).await;
+        let replaced = response.headers()["X-Request-Id"].to_str().unwrap();
+        assert!(Uuid::parse_str(replaced).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_headers_are_rejected() {
+        let config = JobConfig {
+            max_header_bytes: 256,
+            ..JobConfig::default()
+        };
+        let app = app(config).await;
+
+        let response = send(app.clone(), &[("X-Padding", "a".repeat(100))]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(app, &[("X-Padding", "a".repeat(300))]).await;
+        assert_eq!(response.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+}
@@ -1,13 +1,78 @@
 use axum::{
-    body::Body,
-    extract::Request,
-    http::{header::AUTHORIZATION, StatusCode},
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use std::convert::Infallible;
 use std::env;
 
+/// User id for callers the token doesn't identify, unless overridden by
+/// `FLASHPODS_DEFAULT_USER`
+pub const DEFAULT_USER_ID: &str = "default";
+
+/// The user that requests act as when nothing more specific identifies them.
+/// Single-tenant deployments set `FLASHPODS_DEFAULT_USER` to label their data.
+pub fn default_user_id() -> String {
+    resolve_default_user(env::var("FLASHPODS_DEFAULT_USER").ok())
+}
+
+/// The default user for a configured `FLASHPODS_DEFAULT_USER` value; blank
+/// or missing falls back to `DEFAULT_USER_ID`
+fn resolve_default_user(configured: Option<String>) -> String {
+    configured
+        .map(|user| user.trim().to_string())
+        .filter(|user| !user.is_empty())
+        .unwrap_or_else(|| DEFAULT_USER_ID.to_string())
+}
+
+/// Identity of the authenticated caller, attached to each request by
+/// `auth_middleware`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caller {
+    pub user_id: String,
+    pub is_admin: bool,
+}
+
+impl Caller {
+    /// `403 admin_required` unless the caller holds the admin token;
+    /// `message` says what is reserved for admins
+    pub fn require_admin(&self, message: &str) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+        if self.is_admin {
+            return Ok(());
+        }
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "admin_required",
+                "message": message
+            })),
+        ))
+    }
+}
+
+impl Default for Caller {
+    fn default() -> Self {
+        Self {
+            user_id: DEFAULT_USER_ID.to_string(),
+            is_admin: false,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    /// Requests that bypassed auth (e.g. unit-tested routers) act as the
+    /// default, non-admin user
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned().unwrap_or_default())
+    }
+}
+
 /// Bearer token authentication middleware
 pub async fn auth_middleware(request: Request, next: Next) -> Response {
     // Skip auth for health endpoint
@@ -52,8 +117,11 @@ pub async fn auth_middleware(request: Request, next: Next) -> Response {
                     .into_response();
             }
 
-            // Validate token
-            if parts[1] != expected_token {
+            // Validate token. The optional admin token grants admin access.
+            let is_admin = env::var("FLASHPODS_ADMIN_TOKEN")
+                .map(|admin| !admin.is_empty() && parts[1] == admin)
+                .unwrap_or(false);
+            if parts[1] != expected_token && !is_admin {
                 return (
                     StatusCode::UNAUTHORIZED,
                     Json(serde_json::json!({
@@ -65,6 +133,11 @@ pub async fn auth_middleware(request: Request, next: Next) -> Response {
             }
 
             // Token valid, proceed
+            let mut request = request;
+            request.extensions_mut().insert(Caller {
+                user_id: default_user_id(),
+                is_admin,
+            });
             next.run(request).await
         }
         None => (
@@ -82,6 +155,7 @@ pub async fn auth_middleware(request: Request, next: Next) -> Response {
 mod tests {
     use super::*;
     use axum::{
+        body::Body,
         http::{Method, Request},
         middleware,
         routing::get,
@@ -155,6 +229,41 @@ mod tests {
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
+    #[test]
+    fn test_resolve_default_user() {
+        assert_eq!(resolve_default_user(Some(" team-a ".to_string())), "team-a");
+        assert_eq!(resolve_default_user(Some("  ".to_string())), DEFAULT_USER_ID);
+        assert_eq!(resolve_default_user(None), DEFAULT_USER_ID);
+    }
+
+    #[tokio::test]
+    async fn test_caller_uses_configured_default_user() {
+        unsafe {
+            env::set_var("FLASHPODS_API_TOKEN", "test-token-123");
+        }
+        let app = Router::new()
+            .route("/whoami", get(|caller: Caller| async move { caller.user_id }))
+            .layer(middleware::from_fn(auth_middleware));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::GET)
+                    .uri("/whoami")
+                    .header(AUTHORIZATION, "Bearer test-token-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&bytes[..], default_user_id().as_bytes());
+    }
+
     #[tokio::test]
     async fn test_protected_valid_token() {
         let app = setup_test_app();
@@ -0,0 +1,206 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use super::Caller;
+use crate::AppState;
+
+/// Whether the API is in maintenance mode, e.g. during a deployment or
+/// migration. Starts from `FLASHPODS_MAINTENANCE_MODE` and is toggled by
+/// admins with `PUT /maintenance`.
+#[derive(Debug, Default)]
+pub struct MaintenanceMode {
+    enabled: AtomicBool,
+}
+
+impl MaintenanceMode {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Whether a request is served during maintenance: health checks, so load
+/// balancers keep the node, and admins, so they can finish the work
+pub fn allowed_during_maintenance(path: &str, caller: Option<&Caller>) -> bool {
+    path == "/health" || path.starts_with("/health/") || caller.is_some_and(|c| c.is_admin)
+}
+
+/// Reject everything but health checks and admin requests with 503 while
+/// maintenance mode is on. Runs after `auth_middleware`, which identifies
+/// the caller.
+pub async fn maintenance_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.maintenance.is_enabled()
+        || allowed_during_maintenance(request.uri().path(), request.extensions().get::<Caller>())
+    {
+        return next.run(request).await;
+    }
+
+    let retry_after = state.job_config.maintenance_retry_after_secs;
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({
+            "error": "maintenance",
+            "message": "The API is in maintenance mode; retry later"
+        })),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/", axum::routing::get(get_maintenance).put(set_maintenance))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintenanceResponse {
+    pub enabled: bool,
+    pub retry_after_secs: u64,
+}
+
+fn maintenance_response(state: &AppState) -> Json<MaintenanceResponse> {
+    Json(MaintenanceResponse {
+        enabled: state.maintenance.is_enabled(),
+        retry_after_secs: state.job_config.maintenance_retry_after_secs,
+    })
+}
+
+/// GET /maintenance - admin only
+async fn get_maintenance(
+    State(state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<MaintenanceResponse>, (StatusCode, Json<serde_json::Value>)> {
+    caller.require_admin("Maintenance mode is managed by admins")?;
+    Ok(maintenance_response(&state))
+}
+
+/// PUT /maintenance - admin only; turn maintenance mode on or off
+async fn set_maintenance(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<SetMaintenanceRequest>,
+) -> Result<Json<MaintenanceResponse>, (StatusCode, Json<serde_json::Value>)> {
+    caller.require_admin("Maintenance mode is managed by admins")?;
+    if state.maintenance.is_enabled() != req.enabled {
+        tracing::warn!(
+            "Maintenance mode turned {} by {}",
+            if req.enabled { "on" } else { "off" },
+            caller.user_id
+        );
+    }
+    state.maintenance.set(req.enabled);
+    Ok(maintenance_response(&state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        middleware::{from_fn, from_fn_with_state},
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    /// Router with the production layering: the caller is attached before
+    /// the maintenance check runs
+    fn app(state: AppState, is_admin: bool) -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .nest("/jobs", crate::jobs::routes())
+            .nest("/maintenance", routes())
+            .layer(from_fn_with_state(state.clone(), maintenance_middleware))
+            .layer(from_fn(move |mut request: Request, next: Next| async move {
+                request.extensions_mut().insert(Caller {
+                    user_id: "default".to_string(),
+                    is_admin,
+                });
+                next.run(request).await
+            }))
+            .with_state(state)
+    }
+
+    async fn send(app: Router, method: &str, uri: &str, body: serde_json::Value) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_blocks_jobs_but_not_health_or_admins() {
+        let state = crate::test_state().await;
+        let user = app(state.clone(), false);
+        let admin = app(state.clone(), true);
+        let submit = serde_json::json!({"type": "worker", "command": "make"});
+
+        let response = send(admin.clone(), "PUT", "/maintenance", serde_json::json!({"enabled": true})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.maintenance.is_enabled());
+
+        let response = send(user.clone(), "POST", "/jobs", submit.clone()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "300");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "maintenance");
+
+        let response = send(user.clone(), "GET", "/health", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Admins get through, to their endpoints and everything else
+        let response = send(admin.clone(), "GET", "/maintenance", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(admin.clone(), "GET", "/jobs", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        send(admin, "PUT", "/maintenance", serde_json::json!({"enabled": false})).await;
+        let response = send(user, "GET", "/jobs", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_only_admins_toggle_maintenance() {
+        let state = crate::test_state().await;
+        let response = send(
+            app(state.clone(), false),
+            "PUT",
+            "/maintenance",
+            serde_json::json!({"enabled": true}),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!state.maintenance.is_enabled());
+    }
+}
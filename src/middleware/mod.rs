@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod maintenance;
+pub mod request_id;
 
-pub use auth::auth_middleware;
+pub use auth::{auth_middleware, Caller};
+pub use maintenance::{maintenance_middleware, MaintenanceMode};
+pub use request_id::RequestId;
@@ -0,0 +1,93 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use uuid::Uuid;
+
+/// Header carrying the request id, both on the request and the response
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest client-supplied request id that is passed through
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of each request, echoed in the `X-Request-Id` response header and
+/// attached to the request by the `request_headers` middleware
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// The caller's `X-Request-Id` when it is a valid id, so clients can
+    /// correlate requests; otherwise a freshly generated UUID
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map(|id| RequestId(id.to_string()))
+            .unwrap_or_else(|| RequestId(Uuid::new_v4().to_string()))
+    }
+}
+
+/// Whether a client-supplied id is safe to pass through: a UUID or another
+/// token of at most `MAX_REQUEST_ID_LEN` letters, digits, `-`, `_`, `.` or `:`
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    /// Requests that bypassed the middleware (e.g. unit-tested routers) get
+    /// an empty id
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(|| RequestId(String::new())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with(id: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_bytes(id).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_request_id_validation() {
+        assert!(is_valid_request_id("3f2b8c1e-7d4a-4e6b-9a0c-1d2e3f4a5b6c"));
+        assert!(is_valid_request_id("client.trace_42:retry-1"));
+        assert!(is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN)));
+
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+        assert!(!is_valid_request_id("id with spaces"));
+        assert!(!is_valid_request_id("<script>"));
+    }
+
+    #[test]
+    fn test_from_headers_keeps_valid_ids_and_replaces_others() {
+        let id = "3f2b8c1e-7d4a-4e6b-9a0c-1d2e3f4a5b6c";
+        assert_eq!(RequestId::from_headers(&headers_with(id.as_bytes())).0, id);
+
+        for bad in ["bad id".as_bytes(), "x".repeat(500).as_bytes(), b"caf\xc3\xa9"] {
+            let RequestId(generated) = RequestId::from_headers(&headers_with(bad));
+            assert!(Uuid::parse_str(&generated).is_ok(), "{:?}", bad);
+        }
+        let RequestId(generated) = RequestId::from_headers(&HeaderMap::new());
+        assert!(Uuid::parse_str(&generated).is_ok());
+    }
+}
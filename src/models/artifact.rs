@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Artifact record from database
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub id: i64,
+    pub job_id: String,
+    pub name: String,
+    pub path: String,
+    /// Path relative to `/artifacts` as the job wrote it, when recorded
+    pub original_path: Option<String>,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response for artifact metadata (without the body)
+#[derive(Debug, Serialize)]
+pub struct ArtifactInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
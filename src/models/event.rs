@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Where a timeline event was observed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventSource {
+    /// A status transition recorded by the API
+    Job,
+    /// Reported by `podman events`
+    Container,
+}
+
+/// One entry in a job's timeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobEvent {
+    pub at: DateTime<Utc>,
+    pub source: EventSource,
+    /// Job status, or podman's event status such as `start` or `died`
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Job type matching database schema
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
@@ -20,17 +21,56 @@ impl std::fmt::Display for JobType {
 }
 
 impl std::str::FromStr for JobType {
-    type Err = String;
+    type Err = InvalidEnumValue;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "worker" => Ok(JobType::Worker),
             "agent" => Ok(JobType::Agent),
-            _ => Err(format!("Invalid job type: {}", s)),
+            _ => Err(InvalidEnumValue::new("type", s, JobType::VALUES)),
+        }
+    }
+}
+
+impl JobType {
+    /// Every job type as the API spells it
+    pub const VALUES: &'static [&'static str] = &["worker", "agent"];
+}
+
+/// A string that isn't one of an enum's values, e.g. `"type": "workr"`.
+/// The API reports it as 400 `invalid_enum_value` listing `allowed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidEnumValue {
+    /// The request field or query parameter holding the value
+    pub field: &'static str,
+    pub value: String,
+    pub allowed: &'static [&'static str],
+}
+
+impl InvalidEnumValue {
+    pub fn new(field: &'static str, value: &str, allowed: &'static [&'static str]) -> Self {
+        Self {
+            field,
+            value: value.to_string(),
+            allowed,
         }
     }
 }
 
+impl std::fmt::Display for InvalidEnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid {} '{}': expected one of {}",
+            self.field,
+            self.value,
+            self.allowed.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidEnumValue {}
+
 /// Job status matching database schema
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "TEXT", rename_all = "snake_case")]
@@ -64,7 +104,7 @@ impl std::fmt::Display for JobStatus {
 }
 
 impl std::str::FromStr for JobStatus {
-    type Err = String;
+    type Err = InvalidEnumValue;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
@@ -77,12 +117,25 @@ impl std::str::FromStr for JobStatus {
             "cancelled" => Ok(JobStatus::Cancelled),
             "cleaning" => Ok(JobStatus::Cleaning),
             "cleaned" => Ok(JobStatus::Cleaned),
-            _ => Err(format!("Invalid job status: {}", s)),
+            _ => Err(InvalidEnumValue::new("status", s, JobStatus::VALUES)),
         }
     }
 }
 
 impl JobStatus {
+    /// Every status as the API spells it
+    pub const VALUES: &'static [&'static str] = &[
+        "pending",
+        "starting",
+        "running",
+        "completed",
+        "failed",
+        "timed_out",
+        "cancelled",
+        "cleaning",
+        "cleaned",
+    ];
+
     pub fn is_terminal(&self) -> bool {
         matches!(
             self,
@@ -94,6 +147,119 @@ impl JobStatus {
     }
 }
 
+/// Why the scheduler last left a job in `Pending`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingReason {
+    /// Not enough free CPU or memory
+    Resources,
+    /// Waiting on another job to finish
+    Dependency,
+    /// Scheduling is paused by an operator
+    Paused,
+    /// The owner is at their job quota
+    Quota,
+    /// Waiting for its `start_after` time
+    Scheduled,
+}
+
+impl std::fmt::Display for PendingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PendingReason::Resources => write!(f, "resources"),
+            PendingReason::Dependency => write!(f, "dependency"),
+            PendingReason::Paused => write!(f, "paused"),
+            PendingReason::Quota => write!(f, "quota"),
+            PendingReason::Scheduled => write!(f, "scheduled"),
+        }
+    }
+}
+
+impl std::str::FromStr for PendingReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "resources" => Ok(PendingReason::Resources),
+            "dependency" => Ok(PendingReason::Dependency),
+            "paused" => Ok(PendingReason::Paused),
+            "quota" => Ok(PendingReason::Quota),
+            "scheduled" => Ok(PendingReason::Scheduled),
+            _ => Err(format!("Invalid pending reason: {}", s)),
+        }
+    }
+}
+
+/// Why a job failed. Permanent categories can't succeed by retrying the
+/// same job and make it dead-lettered.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    /// The image doesn't exist or the registry refused it
+    Image,
+    /// A `secret_refs` entry couldn't be resolved
+    Secret,
+    /// A directory mounted into the container is missing or inaccessible
+    Mount,
+    /// `max_total_runtime_minutes` is used up
+    RuntimeBudget,
+    /// The host was short on memory
+    Resources,
+    /// Any other failure to start the container
+    Start,
+    /// The command exited with a code outside `success_exit_codes`
+    ExitCode,
+    /// The container disappeared while the job was active
+    ContainerLost,
+}
+
+impl FailureCategory {
+    /// Categories that dead-letter a job
+    pub const PERMANENT: [FailureCategory; 4] = [
+        FailureCategory::Image,
+        FailureCategory::Secret,
+        FailureCategory::Mount,
+        FailureCategory::RuntimeBudget,
+    ];
+
+    pub fn is_permanent(self) -> bool {
+        Self::PERMANENT.contains(&self)
+    }
+}
+
+impl std::fmt::Display for FailureCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureCategory::Image => write!(f, "image"),
+            FailureCategory::Secret => write!(f, "secret"),
+            FailureCategory::Mount => write!(f, "mount"),
+            FailureCategory::RuntimeBudget => write!(f, "runtime_budget"),
+            FailureCategory::Resources => write!(f, "resources"),
+            FailureCategory::Start => write!(f, "start"),
+            FailureCategory::ExitCode => write!(f, "exit_code"),
+            FailureCategory::ContainerLost => write!(f, "container_lost"),
+        }
+    }
+}
+
+impl std::str::FromStr for FailureCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "image" => Ok(FailureCategory::Image),
+            "secret" => Ok(FailureCategory::Secret),
+            "mount" => Ok(FailureCategory::Mount),
+            "runtime_budget" => Ok(FailureCategory::RuntimeBudget),
+            "resources" => Ok(FailureCategory::Resources),
+            "start" => Ok(FailureCategory::Start),
+            "exit_code" => Ok(FailureCategory::ExitCode),
+            "container_lost" => Ok(FailureCategory::ContainerLost),
+            _ => Err(format!("Invalid failure category: {}", s)),
+        }
+    }
+}
+
 /// Job record from database
 #[derive(Debug, Clone)]
 pub struct Job {
@@ -103,37 +269,159 @@ pub struct Job {
     pub status: JobStatus,
     // Worker fields
     pub command: Option<String>,
+    pub commands: Option<Vec<String>>,
     // Agent fields
     pub task: Option<String>,
     pub context: Option<String>,
     pub git_branch: Option<String>,
     // Common fields
     pub files_id: Option<String>,
+    pub input_from_job: Option<String>,
     pub image: String,
     pub cpus: i32,
     pub memory_gb: i32,
     pub timeout_minutes: i32,
+    pub restartable: bool,
+    /// Earliest time the scheduler may start the job
+    pub start_after: Option<DateTime<Utc>>,
+    /// References only; resolved values are never stored
+    pub secret_refs: Vec<SecretRef>,
+    /// Cap on runtime summed over all of the job's attempts
+    pub max_total_runtime_minutes: Option<i32>,
+    /// Node labels the job must run on, see `JobConfig::node_labels`
+    pub node_selector: BTreeMap<String, String>,
+    /// Subpaths of `/artifacts` that are collected; empty collects all
+    pub artifact_paths: Vec<String>,
+    /// No `/artifacts` mount and nothing collected
+    pub ephemeral: bool,
+    /// Exit codes that count as success; anything else fails the job
+    pub success_exit_codes: Vec<i32>,
+    /// Size of `/dev/shm`; podman's default when unset
+    pub shm_size_mb: Option<i32>,
+    /// Keep the captured log as a `job.log` artifact once the job finishes
+    pub promote_log_to_artifact: bool,
     // Runtime fields
+    /// Runtime of earlier attempts, added up each time the job is retried
+    pub runtime_seconds: i64,
+    /// Chargeback cost, recorded by the cleanup sweep once the job finished
+    pub cost_units: Option<f64>,
     pub container_id: Option<String>,
     pub exit_code: Option<i32>,
     pub error: Option<String>,
+    /// Set when the job failed for a known reason
+    pub failure_category: Option<FailureCategory>,
+    /// When output capture started writing the job's log file
+    pub log_created_at: Option<DateTime<Utc>>,
+    /// Set once log retention has deleted the log file
+    pub logs_deleted_at: Option<DateTime<Utc>>,
     // Timestamps
     pub created_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
+    // Scheduler state
+    pub pending_reason: Option<PendingReason>,
+    // Client-supplied metadata
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    pub submission: SubmissionSource,
+}
+
+impl Job {
+    /// Failed for a reason retrying the same job won't fix
+    pub fn is_dead_letter(&self) -> bool {
+        self.status == JobStatus::Failed && self.failure_category.is_some_and(FailureCategory::is_permanent)
+    }
+
+    /// Final status of the job once its container exited with `exit_code`
+    pub fn status_for_exit(&self, exit_code: i32) -> JobStatus {
+        if self.success_exit_codes.contains(&exit_code) {
+            JobStatus::Completed
+        } else {
+            JobStatus::Failed
+        }
+    }
+
+    /// A 1 CPU, 1GB worker running `make` on `ubuntu:22.04`, for tests to
+    /// adjust with `..Job::sample(..)`
+    #[cfg(test)]
+    pub fn sample(id: &str, user_id: &str, status: JobStatus) -> Self {
+        Self {
+            id: id.to_string(),
+            user_id: user_id.to_string(),
+            job_type: JobType::Worker,
+            status,
+            command: Some("make".to_string()),
+            commands: None,
+            task: None,
+            context: None,
+            git_branch: None,
+            files_id: None,
+            input_from_job: None,
+            image: "ubuntu:22.04".to_string(),
+            cpus: 1,
+            memory_gb: 1,
+            timeout_minutes: 30,
+            restartable: false,
+            start_after: None,
+            secret_refs: Vec::new(),
+            max_total_runtime_minutes: None,
+            node_selector: BTreeMap::new(),
+            artifact_paths: Vec::new(),
+            ephemeral: false,
+            success_exit_codes: vec![0],
+            shm_size_mb: None,
+            promote_log_to_artifact: false,
+            runtime_seconds: 0,
+            cost_units: None,
+            failure_category: None,
+            log_created_at: None,
+            logs_deleted_at: None,
+            submission: SubmissionSource::default(),
+            container_id: None,
+            exit_code: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            completed_at: None,
+            pending_reason: None,
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+        }
+    }
+}
+
+/// Where a job was submitted from, recorded for auditing
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SubmissionSource {
+    pub client_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// Environment variable filled from an external secret when the container
+/// starts, see `crate::secrets::SecretProvider`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretRef {
+    pub env_name: String,
+    #[serde(rename = "ref")]
+    pub reference: String,
 }
 
 /// Request to create a new job
 #[derive(Debug, Deserialize)]
 pub struct CreateJobRequest {
+    /// Set when the body is an exported `JobSpec`
+    pub spec_version: Option<u32>,
     pub client_job_id: Option<String>,
     #[serde(rename = "type")]
     pub job_type: String,
     pub command: Option<String>,
+    pub commands: Option<Vec<String>>,
     pub task: Option<String>,
     pub context: Option<String>,
     pub git_branch: Option<String>,
     pub files_id: Option<String>,
+    pub input_from_job: Option<String>,
     #[serde(default = "default_image")]
     pub image: String,
     #[serde(default = "default_cpus")]
@@ -142,6 +430,36 @@ pub struct CreateJobRequest {
     pub memory_gb: i32,
     #[serde(default = "default_timeout")]
     pub timeout_minutes: i32,
+    #[serde(default)]
+    pub restartable: bool,
+    pub start_after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub secret_refs: Vec<SecretRef>,
+    pub max_total_runtime_minutes: Option<i32>,
+    #[serde(default)]
+    pub node_selector: BTreeMap<String, String>,
+    /// Only collect artifacts under these subpaths of `/artifacts`,
+    /// overriding `JobConfig::artifact_paths`
+    #[serde(default)]
+    pub artifact_paths: Vec<String>,
+    /// Skip the `/artifacts` mount and artifact collection (workers only)
+    #[serde(default)]
+    pub ephemeral: bool,
+    /// Exit codes that mark the job completed rather than failed
+    #[serde(default = "default_success_exit_codes")]
+    pub success_exit_codes: Vec<i32>,
+    /// Size of `/dev/shm`, up to `JobConfig::max_shm_size_mb`
+    pub shm_size_mb: Option<i32>,
+    /// List the captured log among the job's artifacts as `job.log`
+    #[serde(default)]
+    pub promote_log_to_artifact: bool,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    /// Fields not recognised above, rejected when strict parsing is enabled
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 fn default_image() -> String {
@@ -160,6 +478,18 @@ fn default_timeout() -> i32 {
     30
 }
 
+fn default_success_exit_codes() -> Vec<i32> {
+    vec![0]
+}
+
+fn is_default_success_exit_codes(codes: &[i32]) -> bool {
+    *codes == [0]
+}
+
+fn is_zero(n: &i64) -> bool {
+    *n == 0
+}
+
 /// Response for job creation
 #[derive(Debug, Serialize)]
 pub struct CreateJobResponse {
@@ -178,17 +508,55 @@ pub struct JobResponse {
     pub job_type: JobType,
     pub status: JobStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_reason: Option<PendingReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub commands: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_step: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub task: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_from_job: Option<String>,
     pub image: String,
     pub cpus: i32,
     pub memory_gb: i32,
     pub timeout_minutes: i32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub restartable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_after: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secret_refs: Vec<SecretRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_runtime_minutes: Option<i32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifact_paths: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ephemeral: bool,
+    #[serde(skip_serializing_if = "is_default_success_exit_codes")]
+    pub success_exit_codes: Vec<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shm_size_mb: Option<i32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub promote_log_to_artifact: bool,
+    /// Runtime used by earlier attempts of a retried job
+    #[serde(skip_serializing_if = "is_zero")]
+    pub runtime_seconds: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_units: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exit_code: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_category: Option<FailureCategory>,
+    /// Failed for a permanent reason, see `FailureCategory::PERMANENT`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dead_letter: bool,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub started_at: Option<DateTime<Utc>>,
@@ -198,6 +566,10 @@ pub struct JobResponse {
     pub elapsed_seconds: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_seconds: Option<i64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
 }
 
 impl From<Job> for JobResponse {
@@ -208,23 +580,177 @@ impl From<Job> for JobResponse {
             job.completed_at.map(|completed| (completed - started).num_seconds())
         });
 
+        let dead_letter = job.is_dead_letter();
+        // Only meaningful while the job is still waiting to be scheduled
+        let pending_reason = match job.status {
+            JobStatus::Pending => job.pending_reason,
+            _ => None,
+        };
+
         Self {
             id: job.id,
             job_type: job.job_type,
             status: job.status,
+            pending_reason,
             command: job.command,
+            commands: job.commands,
+            failed_step: None,
             task: job.task,
+            input_from_job: job.input_from_job,
             image: job.image,
             cpus: job.cpus,
             memory_gb: job.memory_gb,
             timeout_minutes: job.timeout_minutes,
+            restartable: job.restartable,
+            start_after: job.start_after,
+            secret_refs: job.secret_refs,
+            max_total_runtime_minutes: job.max_total_runtime_minutes,
+            node_selector: job.node_selector,
+            artifact_paths: job.artifact_paths,
+            ephemeral: job.ephemeral,
+            success_exit_codes: job.success_exit_codes,
+            shm_size_mb: job.shm_size_mb,
+            promote_log_to_artifact: job.promote_log_to_artifact,
+            runtime_seconds: job.runtime_seconds,
+            cost_units: job.cost_units,
             exit_code: job.exit_code,
             error: job.error,
+            failure_category: job.failure_category,
+            dead_letter,
             created_at: job.created_at,
             started_at: job.started_at,
             completed_at: job.completed_at,
             elapsed_seconds,
             duration_seconds,
+            labels: job.labels,
+            annotations: job.annotations,
+        }
+    }
+}
+
+/// Admin view of a job: the regular details plus owner and audit data
+#[derive(Debug, Serialize)]
+pub struct AdminJobResponse {
+    #[serde(flatten)]
+    pub job: JobResponse,
+    pub user_id: String,
+    pub submission: SubmissionSource,
+}
+
+/// Compact view of a job for listings; `GET /jobs/:id` has the details
+#[derive(Debug, Serialize)]
+pub struct JobSummary {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub job_type: JobType,
+    pub status: JobStatus,
+    pub created_at: DateTime<Utc>,
+    /// Seconds since the job started, stopping when it finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_seconds: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_category: Option<FailureCategory>,
+}
+
+impl From<Job> for JobSummary {
+    fn from(job: Job) -> Self {
+        let elapsed_seconds = job
+            .started_at
+            .map(|started| (job.completed_at.unwrap_or_else(Utc::now) - started).num_seconds());
+        Self {
+            id: job.id,
+            job_type: job.job_type,
+            status: job.status,
+            created_at: job.created_at,
+            elapsed_seconds,
+            failure_category: job.failure_category,
+        }
+    }
+}
+
+impl From<Job> for AdminJobResponse {
+    fn from(job: Job) -> Self {
+        Self {
+            user_id: job.user_id.clone(),
+            submission: job.submission.clone(),
+            job: JobResponse::from(job),
+        }
+    }
+}
+
+/// Current version of the portable `JobSpec` format
+pub const JOB_SPEC_VERSION: u32 = 1;
+
+/// Portable description of a job: everything needed to submit it again,
+/// without runtime state, ownership or scheduling. The JSON is a valid
+/// `POST /jobs` body.
+///
+/// `files_id` and `input_from_job` are left out: an upload is consumed by
+/// the job that used it, and the input job only exists on this server.
+#[derive(Debug, Serialize)]
+pub struct JobSpec {
+    pub spec_version: u32,
+    #[serde(rename = "type")]
+    pub job_type: JobType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commands: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    pub image: String,
+    pub cpus: i32,
+    pub memory_gb: i32,
+    pub timeout_minutes: i32,
+    pub restartable: bool,
+    pub secret_refs: Vec<SecretRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_runtime_minutes: Option<i32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artifact_paths: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ephemeral: bool,
+    #[serde(skip_serializing_if = "is_default_success_exit_codes")]
+    pub success_exit_codes: Vec<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shm_size_mb: Option<i32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub promote_log_to_artifact: bool,
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl From<Job> for JobSpec {
+    fn from(job: Job) -> Self {
+        Self {
+            spec_version: JOB_SPEC_VERSION,
+            job_type: job.job_type,
+            command: job.command,
+            commands: job.commands,
+            task: job.task,
+            context: job.context,
+            git_branch: job.git_branch,
+            image: job.image,
+            cpus: job.cpus,
+            memory_gb: job.memory_gb,
+            timeout_minutes: job.timeout_minutes,
+            restartable: job.restartable,
+            secret_refs: job.secret_refs,
+            max_total_runtime_minutes: job.max_total_runtime_minutes,
+            node_selector: job.node_selector,
+            artifact_paths: job.artifact_paths,
+            ephemeral: job.ephemeral,
+            success_exit_codes: job.success_exit_codes,
+            shm_size_mb: job.shm_size_mb,
+            promote_log_to_artifact: job.promote_log_to_artifact,
+            labels: job.labels,
+            annotations: job.annotations,
         }
     }
 }
@@ -234,6 +760,9 @@ impl From<Job> for JobResponse {
 pub struct ResourceLimits {
     pub max_cpus: i32,
     pub max_memory_gb: i32,
+    /// Shorter timeouts are raised to this; agents need time to do anything
+    /// useful, so a very short one is almost certainly a mistake
+    pub min_timeout_minutes: i32,
     pub max_timeout_minutes: i32,
 }
 
@@ -243,11 +772,13 @@ impl ResourceLimits {
             JobType::Worker => Self {
                 max_cpus: 8,
                 max_memory_gb: 16,
+                min_timeout_minutes: 1,
                 max_timeout_minutes: 120,
             },
             JobType::Agent => Self {
                 max_cpus: 4,
                 max_memory_gb: 8,
+                min_timeout_minutes: 10,
                 max_timeout_minutes: 120,
             },
         }
@@ -258,7 +789,358 @@ impl ResourceLimits {
         (
             cpus.clamp(1, self.max_cpus),
             memory_gb.clamp(1, self.max_memory_gb),
-            timeout_minutes.clamp(1, self.max_timeout_minutes),
+            timeout_minutes.clamp(self.min_timeout_minutes, self.max_timeout_minutes),
         )
     }
 }
+
+/// Job execution configuration
+#[derive(Debug, Clone)]
+pub struct JobConfig {
+    /// Images pulled in the background at startup to avoid cold-start pulls
+    pub prewarm_images: Vec<String>,
+    /// Maximum number of concurrent pulls while warming images
+    pub prewarm_concurrency: usize,
+    /// Container name template, see `PodmanService::with_name_template`
+    pub container_name_template: String,
+    /// Maximum number of labels (and, separately, annotations) per job
+    pub max_labels: usize,
+    /// Maximum total key + value bytes across a job's labels (or annotations)
+    pub max_label_bytes: usize,
+    /// SIGTERM grace before SIGKILL when a job is killed without `?grace=`
+    pub kill_grace_seconds: u64,
+    /// Seconds between database maintenance runs; 0 disables maintenance
+    pub db_maintenance_interval_secs: u64,
+    /// Maintenance is skipped while more than this many jobs are active
+    pub db_maintenance_max_active_jobs: usize,
+    /// Where `input_from_job` artifacts are mounted inside the container
+    pub input_mount_path: String,
+    /// Reject job requests containing unknown fields instead of ignoring them
+    pub strict_requests: bool,
+    /// Worker command denylist patterns, see `CommandDenylist`
+    pub forbidden_commands: Vec<String>,
+    /// Restartable jobs lost to a host restart are re-created if they started
+    /// within this many minutes
+    pub restart_window_minutes: i64,
+    /// Cap on concurrently active agent jobs, on top of the overall limits
+    pub max_concurrent_agents: Option<i64>,
+    /// Mirror each container's output into `logs_dir` while it runs
+    pub capture_logs: bool,
+    /// Directory `FileSecretProvider` resolves `secret_refs` against
+    pub secrets_dir: String,
+    /// Directory holding captured `{job_id}.log` files
+    pub logs_dir: String,
+    /// Captured logs stop growing past this size
+    pub max_log_bytes: u64,
+    /// Cap on concurrent `/output/stream` subscribers per job
+    pub max_log_subscribers: usize,
+    /// Cap on long-lived podman subprocesses (log capture and stream
+    /// followers) across all jobs; 0 disables
+    pub max_background_processes: usize,
+    /// How far in the future `start_after` may be
+    pub max_start_delay_minutes: i64,
+    /// How often the scheduler looks for scheduled jobs that are due
+    pub scheduler_interval_secs: u64,
+    /// Artifact names longer than this are shortened, see
+    /// `sanitize_artifact_name`
+    pub max_artifact_name_len: usize,
+    /// Jobs each user may submit per minute; 0 disables the limit
+    pub max_submissions_per_minute: usize,
+    /// Reject jobs whose finalized upload's directory no longer exists,
+    /// instead of starting them with an empty `/work`
+    pub require_upload_on_disk: bool,
+    /// Attributes of this node that a job's `node_selector` must match
+    pub node_labels: BTreeMap<String, String>,
+    /// Upload finalizations and container starts allowed in flight at once;
+    /// 0 removes the bound
+    pub max_pipeline_depth: usize,
+    /// Finished jobs keep their artifacts this long before being cleaned
+    pub artifact_retention_minutes: i64,
+    /// How often finished jobs are checked for cleanup; 0 disables it
+    pub cleanup_interval_secs: u64,
+    /// Captured logs of finished jobs are kept this long after capture
+    /// started, independently of artifact retention
+    pub log_retention_minutes: i64,
+    /// How often resource usage is recorded for `GET /resources/peaks`;
+    /// 0 disables sampling
+    pub resource_sample_interval_secs: u64,
+    /// Jobs kept in the `GET /jobs/:id` cache; 0 disables it
+    pub job_cache_capacity: usize,
+    /// How long a cached job is served before re-reading it
+    pub job_cache_ttl_secs: u64,
+    /// Cache lifetime for finished jobs, which only change on cleanup
+    pub job_cache_terminal_ttl_secs: u64,
+    /// How long to wait after `podman run` before confirming the container
+    /// is still up; 0 marks jobs running straight away
+    pub startup_confirm_ms: u64,
+    /// Subpaths of `/artifacts` collected from jobs that don't set their own
+    /// `artifact_paths`; empty collects everything
+    pub artifact_paths: Vec<String>,
+    /// Run containers with `--rm`. Off by default: podman removing a
+    /// container as it exits races inspecting it, so the cleanup sweep
+    /// removes finished jobs' containers instead
+    pub container_autoremove: bool,
+    /// Where job containers go in the host's cgroup tree
+    pub cgroup_mode: crate::podman::CgroupMode,
+    /// Largest `shm_size_mb` a job may ask for
+    pub max_shm_size_mb: i32,
+    /// Largest total size of a request's headers (names plus values);
+    /// larger requests get 431. 0 disables the check
+    pub max_header_bytes: usize,
+    /// Turns finished jobs' resources and runtime into `cost_units`
+    pub cost_formula: crate::resources::CostFormula,
+    /// Re-check upload storage quotas when an agent job takes its upload,
+    /// since other uploads may have filled them since finalize
+    pub recheck_upload_quota: bool,
+    /// What re-finalizing an already consumed upload does
+    pub consumed_finalize: crate::uploads::ConsumedFinalizePolicy,
+    /// Stop every active job's container when the API shuts down
+    pub stop_jobs_on_shutdown: bool,
+    /// Which containers a bulk stop stops first
+    pub stop_order: crate::jobs::StopOrder,
+    /// Containers a bulk stop stops at once
+    pub stop_concurrency: usize,
+    /// Start in maintenance mode, see `MaintenanceMode`
+    pub maintenance_mode: bool,
+    /// `Retry-After` sent with maintenance-mode 503s
+    pub maintenance_retry_after_secs: u64,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            prewarm_images: Vec::new(),
+            prewarm_concurrency: 2,
+            container_name_template: crate::podman::DEFAULT_NAME_TEMPLATE.to_string(),
+            max_labels: 64,
+            max_label_bytes: 16 * 1024,
+            kill_grace_seconds: 10,
+            db_maintenance_interval_secs: 24 * 60 * 60,
+            db_maintenance_max_active_jobs: 0,
+            input_mount_path: "/input".to_string(),
+            strict_requests: false,
+            forbidden_commands: Vec::new(),
+            restart_window_minutes: 60,
+            max_concurrent_agents: None,
+            capture_logs: false,
+            secrets_dir: "/run/flashpods/secrets".to_string(),
+            logs_dir: "/var/log/flashpods".to_string(),
+            max_log_bytes: 50 * 1024 * 1024, // 50 MB
+            max_log_subscribers: 16,
+            max_background_processes: 256,
+            max_start_delay_minutes: 7 * 24 * 60,
+            scheduler_interval_secs: 5,
+            max_artifact_name_len: crate::artifacts::MAX_ARTIFACT_NAME_LEN,
+            max_submissions_per_minute: 30,
+            require_upload_on_disk: true,
+            node_labels: BTreeMap::new(),
+            max_pipeline_depth: 4,
+            artifact_retention_minutes: 60,
+            cleanup_interval_secs: 60,
+            log_retention_minutes: 60,
+            resource_sample_interval_secs: 60,
+            job_cache_capacity: 1024,
+            job_cache_ttl_secs: 2,
+            job_cache_terminal_ttl_secs: 60,
+            startup_confirm_ms: 0,
+            artifact_paths: Vec::new(),
+            container_autoremove: false,
+            cgroup_mode: Default::default(),
+            max_shm_size_mb: 8192,
+            max_header_bytes: 16 * 1024,
+            cost_formula: Default::default(),
+            recheck_upload_quota: true,
+            consumed_finalize: Default::default(),
+            stop_jobs_on_shutdown: false,
+            stop_order: Default::default(),
+            stop_concurrency: 4,
+            maintenance_mode: false,
+            maintenance_retry_after_secs: 300,
+        }
+    }
+}
+
+impl JobConfig {
+    /// Build configuration from `FLASHPODS_*` environment variables, falling
+    /// back to defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Ok(images) = std::env::var("FLASHPODS_PREWARM_IMAGES") {
+            config.prewarm_images = images
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+        if let Some(n) = env_parse("FLASHPODS_PREWARM_CONCURRENCY") {
+            config.prewarm_concurrency = n;
+        }
+
+        if let Ok(template) = std::env::var("FLASHPODS_CONTAINER_NAME_TEMPLATE") {
+            config.container_name_template = template;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_LABELS") {
+            config.max_labels = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_LABEL_BYTES") {
+            config.max_label_bytes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_KILL_GRACE_SECONDS") {
+            config.kill_grace_seconds = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_DB_MAINTENANCE_INTERVAL_SECS") {
+            config.db_maintenance_interval_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_DB_MAINTENANCE_MAX_ACTIVE_JOBS") {
+            config.db_maintenance_max_active_jobs = n;
+        }
+        if let Ok(path) = std::env::var("FLASHPODS_INPUT_MOUNT_PATH") {
+            config.input_mount_path = path;
+        }
+        if let Some(strict) = env_parse("FLASHPODS_STRICT_REQUESTS") {
+            config.strict_requests = strict;
+        }
+        if let Some(n) = env_parse("FLASHPODS_RESTART_WINDOW_MINUTES") {
+            config.restart_window_minutes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_CONCURRENT_AGENTS") {
+            config.max_concurrent_agents = Some(n);
+        }
+        if let Some(capture) = env_parse("FLASHPODS_CAPTURE_LOGS") {
+            config.capture_logs = capture;
+        }
+        if let Ok(path) = std::env::var("FLASHPODS_SECRETS_DIR") {
+            config.secrets_dir = path;
+        }
+        if let Ok(path) = std::env::var("FLASHPODS_LOGS_PATH") {
+            config.logs_dir = path;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_LOG_BYTES") {
+            config.max_log_bytes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_LOG_SUBSCRIBERS") {
+            config.max_log_subscribers = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_BACKGROUND_PROCESSES") {
+            config.max_background_processes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_START_DELAY_MINUTES") {
+            config.max_start_delay_minutes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_SCHEDULER_INTERVAL_SECS") {
+            config.scheduler_interval_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_ARTIFACT_NAME_LEN") {
+            config.max_artifact_name_len = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_SUBMISSIONS_PER_MINUTE") {
+            config.max_submissions_per_minute = n;
+        }
+        if let Some(require) = env_parse("FLASHPODS_REQUIRE_UPLOAD_ON_DISK") {
+            config.require_upload_on_disk = require;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_PIPELINE_DEPTH") {
+            config.max_pipeline_depth = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_ARTIFACT_RETENTION_MINUTES") {
+            config.artifact_retention_minutes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_CLEANUP_INTERVAL_SECS") {
+            config.cleanup_interval_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_LOG_RETENTION_MINUTES") {
+            config.log_retention_minutes = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_RESOURCE_SAMPLE_INTERVAL_SECS") {
+            config.resource_sample_interval_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_JOB_CACHE_CAPACITY") {
+            config.job_cache_capacity = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_JOB_CACHE_TTL_SECS") {
+            config.job_cache_ttl_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_JOB_CACHE_TERMINAL_TTL_SECS") {
+            config.job_cache_terminal_ttl_secs = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_STARTUP_CONFIRM_MS") {
+            config.startup_confirm_ms = n;
+        }
+        // Comma-separated, e.g. `output,reports`; invalid entries are dropped
+        if let Ok(paths) = std::env::var("FLASHPODS_ARTIFACT_PATHS") {
+            config.artifact_paths = paths
+                .split(',')
+                .filter_map(|p| crate::artifacts::normalize_artifact_path(p.trim()).ok())
+                .collect();
+        }
+        if let Some(autoremove) = env_parse("FLASHPODS_CONTAINER_AUTOREMOVE") {
+            config.container_autoremove = autoremove;
+        }
+        // `default`, `split`, `systemd` or `systemd:<name>.slice`
+        if let Some(mode) = env_parse("FLASHPODS_CGROUP_MODE") {
+            config.cgroup_mode = mode;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_SHM_SIZE_MB") {
+            config.max_shm_size_mb = n;
+        }
+        if let Some(n) = env_parse("FLASHPODS_MAX_HEADER_BYTES") {
+            config.max_header_bytes = n;
+        }
+        // `product` or `linear:<cpu_rate>,<memory_rate>`
+        if let Some(formula) = env_parse("FLASHPODS_COST_FORMULA") {
+            config.cost_formula = formula;
+        }
+        if let Some(recheck) = env_parse("FLASHPODS_RECHECK_UPLOAD_QUOTA") {
+            config.recheck_upload_quota = recheck;
+        }
+        if let Some(policy) = env_parse("FLASHPODS_CONSUMED_FINALIZE") {
+            config.consumed_finalize = policy;
+        }
+        if let Some(stop) = env_parse("FLASHPODS_STOP_JOBS_ON_SHUTDOWN") {
+            config.stop_jobs_on_shutdown = stop;
+        }
+        // `oldest-first` or `agents-first`
+        if let Some(order) = env_parse("FLASHPODS_STOP_ORDER") {
+            config.stop_order = order;
+        }
+        if let Some(n) = env_parse("FLASHPODS_STOP_CONCURRENCY") {
+            config.stop_concurrency = n;
+        }
+        if let Some(maintenance) = env_parse("FLASHPODS_MAINTENANCE_MODE") {
+            config.maintenance_mode = maintenance;
+        }
+        if let Some(secs) = env_parse("FLASHPODS_MAINTENANCE_RETRY_AFTER_SECS") {
+            config.maintenance_retry_after_secs = secs;
+        }
+        // Comma-separated `key=value` pairs, e.g. `gpu=none,zone=lab`
+        if let Ok(labels) = std::env::var("FLASHPODS_NODE_LABELS") {
+            config.node_labels = labels
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .filter(|(k, _)| !k.is_empty())
+                .collect();
+        }
+        // One pattern per line; blank lines and `#` comments are ignored
+        if let Ok(path) = std::env::var("FLASHPODS_FORBIDDEN_COMMANDS_FILE") {
+            match std::fs::read_to_string(&path) {
+                Ok(contents) => {
+                    config.forbidden_commands = contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(String::from)
+                        .collect();
+                }
+                Err(e) => tracing::warn!("Failed to read forbidden commands from {}: {}", path, e),
+            }
+        }
+
+        config
+    }
+}
+
+pub(crate) fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
@@ -1,7 +1,12 @@
+pub mod artifact;
+pub mod event;
 pub mod job;
 pub mod upload;
 
+pub use artifact::{Artifact, ArtifactInfo};
+pub use event::{EventSource, JobEvent};
 pub use job::{
-    CreateJobRequest, CreateJobResponse, Job, JobResponse, JobStatus, JobType, ResourceLimits,
+    AdminJobResponse, CreateJobRequest, CreateJobResponse, FailureCategory, InvalidEnumValue, Job, JobConfig, JobResponse, JobSpec, JobStatus, JobSummary, JobType,
+    PendingReason, ResourceLimits, SecretRef, SubmissionSource, JOB_SPEC_VERSION,
 };
 pub use upload::{Upload, UploadConfig, UploadResponse, UploadState};
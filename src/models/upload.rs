@@ -88,8 +88,12 @@ pub struct UploadConfig {
     pub upload_dir: String,
     pub max_upload_size_bytes: i64,
     pub max_total_disk_bytes: i64,
+    /// Upload storage each user may hold; 0 leaves only the total limit
+    pub max_user_disk_bytes: i64,
     pub ttl_uploading_minutes: i32,
     pub ttl_finalized_minutes: i32,
+    /// Longest an upload can be kept in `uploading` by touching it
+    pub max_uploading_lifetime_minutes: i32,
 }
 
 impl Default for UploadConfig {
@@ -98,8 +102,24 @@ impl Default for UploadConfig {
             upload_dir: "/tmp/flashpods/uploads".to_string(),
             max_upload_size_bytes: 2 * 1024 * 1024 * 1024, // 2 GB
             max_total_disk_bytes: 10 * 1024 * 1024 * 1024, // 10 GB
+            max_user_disk_bytes: 0,
             ttl_uploading_minutes: 30,
             ttl_finalized_minutes: 60,
+            max_uploading_lifetime_minutes: 24 * 60,
         }
     }
 }
+
+impl UploadConfig {
+    /// Build configuration from `FLASHPODS_*` environment variables, falling
+    /// back to defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Some(n) = super::job::env_parse("FLASHPODS_MAX_USER_DISK_BYTES") {
+            config.max_user_disk_bytes = n;
+        }
+
+        config
+    }
+}
@@ -1,6 +1,15 @@
+use std::collections::{BTreeMap, HashMap};
 use std::process::Command;
 use tracing::{debug, error, info, warn};
 
+use chrono::{DateTime, Utc};
+
+use crate::models::{EventSource, JobEvent};
+
+mod supervisor;
+
+pub use supervisor::{ProcessCounts, ProcessSupervisor, SupervisorError};
+
 /// Container information returned by podman inspect
 #[derive(Debug, Clone)]
 pub struct ContainerInfo {
@@ -11,6 +20,438 @@ pub struct ContainerInfo {
     pub labels: std::collections::HashMap<String, String>,
 }
 
+/// Output read by `PodmanService::logs`
+#[derive(Debug, PartialEq)]
+pub struct ContainerLogs {
+    pub text: String,
+    /// Output past the byte cap was dropped
+    pub truncated: bool,
+}
+
+/// Read `reader` to the end, keeping whole lines while `budget` (shared by
+/// the readers of one command) lasts. Returns what was kept and whether
+/// anything was dropped.
+fn read_capped(reader: impl std::io::Read, budget: &std::sync::atomic::AtomicU64) -> (String, bool) {
+    use std::io::{BufRead, Read};
+    use std::sync::atomic::Ordering;
+
+    let max_line = budget.load(Ordering::SeqCst) + 1;
+    let mut reader = std::io::BufReader::new(reader);
+    let (mut kept, mut line) = (Vec::new(), Vec::new());
+    let mut truncated = false;
+    loop {
+        line.clear();
+        // A line longer than the whole budget is read in pieces, never held
+        match reader.by_ref().take(max_line).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let reserved = !truncated
+                    && budget
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(n as u64))
+                        .is_ok();
+                if reserved {
+                    kept.extend_from_slice(&line);
+                } else {
+                    truncated = true;
+                }
+            }
+        }
+    }
+    (String::from_utf8_lossy(&kept).into_owned(), truncated)
+}
+
+/// Variables set on a job's container with `-e NAME=value`: agents get
+/// their task and identity. Secrets are passed separately, see
+/// `ContainerConfig::secret_env`.
+pub fn job_env(
+    job_id: &str,
+    job_type: JobType,
+    task: Option<&str>,
+    context: Option<&str>,
+    git_branch: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+    if job_type == JobType::Agent {
+        let optional = [
+            ("FLASHPODS_TASK", task),
+            ("FLASHPODS_CONTEXT", context),
+            ("FLASHPODS_GIT_BRANCH", git_branch),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                env.push((name.to_string(), value.to_string()));
+            }
+        }
+        env.push(("FLASHPODS_JOB_ID".to_string(), job_id.to_string()));
+    }
+    env
+}
+
+/// Parse one entry of `podman inspect --format json`
+fn parse_inspected(container: &serde_json::Value) -> ContainerInfo {
+    let state = container.get("State");
+
+    let id = container
+        .get("Id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let name = container
+        .get("Name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim_start_matches('/')
+        .to_string();
+
+    let status = state
+        .and_then(|s| s.get("Status"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+
+    let exit_code = state
+        .and_then(|s| s.get("ExitCode"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    let labels = container
+        .get("Config")
+        .and_then(|c| c.get("Labels"))
+        .and_then(|l| l.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ContainerInfo {
+        id,
+        name,
+        state: status.parse().unwrap_or(ContainerState::Unknown),
+        exit_code,
+        labels,
+    }
+}
+
+/// Pick job `job_id`'s container among several sharing a name: the one
+/// labelled `flashpods-job-id=<job_id>`, else the first without a job label
+/// at all (created before labelling). Containers labelled for another job
+/// are never chosen. Returns the choice and the rest.
+fn select_job_container(
+    containers: Vec<ContainerInfo>,
+    job_id: &str,
+) -> (Option<ContainerInfo>, Vec<ContainerInfo>) {
+    let label = |c: &ContainerInfo| c.labels.get("flashpods-job-id").cloned();
+    let position = containers
+        .iter()
+        .position(|c| label(c).as_deref() == Some(job_id))
+        .or_else(|| containers.iter().position(|c| label(c).is_none()));
+
+    let mut rest = containers;
+    let chosen = position.map(|i| rest.remove(i));
+    (chosen, rest)
+}
+
+/// Parse the JSON array `podman inspect` and `podman ps --format json`
+/// print, tolerating version quirks: non-JSON lines around the array (e.g.
+/// warnings on stdout) are dropped. With `skip_malformed`, entries that
+/// don't parse or aren't containers are skipped with a warning instead of
+/// failing the rest, and only output with no usable array at all is an
+/// error. Without it any such entry is an error, for callers that would
+/// take a missing entry to mean a missing container. `what` names the
+/// output in logs and errors.
+fn parse_container_array(
+    stdout: &str,
+    what: &str,
+    skip_malformed: bool,
+) -> Result<Vec<serde_json::Value>, PodmanError> {
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    // The array opens on the first line starting with `[` and ends at the
+    // bracket matching it; warnings like `WARN[0000] ...` have brackets too
+    let mut offset = 0;
+    let mut start = None;
+    for line in stdout.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            start = Some(offset + line.len() - trimmed.len());
+            break;
+        }
+        offset += line.len();
+    }
+    let bounds = start.and_then(|start| Some((start, start + closing_bracket(&stdout[start..])?)));
+    let Some((start, end)) = bounds else {
+        return Err(PodmanError::Parse(format!("No JSON array in {} output", what)));
+    };
+    let stray = stdout[..start]
+        .lines()
+        .chain(stdout[end + 1..].lines())
+        .filter(|l| !l.trim().is_empty())
+        .count();
+    if stray > 0 {
+        warn!("Ignoring {} non-JSON line(s) in {} output", stray, what);
+    }
+    let json = &stdout[start..=end];
+
+    let mut dropped = 0;
+    let entries = match serde_json::from_str::<Vec<serde_json::Value>>(json) {
+        Ok(entries) => entries,
+        Err(e) if !skip_malformed => {
+            return Err(PodmanError::Parse(format!("Failed to parse {} output: {}", what, e)));
+        }
+        Err(e) => {
+            warn!("Failed to parse {} output ({}); parsing its entries one by one", what, e);
+            let chunks = split_array_entries(json);
+            let entries: Vec<serde_json::Value> = chunks
+                .iter()
+                .filter_map(|chunk| serde_json::from_str(chunk).ok())
+                .collect();
+            if entries.is_empty() && !chunks.is_empty() {
+                return Err(PodmanError::Parse(format!("Failed to parse {} output: {}", what, e)));
+            }
+            dropped = chunks.len() - entries.len();
+            entries
+        }
+    };
+
+    let total = entries.len();
+    let containers: Vec<serde_json::Value> = entries
+        .into_iter()
+        .filter(|entry| {
+            entry
+                .get("Id")
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| !id.is_empty())
+        })
+        .collect();
+    dropped += total - containers.len();
+    if dropped > 0 {
+        if !skip_malformed {
+            return Err(PodmanError::Parse(format!(
+                "{} malformed entries in {} output",
+                dropped, what
+            )));
+        }
+        warn!("Skipping {} malformed entries in {} output", dropped, what);
+    }
+    Ok(containers)
+}
+
+/// Byte offset of the `]` closing the array `text` starts with, skipping
+/// brackets inside strings; `None` if the array never closes
+fn closing_bracket(text: &str) -> Option<usize> {
+    let (mut depth, mut in_string, mut escaped) = (0i32, false, false);
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a JSON array's text into its top-level entries without parsing
+/// them, so one broken entry can be told apart from the others. `json`
+/// starts with `[` and ends with `]`.
+fn split_array_entries(json: &str) -> Vec<&str> {
+    let json = json.trim();
+    let inner = &json[1..json.len() - 1];
+
+    let mut entries = Vec::new();
+    let (mut depth, mut in_string, mut escaped, mut from) = (0i32, false, false, 0);
+    for (i, c) in inner.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                entries.push(inner[from..i].trim());
+                from = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(inner[from..].trim());
+    entries.retain(|entry| !entry.is_empty());
+    entries
+}
+
+/// What `podman inspect` says about a container beyond `ContainerInfo`:
+/// enough to import one flashpods didn't start as a job
+#[derive(Debug, Clone)]
+pub struct ContainerDetails {
+    pub info: ContainerInfo,
+    pub image: String,
+    /// The container's `Cmd`, joined with spaces
+    pub command: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// `--cpus`, rounded up; `None` when unlimited
+    pub cpus: Option<i32>,
+    /// `--memory` in GB, rounded up; `None` when unlimited
+    pub memory_gb: Option<i32>,
+}
+
+fn parse_details(container: &serde_json::Value) -> ContainerDetails {
+    let config = container.get("Config");
+    let state = container.get("State");
+    let host_config = container.get("HostConfig");
+
+    let image = config
+        .and_then(|c| c.get("Image"))
+        .or_else(|| container.get("ImageName"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let command = config
+        .and_then(|c| c.get("Cmd"))
+        .and_then(|v| v.as_array())
+        .map(|args| args.iter().filter_map(|a| a.as_str()).collect::<Vec<_>>().join(" "))
+        .filter(|c| !c.is_empty());
+
+    // podman reports times it hasn't reached as 0001-01-01
+    let time = |key: &str| {
+        state
+            .and_then(|s| s.get(key))
+            .and_then(|v| v.as_str())
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc))
+            .filter(|t| t.timestamp() > 0)
+    };
+
+    let limit = |key: &str, unit: f64| {
+        host_config
+            .and_then(|h| h.get(key))
+            .and_then(|v| v.as_i64())
+            .filter(|&v| v > 0)
+            .map(|v| (v as f64 / unit).ceil() as i32)
+    };
+
+    ContainerDetails {
+        info: parse_inspected(container),
+        image,
+        command,
+        started_at: time("StartedAt"),
+        finished_at: time("FinishedAt"),
+        cpus: limit("NanoCpus", 1e9),
+        memory_gb: limit("Memory", (1u64 << 30) as f64),
+    }
+}
+
+/// Host-level podman facts that change how containers behave
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PodmanInfo {
+    pub version: Option<String>,
+    /// e.g. `overlay` or the much slower `vfs`
+    pub storage_driver: String,
+    /// `--userns=keep-id` only has an effect when rootless
+    pub rootless: bool,
+}
+
+impl PodmanInfo {
+    /// Parse the output of `podman info --format json`
+    pub fn parse(json: &str) -> Result<Self, PodmanError> {
+        let info: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| PodmanError::Parse(format!("Failed to parse podman info: {}", e)))?;
+
+        let storage_driver = info
+            .pointer("/store/graphDriverName")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| PodmanError::Parse("podman info has no storage driver".to_string()))?
+            .to_string();
+        let rootless = info
+            .pointer("/host/security/rootless")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let version = info
+            .pointer("/version/Version")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        Ok(Self {
+            version,
+            storage_driver,
+            rootless,
+        })
+    }
+}
+
+/// Image label declaring the fewest CPUs a job on the image needs
+pub const MIN_CPUS_LABEL: &str = "flashpods.min-cpus";
+/// Image label declaring the least memory, in GB, a job on the image needs
+pub const MIN_MEMORY_LABEL: &str = "flashpods.min-memory";
+
+/// Minimum resources an image declares through its labels
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageRequirements {
+    pub min_cpus: Option<i32>,
+    pub min_memory_gb: Option<i32>,
+}
+
+impl ImageRequirements {
+    /// Read the `flashpods.min-*` labels. Memory is in GB, with an optional
+    /// `g`/`G` suffix. Unparsable values are ignored.
+    pub fn from_labels(labels: &HashMap<String, String>) -> Self {
+        let parse = |label: &str| {
+            let value = labels.get(label)?;
+            let number = value.trim().trim_end_matches(['g', 'G']);
+            match number.parse::<i32>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => {
+                    warn!("Ignoring image label {}={:?}: not a positive integer", label, value);
+                    None
+                }
+            }
+        };
+        Self {
+            min_cpus: parse(MIN_CPUS_LABEL),
+            min_memory_gb: parse(MIN_MEMORY_LABEL),
+        }
+    }
+
+    /// Check a job's resources against the declared minimums
+    pub fn check(&self, cpus: i32, memory_gb: i32) -> Result<(), String> {
+        if let Some(min) = self.min_cpus.filter(|min| cpus < *min) {
+            return Err(format!("Image requires at least {} CPUs, {} requested", min, cpus));
+        }
+        if let Some(min) = self.min_memory_gb.filter(|min| memory_gb < *min) {
+            return Err(format!(
+                "Image requires at least {}GB memory, {}GB requested",
+                min, memory_gb
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ContainerState {
     Created,
@@ -69,16 +510,31 @@ impl std::fmt::Display for JobType {
 #[derive(Debug, Clone)]
 pub struct ContainerConfig {
     pub job_id: String,
+    pub user_id: String,
     pub job_type: JobType,
     pub upload_id: String,
+    /// Completed job whose artifacts are mounted read-only as input
+    pub input_from_job: Option<String>,
+    pub input_mount_path: String,
     pub image: String,
     pub command: Option<String>,
+    pub commands: Option<Vec<String>>,
     pub cpus: i32,
     pub memory_gb: i32,
+    /// Size of `/dev/shm`; podman's default (64MB) when unset
+    pub shm_size_mb: Option<i32>,
     // Agent-specific fields
     pub task: Option<String>,
     pub context: Option<String>,
     pub git_branch: Option<String>,
+    // Client-supplied metadata
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    /// Resolved secret values, passed in an env file so they never appear on
+    /// podman's command line or in its own environment
+    pub secret_env: Vec<(String, String)>,
+    /// Whether the job gets a `/artifacts` directory; ephemeral jobs don't
+    pub mount_artifacts: bool,
 }
 
 /// Podman service for container lifecycle management
@@ -86,18 +542,77 @@ pub struct PodmanService {
     podman_path: String,
     upload_dir: String,
     artifacts_dir: String,
+    /// Per-job directories for flashpods' own bookkeeping, kept apart from
+    /// the job's outputs
+    state_dir: String,
     spire_socket: String,
     token_socket: String,
+    name_template: String,
+    autoremove: bool,
+    cgroup_mode: CgroupMode,
+    /// Flags placed before the subcommand of every podman invocation
+    global_args: Vec<String>,
+}
+
+/// Slice that `CgroupMode::Systemd` places job scopes under by default
+pub const DEFAULT_SYSTEMD_SLICE: &str = "flashpods.slice";
+
+/// How job containers are placed in the host's cgroup tree. Set with
+/// `FLASHPODS_CGROUP_MODE`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CgroupMode {
+    /// Podman's own defaults; no flags are added (`default`)
+    #[default]
+    Default,
+    /// `--cgroups=split`: conmon and the container stay inside the API's
+    /// cgroup, so its systemd unit accounts for them and stopping the unit
+    /// kills stray processes (`split`)
+    Split,
+    /// Each container in its own systemd scope under `slice`, via
+    /// `--cgroup-manager=systemd --cgroup-parent=<slice>`; stopping the
+    /// slice cleans up every job (`systemd` or `systemd:<slice>`)
+    Systemd { slice: String },
+}
+
+impl std::str::FromStr for CgroupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "default" => Ok(Self::Default),
+            None if s == "split" => Ok(Self::Split),
+            None if s == "systemd" => Ok(Self::Systemd {
+                slice: DEFAULT_SYSTEMD_SLICE.to_string(),
+            }),
+            Some(("systemd", slice)) if slice.ends_with(".slice") && !slice.contains('/') => {
+                Ok(Self::Systemd {
+                    slice: slice.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid cgroup mode '{}': expected 'default', 'split', 'systemd' or 'systemd:<name>.slice'",
+                s
+            )),
+        }
+    }
 }
 
+/// Default container name template, kept for compatibility with existing hosts
+pub const DEFAULT_NAME_TEMPLATE: &str = "job_{job_id}";
+
 impl PodmanService {
     pub fn new() -> Self {
         Self {
             podman_path: "podman".to_string(),
             upload_dir: "/tmp/flashpods/uploads".to_string(),
             artifacts_dir: "/var/lib/flashpods/artifacts".to_string(),
+            state_dir: "/var/lib/flashpods/state".to_string(),
             spire_socket: "/run/spire/sockets/agent.sock".to_string(),
             token_socket: "/run/flashpods/token.sock".to_string(),
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            autoremove: false,
+            cgroup_mode: CgroupMode::Default,
+            global_args: Vec::new(),
         }
     }
 
@@ -107,97 +622,310 @@ impl PodmanService {
         spire_socket: String,
         token_socket: String,
     ) -> Self {
+        // Next to the artifacts directory
+        let state_dir = std::path::Path::new(&artifacts_dir)
+            .with_file_name("state")
+            .display()
+            .to_string();
         Self {
             podman_path: "podman".to_string(),
             upload_dir,
             artifacts_dir,
+            state_dir,
             spire_socket,
             token_socket,
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            autoremove: false,
+            cgroup_mode: CgroupMode::Default,
+            global_args: Vec::new(),
         }
     }
 
+    /// A podman invocation with the global flags already in place
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.podman_path);
+        cmd.args(&self.global_args);
+        cmd
+    }
+
+    /// `command` for use on the async runtime, e.g. for `podman logs -f`
+    pub fn async_command(&self) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(&self.podman_path);
+        cmd.args(&self.global_args);
+        cmd
+    }
+
+    /// Last `tail` lines (or all, with `None`) of a container's combined
+    /// stdout and stderr. Whole lines are kept until `max_bytes` have been
+    /// read; the rest is drained and dropped.
+    pub fn logs(
+        &self,
+        container_id: &str,
+        tail: Option<usize>,
+        max_bytes: u64,
+    ) -> Result<ContainerLogs, PodmanError> {
+        let failed = |e: std::io::Error| PodmanError::Command(format!("Failed to get container logs: {}", e));
+        let mut cmd = self.command();
+        cmd.args(["logs", "--timestamps"]);
+        if let Some(tail) = tail {
+            cmd.args(["--tail", &tail.to_string()]);
+        }
+        let mut child = cmd
+            .arg(container_id)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+
+        // podman replays the container's stderr on its own stderr; both pipes
+        // are drained at once so neither can fill up and stall podman
+        let budget = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(max_bytes));
+        let stderr = child.stderr.take().map(|stderr| {
+            let budget = budget.clone();
+            std::thread::spawn(move || read_capped(stderr, &budget))
+        });
+        let (stdout, stdout_truncated) = child
+            .stdout
+            .take()
+            .map(|stdout| read_capped(stdout, &budget))
+            .unwrap_or_default();
+        let (stderr, stderr_truncated) = stderr
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        let status = child.wait().map_err(failed)?;
+
+        if !status.success() {
+            return Err(PodmanError::Command(format!("Failed to get container logs: {}", stderr)));
+        }
+
+        Ok(ContainerLogs {
+            text: stdout + &stderr,
+            truncated: stdout_truncated || stderr_truncated,
+        })
+    }
+
+    /// Use a different podman binary
+    pub fn with_podman_path(mut self, podman_path: &str) -> Self {
+        self.podman_path = podman_path.to_string();
+        self
+    }
+
+    /// Use a custom container name template.
+    ///
+    /// Supported placeholders are `{job_id}`, `{user}` and `{type}`. The
+    /// template must contain `{job_id}` so names stay unique per job.
+    pub fn with_name_template(mut self, template: &str) -> Result<Self, PodmanError> {
+        if !template.contains("{job_id}") {
+            return Err(PodmanError::InvalidName(format!(
+                "template {:?} must contain {{job_id}}",
+                template
+            )));
+        }
+        validate_container_name(&render_name(template, "job_0", "user", JobType::Worker))?;
+        self.name_template = template.to_string();
+        Ok(self)
+    }
+
+    /// Run containers with `--rm`, so podman removes them as they exit.
+    /// Without it, finished containers are left for `remove_container`.
+    pub fn with_autoremove(mut self, autoremove: bool) -> Self {
+        self.autoremove = autoremove;
+        self
+    }
+
+    /// Place containers in the host's cgroup tree per `mode`
+    pub fn with_cgroup_mode(mut self, mode: CgroupMode) -> Self {
+        // Containers are looked up, stopped and removed through the same
+        // cgroup manager that created them
+        self.global_args = match mode {
+            CgroupMode::Systemd { .. } => vec!["--cgroup-manager=systemd".to_string()],
+            _ => Vec::new(),
+        };
+        self.cgroup_mode = mode;
+        self
+    }
+
+    /// Container name for a job, as rendered from the configured template
+    pub fn container_name(&self, config: &ContainerConfig) -> Result<String, PodmanError> {
+        let name = render_name(
+            &self.name_template,
+            &config.job_id,
+            &config.user_id,
+            config.job_type,
+        );
+        validate_container_name(&name)?;
+        Ok(name)
+    }
+
     /// Create and start a container for a job
     pub fn create_container(&self, config: &ContainerConfig) -> Result<String, PodmanError> {
-        let container_name = format!("job_{}", config.job_id);
+        if config.mount_artifacts {
+            let artifacts_path = self.artifacts_path(&config.job_id);
+            std::fs::create_dir_all(&artifacts_path).map_err(|e| {
+                PodmanError::FileSystem(format!("Failed to create artifacts dir: {}", e))
+            })?;
+        }
+        if config.commands.is_some() {
+            let state_path = self.state_path(&config.job_id);
+            // Left over from an earlier attempt of a restarted job
+            let _ = std::fs::remove_dir_all(&state_path);
+            std::fs::create_dir_all(&state_path).map_err(|e| {
+                PodmanError::FileSystem(format!("Failed to create state dir: {}", e))
+            })?;
+        }
+
+        let args = self.run_args(config)?;
+        debug!(
+            "Running podman command: {} {:?} {:?}",
+            self.podman_path, self.global_args, args
+        );
+
+        // Only needed until podman has read it
+        let _env_file = if config.secret_env.is_empty() {
+            None
+        } else {
+            Some(EnvFile::write(self.env_file_path(&config.job_id), &config.secret_env)?)
+        };
+        let output = self.command().args(args).output().map_err(|e| {
+            PodmanError::Command(format!("Failed to execute podman: {}", e))
+        })?;
+
+        if !output.status.success() {
+            let failure = CommandFailure::from_output(&output);
+            error!("Podman create failed: {}", failure);
+            return Err(classify_start_failure(failure));
+        }
+
+        let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        info!("Created container {} for job {}", container_id, config.job_id);
+
+        Ok(container_id)
+    }
+
+    /// Host directory holding a job's artifacts
+    pub fn artifacts_path(&self, job_id: &str) -> String {
+        format!("{}/{}", self.artifacts_dir, job_id)
+    }
+
+    /// Host directory for flashpods' bookkeeping about a job, mounted at
+    /// `STATE_MOUNT` in multi-command workers
+    pub fn state_path(&self, job_id: &str) -> String {
+        format!("{}/{}", self.state_dir, job_id)
+    }
+
+    /// Env file holding a job's secrets while its container is created. It
+    /// sits beside the job's state directory, which containers may mount,
+    /// rather than inside it.
+    fn env_file_path(&self, job_id: &str) -> std::path::PathBuf {
+        std::path::Path::new(&self.state_dir).join(format!("{}.env", job_id))
+    }
+
+    /// Arguments passed to `podman`, after the global flags, to run a job's
+    /// container
+    pub fn run_args(&self, config: &ContainerConfig) -> Result<Vec<String>, PodmanError> {
+        let container_name = self.container_name(config)?;
         let work_mode = match config.job_type {
             JobType::Worker => "ro",
             JobType::Agent => "rw",
         };
 
-        // Create artifacts directory
-        let artifacts_path = format!("{}/{}", self.artifacts_dir, config.job_id);
-        std::fs::create_dir_all(&artifacts_path)
-            .map_err(|e| PodmanError::FileSystem(format!("Failed to create artifacts dir: {}", e)))?;
+        let mut args: Vec<String> = Vec::new();
+        let mut push = |items: &[&str]| args.extend(items.iter().map(|s| s.to_string()));
 
-        let mut cmd = Command::new(&self.podman_path);
-        cmd.args(["run", "-d", "--rm"]);
-        cmd.args(["--name", &container_name]);
-        cmd.args(["--label", "flashpods-job=true"]);
-        cmd.args(["--label", &format!("flashpods-job-id={}", config.job_id)]);
-        cmd.args(["--label", &format!("flashpods-job-type={}", config.job_type)]);
-        cmd.args(["--cpus", &config.cpus.to_string()]);
-        cmd.args(["--memory", &format!("{}g", config.memory_gb)]);
-        cmd.args(["--userns=keep-id"]);
-        cmd.args(["--network=slirp4netns"]);
-        cmd.args(["--security-opt", "no-new-privileges"]);
-        cmd.args(["--cap-drop", "ALL"]);
+        push(&["run", "-d"]);
+        if self.autoremove {
+            push(&["--rm"]);
+        }
+        match &self.cgroup_mode {
+            CgroupMode::Default => {}
+            CgroupMode::Split => push(&["--cgroups=split"]),
+            CgroupMode::Systemd { slice } => push(&["--cgroup-parent", slice]),
+        }
+        push(&["--name", &container_name]);
+        push(&["--label", "flashpods-job=true"]);
+        push(&["--label", &format!("flashpods-job-id={}", config.job_id)]);
+        push(&["--label", &format!("flashpods-job-type={}", config.job_type)]);
+        for (key, value) in &config.labels {
+            push(&["--label", &format!("{}={}", key, value)]);
+        }
+        for (key, value) in &config.annotations {
+            push(&["--annotation", &format!("{}={}", key, value)]);
+        }
+        push(&["--cpus", &config.cpus.to_string()]);
+        push(&["--memory", &format!("{}g", config.memory_gb)]);
+        if let Some(shm_size_mb) = config.shm_size_mb {
+            push(&[&format!("--shm-size={}m", shm_size_mb)]);
+        }
+        push(&["--userns=keep-id"]);
+        push(&["--network=slirp4netns"]);
+        push(&["--security-opt", "no-new-privileges"]);
+        push(&["--cap-drop", "ALL"]);
 
         // Mounts
         let work_mount = format!("{}/{}:/work:{}", self.upload_dir, config.upload_id, work_mode);
-        let artifacts_mount = format!("{}:/artifacts:rw", artifacts_path);
         let spire_mount = format!("{}:/run/spire/sockets/agent.sock:ro", self.spire_socket);
         let token_mount = format!("{}:/run/flashpods/token.sock:ro", self.token_socket);
 
-        cmd.args(["-v", &work_mount]);
-        cmd.args(["-v", &artifacts_mount]);
-        cmd.args(["-v", &spire_mount]);
-        cmd.args(["-v", &token_mount]);
+        push(&["-v", &work_mount]);
+        if config.mount_artifacts {
+            let artifacts_mount = format!("{}:/artifacts:rw", self.artifacts_path(&config.job_id));
+            push(&["-v", &artifacts_mount]);
+        }
+        // Only the sequential script writes here
+        if config.commands.is_some() {
+            let state_mount = format!("{}:{}:rw", self.state_path(&config.job_id), STATE_MOUNT);
+            push(&["-v", &state_mount]);
+        }
+        push(&["-v", &spire_mount]);
+        push(&["-v", &token_mount]);
+        if let Some(input_job_id) = &config.input_from_job {
+            let input_mount = format!(
+                "{}:{}:ro",
+                self.artifacts_path(input_job_id),
+                config.input_mount_path
+            );
+            push(&["-v", &input_mount]);
+        }
 
-        // Environment variables for agents
-        if config.job_type == JobType::Agent {
-            if let Some(task) = &config.task {
-                cmd.args(["-e", &format!("FLASHPODS_TASK={}", task)]);
-            }
-            if let Some(context) = &config.context {
-                cmd.args(["-e", &format!("FLASHPODS_CONTEXT={}", context)]);
-            }
-            if let Some(git_branch) = &config.git_branch {
-                cmd.args(["-e", &format!("FLASHPODS_GIT_BRANCH={}", git_branch)]);
-            }
-            cmd.args(["-e", &format!("FLASHPODS_JOB_ID={}", config.job_id)]);
+        for (name, value) in job_env(
+            &config.job_id,
+            config.job_type,
+            config.task.as_deref(),
+            config.context.as_deref(),
+            config.git_branch.as_deref(),
+        ) {
+            push(&["-e", &format!("{}={}", name, value)]);
+        }
+
+        if !config.secret_env.is_empty() {
+            let env_file = self.env_file_path(&config.job_id);
+            push(&["--env-file", &env_file.to_string_lossy()]);
         }
 
         // Image
-        cmd.arg(&config.image);
+        push(&[&config.image]);
 
         // Command
         match config.job_type {
             JobType::Worker => {
-                if let Some(command) = &config.command {
-                    cmd.args(["/bin/sh", "-c", command]);
+                if let Some(commands) = &config.commands {
+                    push(&["/bin/sh", "-c", &sequential_script(commands)]);
+                } else if let Some(command) = &config.command {
+                    push(&["/bin/sh", "-c", command]);
                 }
             }
             JobType::Agent => {
-                cmd.arg("/entrypoint.sh");
+                push(&["/entrypoint.sh"]);
             }
         }
 
-        debug!("Running podman command: {:?}", cmd);
-
-        let output = cmd.output().map_err(|e| {
-            PodmanError::Command(format!("Failed to execute podman: {}", e))
-        })?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            error!("Podman create failed: {}", stderr);
-            return Err(PodmanError::ContainerStart(stderr.to_string()));
-        }
-
-        let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        info!("Created container {} for job {}", container_id, config.job_id);
+        Ok(args)
+    }
 
-        Ok(container_id)
+    /// Step number recorded by a failed multi-command worker, if any
+    pub fn failed_step(&self, job_id: &str) -> Option<i32> {
+        let marker = std::path::Path::new(&self.state_path(job_id)).join(FAILED_STEP_FILE);
+        std::fs::read_to_string(marker).ok()?.trim().parse().ok()
     }
 
     /// Stop a container with SIGTERM, then SIGKILL after grace period
@@ -205,7 +933,7 @@ impl PodmanService {
         info!("Stopping container {} with {}s grace period", container_id, grace_seconds);
 
         // First, try graceful stop with SIGTERM
-        let stop_output = Command::new(&self.podman_path)
+        let stop_output = self.command()
             .args(["stop", "-t", &grace_seconds.to_string(), container_id])
             .output()
             .map_err(|e| PodmanError::Command(format!("Failed to stop container: {}", e)))?;
@@ -224,16 +952,16 @@ impl PodmanService {
     pub fn kill_container(&self, container_id: &str) -> Result<(), PodmanError> {
         info!("Killing container {}", container_id);
 
-        let output = Command::new(&self.podman_path)
+        let output = self.command()
             .args(["kill", container_id])
             .output()
             .map_err(|e| PodmanError::Command(format!("Failed to kill container: {}", e)))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+            let failure = CommandFailure::from_output(&output);
             // Ignore "container not found" errors
-            if !stderr.contains("no such container") {
-                return Err(PodmanError::ContainerStop(stderr.to_string()));
+            if !failure.stderr.contains("no such container") {
+                return Err(PodmanError::ContainerStop(failure));
             }
         }
 
@@ -241,78 +969,81 @@ impl PodmanService {
         Ok(())
     }
 
-    /// Get container information by ID or name
-    pub fn inspect_container(&self, container_id: &str) -> Result<Option<ContainerInfo>, PodmanError> {
-        let output = Command::new(&self.podman_path)
-            .args(["inspect", "--format", "json", container_id])
+    /// Remove a container, stopping it first if needed. A container that's
+    /// already gone counts as removed.
+    pub fn remove_container(&self, container_id: &str) -> Result<(), PodmanError> {
+        let output = self.command()
+            .args(["rm", "-f", container_id])
             .output()
-            .map_err(|e| PodmanError::Command(format!("Failed to inspect container: {}", e)))?;
+            .map_err(|e| PodmanError::Command(format!("Failed to remove container: {}", e)))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("no such container") || stderr.contains("not found") {
-                return Ok(None);
+            let failure = CommandFailure::from_output(&output);
+            if !failure.stderr.contains("no such container") {
+                return Err(PodmanError::ContainerRemove(failure));
             }
-            return Err(PodmanError::ContainerInspect(stderr.to_string()));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let containers: Vec<serde_json::Value> = serde_json::from_str(&stdout)
-            .map_err(|e| PodmanError::Parse(format!("Failed to parse inspect output: {}", e)))?;
+        info!("Removed container {}", container_id);
+        Ok(())
+    }
 
-        if containers.is_empty() {
-            return Ok(None);
+    /// Inspect the container of job `job_id` by its ID or name. If a stale
+    /// container shares the name, the one labelled with the job's id wins;
+    /// the others are logged and removed unless they are still running.
+    pub fn inspect_job_container(
+        &self,
+        job_id: &str,
+        container_id: &str,
+    ) -> Result<Option<ContainerInfo>, PodmanError> {
+        let (chosen, duplicates) = select_job_container(self.inspect_all(container_id)?, job_id);
+        for duplicate in duplicates {
+            warn!(
+                "Container {} ({}) also matches {} but isn't job {}'s",
+                duplicate.id, duplicate.name, container_id, job_id
+            );
+            if duplicate.state == ContainerState::Running {
+                continue;
+            }
+            if let Err(e) = self.remove_container(&duplicate.id) {
+                warn!("Failed to remove duplicate container {}: {}", duplicate.id, e);
+            }
         }
+        Ok(chosen)
+    }
 
-        let container = &containers[0];
-        let state = container.get("State");
-
-        let id = container
-            .get("Id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-
-        let name = container
-            .get("Name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .trim_start_matches('/')
-            .to_string();
+    /// Get information on every container `podman inspect` returns for an
+    /// ID or name
+    fn inspect_all(&self, container_id: &str) -> Result<Vec<ContainerInfo>, PodmanError> {
+        Ok(self.inspect_json(container_id)?.iter().map(parse_inspected).collect())
+    }
 
-        let status = state
-            .and_then(|s| s.get("Status"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown");
+    /// Inspect any container by its ID or name, managed or not
+    pub fn inspect_container(&self, container: &str) -> Result<Option<ContainerDetails>, PodmanError> {
+        Ok(self.inspect_json(container)?.first().map(parse_details))
+    }
 
-        let exit_code = state
-            .and_then(|s| s.get("ExitCode"))
-            .and_then(|v| v.as_i64())
-            .map(|v| v as i32);
+    /// Raw `podman inspect` output; empty if nothing matches
+    fn inspect_json(&self, container_id: &str) -> Result<Vec<serde_json::Value>, PodmanError> {
+        let output = self.command()
+            .args(["inspect", "--format", "json", container_id])
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to inspect container: {}", e)))?;
 
-        let labels = container
-            .get("Config")
-            .and_then(|c| c.get("Labels"))
-            .and_then(|l| l.as_object())
-            .map(|obj| {
-                obj.iter()
-                    .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
-                    .collect()
-            })
-            .unwrap_or_default();
+        if !output.status.success() {
+            let failure = CommandFailure::from_output(&output);
+            if failure.stderr.contains("no such container") || failure.stderr.contains("not found") {
+                return Ok(Vec::new());
+            }
+            return Err(PodmanError::ContainerInspect(failure));
+        }
 
-        Ok(Some(ContainerInfo {
-            id,
-            name,
-            state: status.parse().unwrap_or(ContainerState::Unknown),
-            exit_code,
-            labels,
-        }))
+        parse_container_array(&String::from_utf8_lossy(&output.stdout), "inspect", false)
     }
 
     /// List all flashpods containers
     pub fn list_containers(&self) -> Result<Vec<ContainerInfo>, PodmanError> {
-        let output = Command::new(&self.podman_path)
+        let output = self.command()
             .args([
                 "ps",
                 "-a",
@@ -325,17 +1056,10 @@ impl PodmanService {
             .map_err(|e| PodmanError::Command(format!("Failed to list containers: {}", e)))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(PodmanError::ContainerList(stderr.to_string()));
+            return Err(PodmanError::ContainerList(CommandFailure::from_output(&output)));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let containers: Vec<serde_json::Value> = if stdout.trim().is_empty() {
-            Vec::new()
-        } else {
-            serde_json::from_str(&stdout)
-                .map_err(|e| PodmanError::Parse(format!("Failed to parse container list: {}", e)))?
-        };
+        let containers = parse_container_array(&String::from_utf8_lossy(&output.stdout), "container list", true)?;
 
         let mut result = Vec::new();
         for container in containers {
@@ -385,9 +1109,93 @@ impl PodmanService {
         Ok(result)
     }
 
+    /// Make sure an image is present locally, pulling it if necessary
+    pub fn ensure_image(&self, image: &str) -> Result<(), PodmanError> {
+        let exists = self.command()
+            .args(["image", "exists", image])
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to check image: {}", e)))?;
+
+        if exists.status.success() {
+            debug!("Image {} already present", image);
+            return Ok(());
+        }
+
+        info!("Pulling image {}", image);
+        let output = self.command()
+            .args(["pull", "--quiet", image])
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to pull image: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(PodmanError::ImagePull(CommandFailure::from_output(&output)));
+        }
+
+        Ok(())
+    }
+
+    /// Labels of a local image, or `None` if the image isn't present
+    pub fn image_labels(&self, image: &str) -> Result<Option<HashMap<String, String>>, PodmanError> {
+        let output = self.command()
+            .args(["image", "inspect", "--format", "json", image])
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to inspect image: {}", e)))?;
+
+        if !output.status.success() {
+            let failure = CommandFailure::from_output(&output);
+            if failure.stderr.contains("image not known") || failure.stderr.contains("no such image") {
+                return Ok(None);
+            }
+            return Err(PodmanError::ImageInspect(failure));
+        }
+
+        let images: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| PodmanError::Parse(format!("Failed to parse image inspect output: {}", e)))?;
+        let Some(image) = images.first() else {
+            return Ok(None);
+        };
+
+        let labels = image
+            .pointer("/Config/Labels")
+            .or_else(|| image.get("Labels"))
+            .and_then(|l| l.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Some(labels))
+    }
+
+    /// Lifecycle events of a job's containers since `since`, from podman's
+    /// event log
+    pub fn container_events(
+        &self,
+        job_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<JobEvent>, PodmanError> {
+        let output = self.command()
+            .args(["events", "--stream=false", "--format", "json", "--since"])
+            .arg(since.to_rfc3339())
+            .arg("--filter")
+            .arg(format!("label=flashpods-job-id={}", job_id))
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to read events: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(PodmanError::Events(CommandFailure::from_output(&output)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(parse_container_event)
+            .collect())
+    }
+
     /// Check if podman is available
     pub fn is_available(&self) -> bool {
-        Command::new(&self.podman_path)
+        self.command()
             .args(["--version"])
             .output()
             .map(|o| o.status.success())
@@ -396,7 +1204,7 @@ impl PodmanService {
 
     /// Get podman version
     pub fn version(&self) -> Result<String, PodmanError> {
-        let output = Command::new(&self.podman_path)
+        let output = self.command()
             .args(["--version"])
             .output()
             .map_err(|e| PodmanError::Command(format!("Failed to get podman version: {}", e)))?;
@@ -408,6 +1216,185 @@ impl PodmanService {
         let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
         Ok(version)
     }
+
+    /// Get the storage driver and rootless mode podman is running with
+    pub fn info(&self) -> Result<PodmanInfo, PodmanError> {
+        let output = self.command()
+            .args(["info", "--format", "json"])
+            .output()
+            .map_err(|e| PodmanError::Command(format!("Failed to get podman info: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(PodmanError::Command(format!("Failed to get podman info: {}", stderr)));
+        }
+
+        PodmanInfo::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Service whose podman binary is a shell script with `body` under `dir`.
+    /// Every invocation's arguments are appended to `dir/calls.log`.
+    #[cfg(test)]
+    pub fn scripted(dir: &std::path::Path, body: &str) -> Self {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = dir.join("podman");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> {}\n{}\n",
+                dir.join("calls.log").display(),
+                body
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        Self::with_paths(
+            dir.join("uploads").display().to_string(),
+            dir.join("artifacts").display().to_string(),
+            "/tmp/spire.sock".to_string(),
+            "/tmp/token.sock".to_string(),
+        )
+        .with_podman_path(script.to_str().unwrap())
+    }
+}
+
+/// A job's secrets as `NAME=value` lines, readable only by the server and
+/// deleted when dropped
+struct EnvFile(std::path::PathBuf);
+
+impl EnvFile {
+    fn write(path: std::path::PathBuf, env: &[(String, String)]) -> Result<Self, PodmanError> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let failed = |e: std::io::Error| PodmanError::FileSystem(format!("Failed to write env file: {}", e));
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(failed)?;
+        }
+        // Left over from a crash; recreating it keeps the mode below
+        let _ = std::fs::remove_file(&path);
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .map_err(failed)?;
+        let env_file = Self(path);
+        for (name, value) in env {
+            writeln!(file, "{}={}", name, value).map_err(failed)?;
+        }
+        Ok(env_file)
+    }
+}
+
+impl Drop for EnvFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!("Failed to delete env file {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Container lifecycle events worth showing on a job's timeline
+const TIMELINE_CONTAINER_EVENTS: &[&str] = &["create", "start", "stop", "kill", "died", "remove"];
+
+/// Parse one line of `podman events --format json`. Podman reports `died`
+/// for what docker calls `die`.
+fn parse_container_event(line: &str) -> Option<JobEvent> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    if value.get("Type").and_then(|t| t.as_str()) != Some("container") {
+        return None;
+    }
+    let status = value.get("Status")?.as_str()?;
+    let event = if status == "die" { "died" } else { status };
+    if !TIMELINE_CONTAINER_EVENTS.contains(&event) {
+        return None;
+    }
+
+    let at = match value.get("Time") {
+        Some(serde_json::Value::String(time)) => {
+            DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc)
+        }
+        _ => {
+            let nanos = value.get("timeNano").and_then(|t| t.as_i64())?;
+            DateTime::from_timestamp_nanos(nanos)
+        }
+    };
+    let detail = value
+        .get("ContainerExitCode")
+        .and_then(|c| c.as_i64())
+        .filter(|_| event == "died")
+        .map(|code| format!("exit code {}", code));
+
+    Some(JobEvent {
+        at,
+        source: EventSource::Container,
+        event: event.to_string(),
+        detail,
+    })
+}
+
+/// Where a multi-command worker's state directory is mounted. It is
+/// flashpods' own, so neither the job's artifacts nor ephemeral jobs
+/// (which have none) are affected by it.
+pub const STATE_MOUNT: &str = "/run/flashpods/state";
+
+/// File in the state directory naming the step that failed in a
+/// multi-command worker job
+pub const FAILED_STEP_FILE: &str = "failed-step";
+
+/// Build a shell script running `commands` in order, stopping at the first
+/// non-zero exit. The failing step (1-based) is reported on stderr and
+/// written to the failed-step file, and its exit code is preserved.
+///
+/// The file is written after the step's subshell returns, so whatever a
+/// step put there itself is overwritten by the real step number.
+///
+/// Each step runs in its own subshell so an explicit `exit` is still
+/// attributed to that step; shell state such as `cd` does not carry over.
+pub fn sequential_script(commands: &[String]) -> String {
+    let mut script = String::new();
+    for (i, command) in commands.iter().enumerate() {
+        let step = i + 1;
+        script.push_str(&format!(
+            "(\n{command}\n) || {{ rc=$?; echo {step} > {state}/{marker} 2>/dev/null; \
+             echo \"flashpods: step {step} failed with exit code $rc\" >&2; exit $rc; }}\n",
+            command = command,
+            step = step,
+            state = STATE_MOUNT,
+            marker = FAILED_STEP_FILE,
+        ));
+    }
+    script
+}
+
+fn render_name(template: &str, job_id: &str, user_id: &str, job_type: JobType) -> String {
+    template
+        .replace("{job_id}", job_id)
+        .replace("{user}", user_id)
+        .replace("{type}", &job_type.to_string())
+}
+
+/// Check a name against podman's rules: `[a-zA-Z0-9][a-zA-Z0-9_.-]*`
+pub fn validate_container_name(name: &str) -> Result<(), PodmanError> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(first) => {
+            first.is_ascii_alphanumeric()
+                && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        }
+        None => false,
+    };
+
+    if !valid || name.len() > 253 {
+        return Err(PodmanError::InvalidName(format!(
+            "{:?} is not a valid container name",
+            name
+        )));
+    }
+    Ok(())
 }
 
 impl Default for PodmanService {
@@ -416,28 +1403,193 @@ impl Default for PodmanService {
     }
 }
 
+/// Outcome of an image warm-up run
+#[derive(Debug, Default, PartialEq)]
+pub struct WarmupSummary {
+    pub pulled: usize,
+    pub failed: usize,
+}
+
+/// Pre-pull images so the first job using them doesn't pay the pull latency.
+///
+/// At most `concurrency` pulls run at once. Failures are logged and counted,
+/// never propagated: a missing image only means a slower first start.
+pub async fn warm_images<F, Fut>(images: &[String], concurrency: usize, pull: F) -> WarmupSummary
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), PodmanError>> + Send + 'static,
+{
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+
+    for image in images {
+        let permit = semaphore.clone().acquire_owned().await;
+        let fut = pull(image.clone());
+        let image = image.clone();
+        tasks.spawn(async move {
+            let _permit = permit;
+            (image, fut.await)
+        });
+    }
+
+    let mut summary = WarmupSummary::default();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((image, Ok(()))) => {
+                info!("Warmed image {}", image);
+                summary.pulled += 1;
+            }
+            Ok((image, Err(e))) => {
+                warn!("Failed to warm image {}: {}", image, e);
+                summary.failed += 1;
+            }
+            Err(e) => {
+                warn!("Image warm task panicked: {}", e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    summary
+}
+
+/// A podman invocation that ran but exited unsuccessfully
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandFailure {
+    /// `None` if podman was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl CommandFailure {
+    pub fn from_output(output: &std::process::Output) -> Self {
+        Self {
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code {
+            Some(code) => write!(f, "{} (exit code {})", self.stderr, code),
+            None => write!(f, "{} (killed by signal)", self.stderr),
+        }
+    }
+}
+
+/// Turn a failed `podman run` into the error for its most likely cause, from
+/// podman's stderr. Unrecognised failures stay `ContainerStart`.
+pub fn classify_start_failure(failure: CommandFailure) -> PodmanError {
+    let stderr = failure.stderr.to_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|p| stderr.contains(p));
+
+    // Registries answer "denied" for private and for missing images alike,
+    // so auth is checked first
+    if mentions(&["unauthorized", "authentication required", "access to the resource is denied"]) {
+        PodmanError::ImagePullUnauthorized(failure)
+    } else if mentions(&["image not known", "manifest unknown", "name unknown", "no such image"]) {
+        PodmanError::ImageNotFound(failure)
+    } else if mentions(&["cannot allocate memory", "out of memory", "insufficient memory"]) {
+        PodmanError::InsufficientMemory(failure)
+    } else if mentions(&["is already in use", "name is in use"]) {
+        PodmanError::NameInUse(failure)
+    } else if mentions(&["statfs", "invalid mount", "error mounting", "invalid volume"]) {
+        PodmanError::InvalidMount(failure)
+    } else {
+        PodmanError::ContainerStart(failure)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PodmanError {
     #[error("Command error: {0}")]
     Command(String),
     #[error("Failed to start container: {0}")]
-    ContainerStart(String),
+    ContainerStart(CommandFailure),
+    #[error("Image not found, check the image name and tag: {0}")]
+    ImageNotFound(CommandFailure),
+    #[error("Registry refused to serve the image; it may be private and need credentials on the runner: {0}")]
+    ImagePullUnauthorized(CommandFailure),
+    #[error("Not enough memory on the host to start the container; retry later or request less: {0}")]
+    InsufficientMemory(CommandFailure),
+    #[error("A directory mounted into the container is missing or inaccessible: {0}")]
+    InvalidMount(CommandFailure),
+    #[error("Container name is already in use, likely by a leftover container: {0}")]
+    NameInUse(CommandFailure),
     #[error("Failed to stop container: {0}")]
-    ContainerStop(String),
+    ContainerStop(CommandFailure),
+    #[error("Failed to remove container: {0}")]
+    ContainerRemove(CommandFailure),
     #[error("Failed to inspect container: {0}")]
-    ContainerInspect(String),
+    ContainerInspect(CommandFailure),
     #[error("Failed to list containers: {0}")]
-    ContainerList(String),
+    ContainerList(CommandFailure),
+    #[error("Failed to pull image: {0}")]
+    ImagePull(CommandFailure),
+    #[error("Failed to inspect image: {0}")]
+    ImageInspect(CommandFailure),
+    #[error("Failed to read events: {0}")]
+    Events(CommandFailure),
+    #[error("Failed to resolve secrets: {0}")]
+    Secret(#[from] crate::secrets::SecretError),
+    #[error("Invalid container name: {0}")]
+    InvalidName(String),
     #[error("Parse error: {0}")]
     Parse(String),
     #[error("File system error: {0}")]
     FileSystem(String),
 }
 
+impl PodmanError {
+    /// Podman's exit code, for errors from a podman command that exited
+    /// unsuccessfully
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            PodmanError::ContainerStart(failure)
+            | PodmanError::ImageNotFound(failure)
+            | PodmanError::ImagePullUnauthorized(failure)
+            | PodmanError::InsufficientMemory(failure)
+            | PodmanError::InvalidMount(failure)
+            | PodmanError::NameInUse(failure)
+            | PodmanError::ContainerStop(failure)
+            | PodmanError::ContainerRemove(failure)
+            | PodmanError::ContainerInspect(failure)
+            | PodmanError::ContainerList(failure)
+            | PodmanError::ImagePull(failure)
+            | PodmanError::ImageInspect(failure)
+            | PodmanError::Events(failure) => failure.exit_code,
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_image_requirements_from_labels() {
+        let labels: HashMap<String, String> = [
+            (MIN_CPUS_LABEL.to_string(), "2".to_string()),
+            (MIN_MEMORY_LABEL.to_string(), "8G".to_string()),
+        ]
+        .into();
+        let requirements = ImageRequirements::from_labels(&labels);
+        assert_eq!(requirements.min_cpus, Some(2));
+        assert_eq!(requirements.min_memory_gb, Some(8));
+        assert!(requirements.check(2, 8).is_ok());
+        assert!(requirements.check(1, 8).unwrap_err().contains("2 CPUs"));
+        assert!(requirements.check(4, 4).unwrap_err().contains("8GB"));
+
+        // Missing or malformed labels impose nothing
+        let labels: HashMap<String, String> =
+            [(MIN_MEMORY_LABEL.to_string(), "lots".to_string())].into();
+        assert_eq!(ImageRequirements::from_labels(&labels), ImageRequirements::default());
+        assert!(ImageRequirements::default().check(1, 1).is_ok());
+    }
+
     #[test]
     fn test_container_state_display() {
         assert_eq!(ContainerState::Running.to_string(), "running");
@@ -476,6 +1628,542 @@ mod tests {
         assert_eq!(service.artifacts_dir, "/custom/artifacts");
     }
 
+    fn sample_config() -> ContainerConfig {
+        ContainerConfig {
+            job_id: "job_abc123".to_string(),
+            user_id: "alice".to_string(),
+            job_type: JobType::Worker,
+            upload_id: "upload_1".to_string(),
+            input_from_job: None,
+            input_mount_path: "/input".to_string(),
+            image: "ubuntu:22.04".to_string(),
+            command: Some("echo hi".to_string()),
+            commands: None,
+            cpus: 2,
+            memory_gb: 4,
+            shm_size_mb: None,
+            task: None,
+            context: None,
+            git_branch: None,
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            secret_env: Vec::new(),
+            mount_artifacts: true,
+        }
+    }
+
+    #[test]
+    fn test_container_name_default_template() {
+        let service = PodmanService::new();
+        assert_eq!(service.container_name(&sample_config()).unwrap(), "job_job_abc123");
+    }
+
+    #[test]
+    fn test_container_name_custom_template() {
+        let service = PodmanService::new()
+            .with_name_template("flashpods-{user}-{type}-{job_id}")
+            .unwrap();
+        assert_eq!(
+            service.container_name(&sample_config()).unwrap(),
+            "flashpods-alice-worker-job_abc123"
+        );
+
+        // A user id that breaks podman's rules is rejected, not passed through
+        let config = ContainerConfig {
+            user_id: "bad user/".to_string(),
+            ..sample_config()
+        };
+        assert!(matches!(
+            service.container_name(&config),
+            Err(PodmanError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_name_template_validation() {
+        assert!(PodmanService::new().with_name_template("flashpods-{user}").is_err());
+        assert!(PodmanService::new().with_name_template("-{job_id}").is_err());
+        assert!(PodmanService::new().with_name_template("fp:{job_id}").is_err());
+        assert!(validate_container_name("fp_job.1-a").is_ok());
+        assert!(validate_container_name("").is_err());
+    }
+
+    #[test]
+    fn test_parse_podman_info() {
+        let json = r#"{
+            "host": {
+                "arch": "amd64",
+                "security": { "rootless": true, "seccompEnabled": true }
+            },
+            "store": { "graphDriverName": "overlay", "graphRoot": "/home/u/.local/share/containers/storage" },
+            "version": { "APIVersion": "4.9.3", "Version": "4.9.3" }
+        }"#;
+
+        let info = PodmanInfo::parse(json).unwrap();
+        assert_eq!(
+            info,
+            PodmanInfo {
+                version: Some("4.9.3".to_string()),
+                storage_driver: "overlay".to_string(),
+                rootless: true,
+            }
+        );
+
+        assert!(PodmanInfo::parse(r#"{"host": {}}"#).is_err());
+        assert!(PodmanInfo::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_run_args_single_command() {
+        let service = PodmanService::new();
+        let args = service.run_args(&sample_config()).unwrap();
+        assert_eq!(&args[args.len() - 4..], ["ubuntu:22.04", "/bin/sh", "-c", "echo hi"]);
+    }
+
+    #[test]
+    fn test_run_args_autoremove_toggle() {
+        let args = PodmanService::new().run_args(&sample_config()).unwrap();
+        assert!(!args.iter().any(|a| a == "--rm"));
+
+        let args = PodmanService::new()
+            .with_autoremove(true)
+            .run_args(&sample_config())
+            .unwrap();
+        assert_eq!(args[..3], ["run", "-d", "--rm"]);
+    }
+
+    #[test]
+    fn test_run_args_shm_size() {
+        let service = PodmanService::new();
+        let args = service.run_args(&sample_config()).unwrap();
+        assert!(!args.iter().any(|a| a.starts_with("--shm-size")));
+
+        let config = ContainerConfig {
+            shm_size_mb: Some(2048),
+            ..sample_config()
+        };
+        let args = service.run_args(&config).unwrap();
+        let memory = args.iter().position(|a| a == "--memory").unwrap();
+        assert_eq!(args[memory + 2], "--shm-size=2048m");
+    }
+
+    #[test]
+    fn test_run_args_cgroup_modes() {
+        let args_for = |mode: CgroupMode| {
+            PodmanService::new()
+                .with_cgroup_mode(mode)
+                .run_args(&sample_config())
+                .unwrap()
+        };
+
+        let args = args_for(CgroupMode::Default);
+        assert_eq!(args[..2], ["run", "-d"]);
+        assert!(!args.iter().any(|a| a.starts_with("--cgroup")));
+
+        let args = args_for(CgroupMode::Split);
+        assert_eq!(args[..3], ["run", "-d", "--cgroups=split"]);
+
+        let args = args_for("systemd:ci.slice".parse().unwrap());
+        assert_eq!(args[..4], ["run", "-d", "--cgroup-parent", "ci.slice"]);
+        // Limits are still passed; systemd enforces them on the scope
+        assert!(args.windows(2).any(|w| w == ["--memory", "4g"]));
+    }
+
+    #[test]
+    fn test_systemd_cgroup_manager_is_passed_to_every_command() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let podman = PodmanService::scripted(dir.path(), "echo ok")
+            .with_cgroup_mode(CgroupMode::Systemd {
+                slice: DEFAULT_SYSTEMD_SLICE.to_string(),
+            });
+
+        podman.create_container(&sample_config()).unwrap();
+        podman.logs("ctr", None, 1024).unwrap();
+        podman.version().unwrap();
+
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert_eq!(calls.lines().count(), 3);
+        assert!(calls.lines().all(|call| call.starts_with("--cgroup-manager=systemd ")));
+        assert_eq!(calls.matches("--cgroup-manager").count(), 3);
+    }
+
+    #[test]
+    fn test_parse_cgroup_mode() {
+        assert_eq!("default".parse(), Ok(CgroupMode::Default));
+        assert_eq!("split".parse(), Ok(CgroupMode::Split));
+        assert_eq!(
+            "systemd".parse(),
+            Ok(CgroupMode::Systemd {
+                slice: DEFAULT_SYSTEMD_SLICE.to_string()
+            })
+        );
+        for bad in ["", "scope", "systemd:", "systemd:ci", "systemd:../x.slice", "split:x"] {
+            assert!(bad.parse::<CgroupMode>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_run_args_sequential_commands() {
+        let service = PodmanService::new();
+        let config = ContainerConfig {
+            command: None,
+            commands: Some(vec!["make".to_string(), "make test".to_string()]),
+            ..sample_config()
+        };
+        let args = service.run_args(&config).unwrap();
+        assert_eq!(args[args.len() - 3], "/bin/sh");
+        assert_eq!(args[args.len() - 1], sequential_script(config.commands.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_run_args_input_from_job_mount() {
+        let service = PodmanService::new();
+        let args = service.run_args(&sample_config()).unwrap();
+        assert!(!args.iter().any(|a| a.ends_with(":/input:ro")));
+
+        let config = ContainerConfig {
+            input_from_job: Some("job_upstream".to_string()),
+            input_mount_path: "/data/in".to_string(),
+            ..sample_config()
+        };
+        let args = service.run_args(&config).unwrap();
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-v" && w[1] == "/var/lib/flashpods/artifacts/job_upstream:/data/in:ro"));
+    }
+
+    #[test]
+    fn test_sequential_script_stops_and_records_failed_step() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let commands = vec![
+            // A step can't pass off another step as the failed one
+            format!("echo one; echo 1 > {}/{}", STATE_MOUNT, FAILED_STEP_FILE),
+            "exit 3".to_string(),
+            "echo never".to_string(),
+        ];
+        let service = PodmanService::with_paths(
+            "/tmp/uploads".to_string(),
+            temp_dir.path().join("artifacts").display().to_string(),
+            "/tmp/spire.sock".to_string(),
+            "/tmp/token.sock".to_string(),
+        );
+        // Run against the job's state dir standing in for the mount
+        let state = service.state_path("job_steps");
+        std::fs::create_dir_all(&state).unwrap();
+        let script = sequential_script(&commands).replace(STATE_MOUNT, &state);
+
+        let output = Command::new("/bin/sh").args(["-c", &script]).output().unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "one\n");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("step 2 failed with exit code 3"));
+        assert_eq!(service.failed_step("job_steps"), Some(2));
+        // Nothing lands among the job's outputs
+        assert!(!temp_dir.path().join("artifacts").exists());
+    }
+
+    #[test]
+    fn test_state_dir_is_mounted_for_multi_command_workers_only() {
+        let service = PodmanService::new();
+        let state_mount = "/var/lib/flashpods/state/job_abc123:/run/flashpods/state:rw";
+        let args = service.run_args(&sample_config()).unwrap();
+        assert!(!args.iter().any(|a| a == state_mount));
+
+        let config = ContainerConfig {
+            command: None,
+            commands: Some(vec!["make".to_string(), "make test".to_string()]),
+            mount_artifacts: false,
+            ..sample_config()
+        };
+        let args = service.run_args(&config).unwrap();
+        assert!(args.windows(2).any(|w| w[0] == "-v" && w[1] == state_mount));
+    }
+
+    #[tokio::test]
+    async fn test_warm_images_pulls_each_image() {
+        let pulled = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let images = vec![
+            "ubuntu:22.04".to_string(),
+            "rust:latest".to_string(),
+            "missing:tag".to_string(),
+        ];
+
+        let recorder = pulled.clone();
+        let summary = warm_images(&images, 2, move |image| {
+            let recorder = recorder.clone();
+            async move {
+                recorder.lock().unwrap().push(image.clone());
+                if image == "missing:tag" {
+                    Err(PodmanError::ImagePull(CommandFailure {
+                        exit_code: Some(125),
+                        stderr: "manifest unknown".to_string(),
+                    }))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+
+        let mut seen = pulled.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, vec!["missing:tag", "rust:latest", "ubuntu:22.04"]);
+        assert_eq!(summary, WarmupSummary { pulled: 2, failed: 1 });
+    }
+
+    #[test]
+    fn test_classify_start_failure() {
+        let classify = |stderr: &str| {
+            classify_start_failure(CommandFailure {
+                exit_code: Some(125),
+                stderr: stderr.to_string(),
+            })
+        };
+
+        assert!(matches!(
+            classify("Error: docker.io/library/nosuch:latest: image not known"),
+            PodmanError::ImageNotFound(_)
+        ));
+        assert!(matches!(
+            classify("Error: initializing source docker://ghcr.io/acme/tool:v9: reading manifest v9 in ghcr.io/acme/tool: manifest unknown"),
+            PodmanError::ImageNotFound(_)
+        ));
+        assert!(matches!(
+            classify("Error: initializing source docker://registry.acme.io/private:1: reading manifest 1 in registry.acme.io/private: unauthorized: authentication required"),
+            PodmanError::ImagePullUnauthorized(_)
+        ));
+        assert!(matches!(
+            classify("Error: reading manifest latest in docker.io/acme/secret: requested access to the resource is denied"),
+            PodmanError::ImagePullUnauthorized(_)
+        ));
+        assert!(matches!(
+            classify("Error: crun: fork: Cannot allocate memory: OCI runtime error"),
+            PodmanError::InsufficientMemory(_)
+        ));
+        assert!(matches!(
+            classify("Error: statfs /var/lib/flashpods/uploads/upload_1: no such file or directory"),
+            PodmanError::InvalidMount(_)
+        ));
+        assert!(matches!(
+            classify(r#"Error: creating container storage: the container name "flashpods-job_1" is already in use by abc123. You have to remove that container to be able to reuse that name: that name is already in use"#),
+            PodmanError::NameInUse(_)
+        ));
+
+        let err = classify("Error: something unexpected");
+        assert!(matches!(err, PodmanError::ContainerStart(_)));
+        // The original failure is kept for the job's error
+        let err = classify("Error: nosuch: image not known");
+        assert_eq!(err.exit_code(), Some(125));
+        assert!(err.to_string().contains("Error: nosuch: image not known (exit code 125)"));
+    }
+
+    #[test]
+    fn test_command_failures_carry_exit_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let service = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  run) echo "Error: OCI runtime error" >&2; exit 126 ;;
+  kill) echo "Error: can't kill" >&2; exit 2 ;;
+  ps) exit 1 ;;
+esac"#,
+        );
+
+        let err = service.create_container(&sample_config()).unwrap_err();
+        assert!(matches!(&err, PodmanError::ContainerStart(f) if f.stderr == "Error: OCI runtime error"));
+        assert_eq!(err.exit_code(), Some(126));
+        assert_eq!(
+            err.to_string(),
+            "Failed to start container: Error: OCI runtime error (exit code 126)"
+        );
+
+        let err = service.kill_container("ctr_1").unwrap_err();
+        assert!(matches!(err, PodmanError::ContainerStop(_)));
+        assert_eq!(err.exit_code(), Some(2));
+
+        assert_eq!(service.list_containers().unwrap_err().exit_code(), Some(1));
+        // Errors that never reached podman have no exit code
+        assert_eq!(PodmanError::Command("spawn failed".to_string()).exit_code(), None);
+    }
+
+    #[test]
+    fn test_logs_are_capped_at_whole_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let service = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in logs) printf 'aaaa\nbbbb\ncccc\n'; printf 'eeee\n' >&2 ;; esac"#,
+        );
+
+        let all = service.logs("ctr", None, 1024).unwrap();
+        assert_eq!(all, ContainerLogs { text: "aaaa\nbbbb\ncccc\neeee\n".to_string(), truncated: false });
+
+        let capped = service.logs("ctr", None, 12).unwrap();
+        assert!(capped.truncated);
+        assert!(capped.text.len() <= 12);
+        assert!(capped.text.lines().all(|line| line.len() == 4));
+        assert_eq!(capped.text.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_parse_container_array_tolerates_stray_lines_and_bad_entries() {
+        let output = r#"time="2026-01-21T10:00:00Z" level=warning msg="The cgroupv2 manager is set to systemd"
+[
+  {"Id": "ctr_1", "Names": ["job_a"], "State": "running"},
+  "not a container",
+  {"Id": "ctr_2", "Names": ["job_, b"], "State": "exited", "Note": "braces } and \"quotes\""},
+  {"Id": "ctr_broken", "State": },
+  {"Names": ["no_id"]}
+]
+WARN[0000] trailing warning about [brackets]"#;
+        let containers = parse_container_array(output, "container list", true).unwrap();
+        let ids: Vec<&str> = containers.iter().map(|c| c["Id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["ctr_1", "ctr_2"]);
+        // Without skipping, any dropped entry fails the whole output
+        assert!(matches!(
+            parse_container_array(output, "inspect", false),
+            Err(PodmanError::Parse(_))
+        ));
+        assert!(matches!(
+            parse_container_array(r#"[{"Id": "ctr_1"}, {"Names": ["no_id"]}]"#, "inspect", false),
+            Err(PodmanError::Parse(_))
+        ));
+
+        // Well-formed output is taken as is, whatever follows the array
+        let containers = parse_container_array(r#"[{"Id": "ctr_1"}]"#, "inspect", false).unwrap();
+        assert_eq!(containers.len(), 1);
+        let output = "[{\"Id\": \"ctr_1\", \"Name\": \"a]b\"}]\nWARN[0000] layer [x] incomplete";
+        let containers = parse_container_array(output, "inspect", false).unwrap();
+        assert_eq!(containers[0]["Name"], "a]b");
+        assert!(parse_container_array("  \n", "inspect", false).unwrap().is_empty());
+        assert!(parse_container_array("[]", "inspect", false).unwrap().is_empty());
+
+        // Nothing usable at all is still an error
+        for skip_malformed in [true, false] {
+            assert!(matches!(
+                parse_container_array("Error: something went wrong", "inspect", skip_malformed),
+                Err(PodmanError::Parse(_))
+            ));
+            assert!(matches!(
+                parse_container_array("[{\"Id\": }]", "inspect", skip_malformed),
+                Err(PodmanError::Parse(_))
+            ));
+            assert!(matches!(
+                parse_container_array("[{\"Id\": \"ctr_1\"}", "inspect", skip_malformed),
+                Err(PodmanError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_list_and_inspect_survive_podman_quirks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let service = PodmanService::scripted(
+            dir.path(),
+            r#"echo 'WARN[0000] Found incomplete layer, deleting it'
+case "$1" in
+  ps) cat <<'EOF'
+[{"Id": "ctr_ok", "Names": ["flashpods-job_a"], "State": "running",
+  "Labels": {"flashpods-job": "true", "flashpods-job-id": "job_a"}},
+ {"Id": "ctr_bad", "Names": ["flashpods-job_b"], "State": running}]
+EOF
+  ;;
+  inspect) echo '[{"Id": "ctr_ok", "Name": "legacy", "State": {"Status": "running"}}]' ;;
+esac"#,
+        );
+
+        let containers = service.list_containers().unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].id, "ctr_ok");
+        assert_eq!(containers[0].state, ContainerState::Running);
+
+        let details = service.inspect_container("legacy").unwrap().unwrap();
+        assert_eq!(details.info.id, "ctr_ok");
+    }
+
+    #[test]
+    fn test_inspect_prefers_the_job_labelled_container() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let service = PodmanService::scripted(
+            dir.path(),
+            r#"case "$1" in
+  inspect) cat <<'EOF'
+[
+  {"Id": "ctr_stale", "Name": "job_abc", "State": {"Status": "exited", "ExitCode": 0},
+   "Config": {"Labels": {"flashpods-job": "true", "flashpods-job-id": "job_old"}}},
+  {"Id": "ctr_live", "Name": "job_abc", "State": {"Status": "running"},
+   "Config": {"Labels": {"flashpods-job": "true", "flashpods-job-id": "job_abc"}}}
+]
+EOF
+  ;;
+esac"#,
+        );
+
+        let info = service.inspect_job_container("job_abc", "job_abc").unwrap().unwrap();
+        assert_eq!(info.id, "ctr_live");
+        assert_eq!(info.state, ContainerState::Running);
+        // The stale duplicate was cleaned up
+        let calls = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        assert!(calls.lines().any(|l| l == "rm -f ctr_stale"), "{}", calls);
+
+        // Another job's container is never mistaken for this one
+        assert!(service.inspect_job_container("job_other", "job_abc").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_details() {
+        let container: serde_json::Value = serde_json::from_str(
+            r#"{"Id": "ctr_1", "Name": "legacy", "ImageName": "docker.io/library/ubuntu:22.04",
+                "State": {"Status": "exited", "ExitCode": 3,
+                          "StartedAt": "2026-01-21T10:00:00.123456789Z",
+                          "FinishedAt": "2026-01-21T11:00:00Z"},
+                "Config": {"Image": "ubuntu:22.04", "Cmd": ["make", "-j4"], "Labels": {"team": "ml"}},
+                "HostConfig": {"NanoCpus": 1500000000, "Memory": 2147483648}}"#,
+        )
+        .unwrap();
+        let details = parse_details(&container);
+        assert_eq!(details.info.id, "ctr_1");
+        assert_eq!(details.info.exit_code, Some(3));
+        assert_eq!(details.image, "ubuntu:22.04");
+        assert_eq!(details.command.as_deref(), Some("make -j4"));
+        assert_eq!(details.started_at.unwrap().to_rfc3339(), "2026-01-21T10:00:00.123456789+00:00");
+        assert!(details.finished_at.is_some());
+        assert_eq!((details.cpus, details.memory_gb), (Some(2), Some(2)));
+
+        // Unset limits and times that haven't happened yet
+        let container: serde_json::Value = serde_json::from_str(
+            r#"{"Id": "ctr_2", "State": {"Status": "running", "StartedAt": "2026-01-21T10:00:00Z",
+                                         "FinishedAt": "0001-01-01T00:00:00Z"},
+                "HostConfig": {"NanoCpus": 0, "Memory": 0}}"#,
+        )
+        .unwrap();
+        let details = parse_details(&container);
+        assert!(details.finished_at.is_none());
+        assert_eq!((details.cpus, details.memory_gb), (None, None));
+        assert_eq!(details.command, None);
+    }
+
+    #[test]
+    fn test_select_job_container_falls_back_to_unlabelled() {
+        let container = |id: &str, job: Option<&str>| ContainerInfo {
+            id: id.to_string(),
+            name: "job_abc".to_string(),
+            state: ContainerState::Exited,
+            exit_code: Some(0),
+            labels: job
+                .map(|j| [("flashpods-job-id".to_string(), j.to_string())].into())
+                .unwrap_or_default(),
+        };
+
+        let (chosen, rest) = select_job_container(
+            vec![container("ctr_other", Some("job_old")), container("ctr_legacy", None)],
+            "job_abc",
+        );
+        assert_eq!(chosen.unwrap().id, "ctr_legacy");
+        assert_eq!(rest.len(), 1);
+
+        let (chosen, rest) = select_job_container(Vec::new(), "job_abc");
+        assert!(chosen.is_none() && rest.is_empty());
+    }
+
     // Note: Integration tests that require podman should be in a separate
     // tests/ directory with #[ignore] attribute and run with --ignored flag
 }
@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SupervisorError {
+    #[error("All {max} background podman process slots are in use")]
+    AtCapacity { max: usize },
+}
+
+/// Caps the long-lived podman subprocesses (`podman logs -f` for log
+/// capture and live streams) running at once across features, so together
+/// they can't exhaust the process or file descriptor limits.
+///
+/// Each subprocess holds a slot for as long as it runs. Features that can
+/// catch up later wait for one; interactive ones are turned away instead.
+pub struct ProcessSupervisor {
+    max: usize,
+    slots: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+/// Background podman subprocesses, as reported by `/health/detailed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessCounts {
+    pub active: usize,
+    pub queued: usize,
+    /// `None` when uncapped
+    pub max: Option<usize>,
+}
+
+impl ProcessSupervisor {
+    /// A max of 0 leaves background processes uncapped
+    pub fn new(max: usize) -> Self {
+        let permits = if max == 0 { Semaphore::MAX_PERMITS } else { max };
+        Self {
+            max: permits,
+            slots: Arc::new(Semaphore::new(permits)),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait for a slot; it is released when the permit is dropped
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        let _waiting = Waiting::enter(&self.waiting);
+        let permit = self.slots.clone().acquire_owned().await;
+        permit.expect("supervisor semaphore is never closed")
+    }
+
+    /// Take a slot if one is free right now
+    pub fn try_acquire(&self) -> Result<OwnedSemaphorePermit, SupervisorError> {
+        self.slots
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| SupervisorError::AtCapacity { max: self.max })
+    }
+
+    pub fn counts(&self) -> ProcessCounts {
+        ProcessCounts {
+            active: self.max - self.slots.available_permits(),
+            queued: self.waiting.load(Ordering::Relaxed),
+            max: (self.max != Semaphore::MAX_PERMITS).then_some(self.max),
+        }
+    }
+}
+
+/// Counts a waiter in `queued` until it is dropped, whether it got a slot or
+/// its future was cancelled
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn enter(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_supervisor_enforces_the_cap() {
+        let supervisor = Arc::new(ProcessSupervisor::new(2));
+        let first = supervisor.try_acquire().unwrap();
+        let _second = supervisor.acquire().await;
+        assert_eq!(
+            supervisor.try_acquire().unwrap_err(),
+            SupervisorError::AtCapacity { max: 2 }
+        );
+
+        // A waiter queues until a slot frees up
+        let waiter = tokio::spawn({
+            let supervisor = supervisor.clone();
+            async move { supervisor.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            supervisor.counts(),
+            ProcessCounts {
+                active: 2,
+                queued: 1,
+                max: Some(2)
+            }
+        );
+        assert!(!waiter.is_finished());
+
+        drop(first);
+        let _third = waiter.await.unwrap();
+        assert_eq!(supervisor.counts().active, 2);
+        assert_eq!(supervisor.counts().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_leaves_the_queue() {
+        let supervisor = Arc::new(ProcessSupervisor::new(1));
+        let _held = supervisor.acquire().await;
+
+        let waiter = tokio::spawn({
+            let supervisor = supervisor.clone();
+            async move { supervisor.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(supervisor.counts().queued, 1);
+
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+        assert_eq!(supervisor.counts().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_max_is_uncapped() {
+        let supervisor = ProcessSupervisor::new(0);
+        let held: Vec<_> = (0..100).map(|_| supervisor.try_acquire().unwrap()).collect();
+        assert_eq!(supervisor.counts().active, held.len());
+        assert_eq!(supervisor.counts().max, None);
+    }
+}
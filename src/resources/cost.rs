@@ -0,0 +1,130 @@
+use std::str::FromStr;
+
+use crate::models::Job;
+
+/// How a finished job's size and runtime turn into cost units, for
+/// chargeback. Set with `FLASHPODS_COST_FORMULA`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CostFormula {
+    /// `cpus × memory_gb × hours` (`product`)
+    #[default]
+    Product,
+    /// `(cpu_rate × cpus + memory_rate × memory_gb) × hours`
+    /// (`linear:<cpu_rate>,<memory_rate>`)
+    Linear { cpu_rate: f64, memory_rate: f64 },
+}
+
+impl FromStr for CostFormula {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid cost formula '{}': expected 'product' or 'linear:<cpu_rate>,<memory_rate>'",
+                s
+            )
+        };
+        if s == "product" {
+            return Ok(Self::Product);
+        }
+        let rates = s.strip_prefix("linear:").ok_or_else(invalid)?;
+        let (cpu_rate, memory_rate) = rates.split_once(',').ok_or_else(invalid)?;
+        let rate = |r: &str| {
+            r.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|r| r.is_finite() && *r >= 0.0)
+                .ok_or_else(invalid)
+        };
+        Ok(Self::Linear {
+            cpu_rate: rate(cpu_rate)?,
+            memory_rate: rate(memory_rate)?,
+        })
+    }
+}
+
+impl CostFormula {
+    /// Cost of running `cpus` and `memory_gb` for `seconds`
+    pub fn cost(&self, cpus: i32, memory_gb: i32, seconds: i64) -> f64 {
+        let hours = seconds.max(0) as f64 / 3600.0;
+        match self {
+            Self::Product => cpus as f64 * memory_gb as f64 * hours,
+            Self::Linear {
+                cpu_rate,
+                memory_rate,
+            } => (cpu_rate * cpus as f64 + memory_rate * memory_gb as f64) * hours,
+        }
+    }
+
+    /// Cost of a finished job over all of its attempts
+    pub fn job_cost(&self, job: &Job) -> f64 {
+        self.cost(job.cpus, job.memory_gb, billable_seconds(job))
+    }
+}
+
+/// Runtime of earlier attempts plus the last one; jobs that never started
+/// cost nothing
+pub fn billable_seconds(job: &Job) -> i64 {
+    let last_attempt = match (job.started_at, job.completed_at) {
+        (Some(started), Some(completed)) => (completed - started).num_seconds().max(0),
+        _ => 0,
+    };
+    job.runtime_seconds + last_attempt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::JobStatus;
+    use chrono::{Duration, Utc};
+
+    fn finished_job(cpus: i32, memory_gb: i32, minutes: i64, earlier_seconds: i64) -> Job {
+        let completed = Utc::now();
+        Job {
+            cpus,
+            memory_gb,
+            timeout_minutes: 120,
+            runtime_seconds: earlier_seconds,
+            exit_code: Some(0),
+            created_at: completed - Duration::minutes(minutes),
+            started_at: Some(completed - Duration::minutes(minutes)),
+            completed_at: Some(completed),
+            ..Job::sample("job_cost", "default", JobStatus::Completed)
+        }
+    }
+
+    #[test]
+    fn test_parse_cost_formula() {
+        assert_eq!("product".parse(), Ok(CostFormula::Product));
+        assert_eq!(
+            "linear:1.5, 0.25".parse(),
+            Ok(CostFormula::Linear {
+                cpu_rate: 1.5,
+                memory_rate: 0.25
+            })
+        );
+        for bad in ["", "sum", "linear:", "linear:1", "linear:a,b", "linear:-1,1"] {
+            assert!(bad.parse::<CostFormula>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_job_cost() {
+        // 2 cpus × 4 GB × 30 minutes, plus a 30 minute earlier attempt
+        let job = finished_job(2, 4, 30, 1800);
+        assert_eq!(billable_seconds(&job), 3600);
+        assert_eq!(CostFormula::Product.job_cost(&job), 8.0);
+
+        let linear = CostFormula::Linear {
+            cpu_rate: 1.0,
+            memory_rate: 0.5,
+        };
+        assert_eq!(linear.job_cost(&job), 4.0);
+
+        let never_started = Job {
+            started_at: None,
+            ..finished_job(2, 4, 30, 0)
+        };
+        assert_eq!(CostFormula::Product.job_cost(&never_started), 0.0);
+    }
+}
@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Duration, Utc};
+
+use crate::jobs::{HOST_MAX_CPUS, HOST_MAX_MEMORY_GB};
+use crate::middleware::Caller;
+use crate::AppState;
+
+mod cost;
+
+pub use cost::CostFormula;
+
+/// Longest window `GET /resources/peaks` accepts; older samples are pruned
+pub const MAX_PEAK_WINDOW_DAYS: i64 = 30;
+
+const DEFAULT_PEAK_WINDOW: &str = "7d";
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/peaks", axum::routing::get(get_peaks))
+        .route("/costs", axum::routing::get(get_costs))
+}
+
+#[derive(serde::Deserialize)]
+struct PeaksQuery {
+    window: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct CostsQuery {
+    window: Option<String>,
+    user: Option<String>,
+}
+
+/// Parse a window such as `30m`, `24h` or `7d`
+pub fn parse_window(window: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid window '{}': expected e.g. 30m, 24h or 7d", window);
+    let unit = window.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = window[..window.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    if amount <= 0 {
+        return Err(invalid());
+    }
+    // Amounts too large for a `Duration` are certainly over the cap
+    let duration = match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        _ => return Err(invalid()),
+    };
+    duration
+        .filter(|duration| *duration <= Duration::days(MAX_PEAK_WINDOW_DAYS))
+        .ok_or_else(|| format!("Window may be at most {}d", MAX_PEAK_WINDOW_DAYS))
+}
+
+/// GET /resources/peaks - Peak and average usage over a window of the
+/// sampled history, for sizing the node
+async fn get_peaks(
+    State(state): State<AppState>,
+    Query(query): Query<PeaksQuery>,
+) -> impl IntoResponse {
+    let window = query.window.as_deref().unwrap_or(DEFAULT_PEAK_WINDOW);
+    let duration = parse_window(window).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_window",
+                "message": message
+            })),
+        )
+    })?;
+
+    let to = Utc::now();
+    let from = to - duration;
+    let peaks = state
+        .resource_samples
+        .peaks_since(from)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "window": window,
+        "from": from.to_rfc3339(),
+        "to": to.to_rfc3339(),
+        "samples": peaks.samples,
+        "peak": {
+            "cpus": peaks.peak_cpus,
+            "memory_gb": peaks.peak_memory_gb,
+            "concurrent_jobs": peaks.peak_running_jobs
+        },
+        "average": {
+            "cpus": peaks.avg_cpus,
+            "memory_gb": peaks.avg_memory_gb,
+            "cpu_utilization": peaks.avg_cpus / HOST_MAX_CPUS as f64,
+            "memory_utilization": peaks.avg_memory_gb / HOST_MAX_MEMORY_GB as f64
+        },
+        "host_capacity": { "cpus": HOST_MAX_CPUS, "memory_gb": HOST_MAX_MEMORY_GB }
+    })))
+}
+
+/// GET /resources/costs - Recorded job costs over a window, summed per
+/// user for chargeback. Regular users only see their own.
+async fn get_costs(
+    State(state): State<AppState>,
+    caller: Caller,
+    Query(query): Query<CostsQuery>,
+) -> impl IntoResponse {
+    let window = query.window.as_deref().unwrap_or(DEFAULT_PEAK_WINDOW);
+    let duration = parse_window(window).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_window",
+                "message": message
+            })),
+        )
+    })?;
+
+    let user = match query.user {
+        Some(user) if user != caller.user_id && !caller.is_admin => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": "costs_forbidden",
+                    "message": "Only admins can see other users' costs"
+                })),
+            ));
+        }
+        Some(user) => Some(user),
+        None if caller.is_admin => None,
+        None => Some(caller.user_id.clone()),
+    };
+
+    let to = Utc::now();
+    let from = to - duration;
+    let users = state
+        .job_repo
+        .costs_since(from, user.as_deref())
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    let total: f64 = users.iter().map(|u| u.cost_units).sum();
+
+    Ok(Json(serde_json::json!({
+        "window": window,
+        "from": from.to_rfc3339(),
+        "to": to.to_rfc3339(),
+        "total_cost_units": total,
+        "users": users
+    })))
+}
+
+/// Record current usage every `interval_secs`, dropping samples older than
+/// the longest window
+pub async fn sample_loop(state: AppState, interval_secs: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+        let recorded = match state.job_repo.get_resource_usage().await {
+            Ok(usage) => state.resource_samples.record(now, &usage).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to sample resource usage: {}", e);
+        }
+        if let Err(e) = state
+            .resource_samples
+            .prune(now - Duration::days(MAX_PEAK_WINDOW_DAYS))
+            .await
+        {
+            tracing::warn!("Failed to prune resource samples: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ResourceUsage;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_window("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_window("7d"), Ok(Duration::days(7)));
+        for bad in ["", "d", "7", "7w", "0d", "-1d", "31d", "200000000000d", "9223372036854775807m"] {
+            assert!(parse_window(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peaks_over_seeded_samples() {
+        let state = crate::test_state().await;
+        let now = Utc::now();
+        for (hours_ago, used_cpus, used_memory_gb, running_jobs) in
+            [(1, 4, 8, 2), (2, 12, 16, 5), (3, 8, 24, 3), (48, 16, 32, 9)]
+        {
+            let usage = ResourceUsage {
+                used_cpus,
+                used_memory_gb,
+                running_jobs,
+            };
+            state
+                .resource_samples
+                .record(now - Duration::hours(hours_ago), &usage)
+                .await
+                .unwrap();
+        }
+        let app = routes().with_state(state);
+
+        // The two-day-old sample falls outside the window
+        let (status, body) = get_json(app.clone(), "/peaks?window=1d").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["samples"], 3);
+        assert_eq!(body["peak"]["cpus"], 12);
+        assert_eq!(body["peak"]["memory_gb"], 24);
+        assert_eq!(body["peak"]["concurrent_jobs"], 5);
+        assert_eq!(body["average"]["cpus"], 8.0);
+        assert_eq!(body["average"]["memory_gb"], 16.0);
+        assert_eq!(body["average"]["cpu_utilization"], 0.5);
+        assert_eq!(body["average"]["memory_utilization"], 0.5);
+
+        // The default window covers it
+        let (_, body) = get_json(app.clone(), "/peaks").await;
+        assert_eq!(body["window"], "7d");
+        assert_eq!(body["samples"], 4);
+        assert_eq!(body["peak"]["concurrent_jobs"], 9);
+
+        let (status, body) = get_json(app, "/peaks?window=1y").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_window");
+    }
+
+    fn finished_job(user_id: &str) -> crate::models::Job {
+        crate::models::Job {
+            exit_code: Some(0),
+            ..crate::models::Job::sample(
+                &crate::db::JobRepository::generate_id(),
+                user_id,
+                crate::models::JobStatus::Completed,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_costs_are_scoped_to_the_caller() {
+        let state = crate::test_state().await;
+        for (user_id, cost) in [("default", 2.0), ("default", 0.5), ("other", 8.0)] {
+            let job = finished_job(user_id);
+            state.job_repo.create(&job, None).await.unwrap();
+            state
+                .job_repo
+                .update_status(&job.id, crate::models::JobStatus::Completed)
+                .await
+                .unwrap();
+            state.job_repo.set_cost_units(&job.id, cost).await.unwrap();
+        }
+        let app = routes().with_state(state);
+
+        let (status, body) = get_json(app.clone(), "/costs?window=1d").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_cost_units"], 2.5);
+        assert_eq!(body["users"].as_array().unwrap().len(), 1);
+        assert_eq!(body["users"][0]["user_id"], "default");
+        assert_eq!(body["users"][0]["jobs"], 2);
+
+        let (status, body) = get_json(app.clone(), "/costs?user=other").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "costs_forbidden");
+
+        let (status, _) = get_json(app, "/costs?window=1y").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::SecretRef;
+
+/// Errors resolving a job's secret references
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("Secret not found: {0}")]
+    NotFound(String),
+    #[error("Invalid secret reference: {0}")]
+    InvalidRef(String),
+    #[error("Secret {0} spans several lines, which an environment variable can't carry")]
+    Multiline(String),
+    #[error("Failed to read secret {0}: {1}")]
+    Io(String, std::io::Error),
+}
+
+/// Source of secret values for `secret_refs`.
+///
+/// Values are resolved when a container is started and handed to podman in
+/// a short-lived env file; they are never stored.
+pub trait SecretProvider: Send + Sync {
+    fn resolve(&self, reference: &str) -> Result<String, SecretError>;
+}
+
+/// Reads each secret from a file named by the reference, relative to a
+/// secrets directory (e.g. a Vault agent or systemd credentials mount).
+///
+/// References aren't scoped to the submitting user: any caller may name any
+/// file in the directory, so it suits single-tenant deployments only.
+pub struct FileSecretProvider {
+    dir: PathBuf,
+}
+
+impl FileSecretProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path_for(&self, reference: &str) -> Result<PathBuf, SecretError> {
+        let relative = Path::new(reference);
+        // References must stay inside the secrets directory
+        let escapes = relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)));
+        if reference.is_empty() || escapes {
+            return Err(SecretError::InvalidRef(reference.to_string()));
+        }
+        Ok(self.dir.join(relative))
+    }
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn resolve(&self, reference: &str) -> Result<String, SecretError> {
+        let path = self.path_for(reference)?;
+        match std::fs::read_to_string(&path) {
+            // Files written by `echo` or editors end with a newline that isn't part of the secret
+            Ok(value) => Ok(value.strip_suffix('\n').unwrap_or(&value).to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(SecretError::NotFound(reference.to_string()))
+            }
+            Err(e) => Err(SecretError::Io(reference.to_string(), e)),
+        }
+    }
+}
+
+/// Resolve every reference into `(env_name, value)` pairs, failing on the
+/// first one that can't be resolved or has a line break, which podman's env
+/// file has no way to express
+pub fn resolve_all(
+    provider: &dyn SecretProvider,
+    refs: &[SecretRef],
+) -> Result<Vec<(String, String)>, SecretError> {
+    refs.iter()
+        .map(|r| {
+            let value = provider.resolve(&r.reference)?;
+            if value.contains(['\n', '\r']) {
+                return Err(SecretError::Multiline(r.reference.clone()));
+            }
+            Ok((r.env_name.clone(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_ref(env_name: &str, reference: &str) -> SecretRef {
+        SecretRef {
+            env_name: env_name.to_string(),
+            reference: reference.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_file_provider_resolves_refs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("db")).unwrap();
+        std::fs::write(dir.path().join("db/password"), "hunter2\n").unwrap();
+        std::fs::write(dir.path().join("token"), "abc").unwrap();
+        let provider = FileSecretProvider::new(dir.path());
+
+        let resolved = resolve_all(
+            &provider,
+            &[secret_ref("DB_PASSWORD", "db/password"), secret_ref("TOKEN", "token")],
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            vec![
+                ("DB_PASSWORD".to_string(), "hunter2".to_string()),
+                ("TOKEN".to_string(), "abc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_provider_missing_ref() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let provider = FileSecretProvider::new(dir.path());
+
+        let err = resolve_all(&provider, &[secret_ref("TOKEN", "nope")]).unwrap_err();
+        assert!(matches!(err, SecretError::NotFound(r) if r == "nope"));
+    }
+
+    #[test]
+    fn test_file_provider_rejects_escaping_refs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let provider = FileSecretProvider::new(dir.path().join("secrets"));
+        std::fs::write(dir.path().join("outside"), "x").unwrap();
+
+        for reference in ["", "../outside", "/etc/passwd", "a/../../outside"] {
+            assert!(
+                matches!(provider.resolve(reference), Err(SecretError::InvalidRef(_))),
+                "{:?} should be rejected",
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiline_secret_is_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("key"), "-----BEGIN KEY-----\nabc\n").unwrap();
+        let provider = FileSecretProvider::new(dir.path());
+
+        let err = resolve_all(&provider, &[secret_ref("KEY", "key")]).unwrap_err();
+        assert!(matches!(err, SecretError::Multiline(r) if r == "key"));
+    }
+}
@@ -5,13 +5,55 @@ use axum::{
     Json,
 };
 
-use crate::db::FinalizeError;
-use crate::models::UploadResponse;
+use crate::db::{FinalizeError, TouchError};
+use crate::middleware::Caller;
+use crate::models::{Upload, UploadResponse, UploadState};
 use crate::AppState;
 
+mod orphans;
+mod progress;
+
+pub use progress::DirStatsCache;
+use progress::{compute_progress, upload_stats, ExpectedTotals};
+
+/// What finalizing an upload a job already consumed does. Set with
+/// `FLASHPODS_CONSUMED_FINALIZE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConsumedFinalizePolicy {
+    /// Always 409 `upload_already_consumed` (`strict`)
+    #[default]
+    Strict,
+    /// Return the upload as it is while its size and file count still match
+    /// what it was finalized with, for clients that retry finalize after
+    /// their job started (`idempotent`)
+    Idempotent,
+}
+
+impl std::str::FromStr for ConsumedFinalizePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "idempotent" => Ok(Self::Idempotent),
+            _ => Err(format!(
+                "Invalid consumed finalize policy '{}': expected 'strict' or 'idempotent'",
+                s
+            )),
+        }
+    }
+}
+
 pub fn routes() -> axum::Router<AppState> {
     axum::Router::new()
         .route("/:id/finalize", axum::routing::post(finalize_upload))
+        .route("/:id/touch", axum::routing::post(touch_upload))
+        .route("/:id/verify", axum::routing::post(verify_upload))
+        .route(
+            "/orphans",
+            axum::routing::get(list_orphans).delete(remove_orphans),
+        )
+        .route("/:id/progress", axum::routing::get(get_upload_progress))
         .route("/:id", axum::routing::get(get_upload).delete(delete_upload))
 }
 
@@ -19,8 +61,12 @@ pub fn routes() -> axum::Router<AppState> {
 /// Mark upload as finalized after rsync completes
 async fn finalize_upload(
     State(state): State<AppState>,
+    caller: Caller,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
+    // Scanning the upload shares the finalize-to-start throughput limit
+    let _slot = state.pipeline.enter().await;
+
     let upload_dir = std::path::Path::new(&state.upload_config.upload_dir).join(&id);
 
     // Check if upload directory exists
@@ -48,6 +94,16 @@ async fn finalize_upload(
         }
     };
 
+    // Re-finalizing a consumed upload stores nothing new, so the policy
+    // answers it before any limit is checked
+    match state.upload_repo.get(&id).await {
+        Ok(Some(upload)) if upload.state == UploadState::Consumed => {
+            return refinalize_consumed(&state, upload, size_bytes, file_count);
+        }
+        Ok(_) => {}
+        Err(e) => return Err(crate::db::database_error_response(&e, e.to_string())),
+    }
+
     // Check size limit
     if size_bytes > state.upload_config.max_upload_size_bytes {
         return Err((
@@ -63,19 +119,18 @@ async fn finalize_upload(
         ));
     }
 
-    // Check total disk usage
-    match state.upload_repo.get_total_disk_usage().await {
-        Ok(current_usage) => {
-            if current_usage + size_bytes > state.upload_config.max_total_disk_bytes {
-                return Err((
-                    StatusCode::INSUFFICIENT_STORAGE,
-                    Json(serde_json::json!({
-                        "error": "insufficient_storage",
-                        "message": "Total upload storage quota exceeded"
-                    })),
-                ));
-            }
+    // Check total and per-user disk usage
+    match storage_quota_violation(&state, &caller.user_id, size_bytes).await {
+        Ok(Some(message)) => {
+            return Err((
+                StatusCode::INSUFFICIENT_STORAGE,
+                Json(serde_json::json!({
+                    "error": "insufficient_storage",
+                    "message": message
+                })),
+            ));
         }
+        Ok(None) => {}
         Err(e) => {
             tracing::error!("Failed to get disk usage: {}", e);
         }
@@ -83,7 +138,7 @@ async fn finalize_upload(
 
     // Create upload record if it doesn't exist (idempotent)
     if state.upload_repo.get(&id).await.ok().flatten().is_none() {
-        if let Err(e) = state.upload_repo.create(&id, "default").await {
+        if let Err(e) = state.upload_repo.create(&id, &caller.user_id).await {
             tracing::warn!("Failed to create upload record: {}", e);
         }
     }
@@ -91,6 +146,18 @@ async fn finalize_upload(
     // Finalize in database
     match state.upload_repo.finalize(&id, size_bytes, file_count).await {
         Ok(upload) => Ok(Json(UploadResponse::from(upload))),
+        // Consumed by a job starting since the check above
+        Err(FinalizeError::AlreadyConsumed) => match state.upload_repo.get(&id).await {
+            Ok(Some(upload)) => refinalize_consumed(&state, upload, size_bytes, file_count),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "upload_not_found",
+                    "message": format!("Upload {} not found", id)
+                })),
+            )),
+            Err(e) => Err(crate::db::database_error_response(&e, e.to_string())),
+        },
         Err(e) => {
             let (status, error_code) = match e {
                 FinalizeError::NotFound => (StatusCode::NOT_FOUND, "upload_not_found"),
@@ -101,9 +168,73 @@ async fn finalize_upload(
                     (StatusCode::CONFLICT, "upload_already_consumed")
                 }
                 FinalizeError::Expired => (StatusCode::GONE, "upload_expired"),
-                FinalizeError::Database(_) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "database_error")
+                FinalizeError::Database(ref db_err) => crate::db::database_error_status(db_err),
+            };
+            Err((
+                status,
+                Json(serde_json::json!({
+                    "error": error_code,
+                    "message": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+/// Answer to finalizing `upload` again after a job consumed it, per
+/// `FLASHPODS_CONSUMED_FINALIZE`; `size_bytes` and `file_count` are what the
+/// directory holds now
+fn refinalize_consumed(
+    state: &AppState,
+    upload: Upload,
+    size_bytes: i64,
+    file_count: i64,
+) -> Result<Json<UploadResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let unchanged = upload.size_bytes == Some(size_bytes) && upload.file_count == Some(file_count);
+    match state.job_config.consumed_finalize {
+        ConsumedFinalizePolicy::Idempotent if unchanged => Ok(Json(UploadResponse::from(upload))),
+        ConsumedFinalizePolicy::Idempotent => Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "upload_already_consumed",
+                "message": "Upload already consumed by a job and its files have changed since finalize"
+            })),
+        )),
+        ConsumedFinalizePolicy::Strict => Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "upload_already_consumed",
+                "message": FinalizeError::AlreadyConsumed.to_string()
+            })),
+        )),
+    }
+}
+
+/// POST /uploads/:id/touch
+/// Keep an in-progress upload alive during a long rsync
+async fn touch_upload(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let config = &state.upload_config;
+    match state
+        .upload_repo
+        .touch(
+            &id,
+            chrono::Duration::minutes(config.ttl_uploading_minutes as i64),
+            chrono::Duration::minutes(config.max_uploading_lifetime_minutes as i64),
+        )
+        .await
+    {
+        Ok(upload) => Ok(Json(UploadResponse::from(upload))),
+        Err(e) => {
+            let (status, error_code) = match e {
+                TouchError::NotFound => (StatusCode::NOT_FOUND, "upload_not_found"),
+                TouchError::NotUploading(_) => (StatusCode::CONFLICT, "upload_not_uploading"),
+                TouchError::LifetimeExceeded => {
+                    (StatusCode::CONFLICT, "upload_lifetime_exceeded")
                 }
+                TouchError::Database(ref db_err) => crate::db::database_error_status(db_err),
             };
             Err((
                 status,
@@ -116,6 +247,269 @@ async fn finalize_upload(
     }
 }
 
+/// POST /uploads/:id/verify
+/// Report an upload directory's size and whether it would pass finalize's
+/// limits, without changing its state
+async fn verify_upload(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let upload_dir = std::path::Path::new(&state.upload_config.upload_dir).join(&id);
+
+    if !upload_dir.exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "upload_not_found",
+                "message": format!("Upload directory {} does not exist", id)
+            })),
+        ));
+    }
+
+    let (size_bytes, file_count) = calculate_dir_stats(&upload_dir).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "stat_failed",
+                "message": format!("Failed to calculate upload stats: {}", e)
+            })),
+        )
+    })?;
+
+    let config = &state.upload_config;
+    let mut violations = Vec::new();
+    if size_bytes > config.max_upload_size_bytes {
+        violations.push(format!(
+            "Upload size {} exceeds maximum {}",
+            size_bytes, config.max_upload_size_bytes
+        ));
+    }
+    match storage_quota_violation(&state, &caller.user_id, size_bytes).await {
+        Ok(Some(message)) => violations.push(message),
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to get disk usage: {}", e),
+    }
+
+    Ok(Json(serde_json::json!({
+        "upload_id": id,
+        "size_bytes": size_bytes,
+        "file_count": file_count,
+        "max_upload_size_bytes": config.max_upload_size_bytes,
+        "within_limits": violations.is_empty(),
+        "violations": violations
+    })))
+}
+
+/// Why storing `adding_bytes` more for `user_id` would break the upload
+/// storage quotas: the total across users, and each user's share when
+/// `max_user_disk_bytes` is set. Pass 0 to check uploads already counted.
+pub async fn storage_quota_violation(
+    state: &AppState,
+    user_id: &str,
+    adding_bytes: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let config = &state.upload_config;
+    if state.upload_repo.get_total_disk_usage().await? + adding_bytes > config.max_total_disk_bytes {
+        return Ok(Some("Total upload storage quota exceeded".to_string()));
+    }
+    if config.max_user_disk_bytes > 0
+        && state.upload_repo.get_user_disk_usage(user_id).await? + adding_bytes
+            > config.max_user_disk_bytes
+    {
+        return Ok(Some(format!(
+            "Upload storage quota of user {} exceeded ({} bytes)",
+            user_id, config.max_user_disk_bytes
+        )));
+    }
+    Ok(None)
+}
+
+/// Orphaned directories under `upload_dir`, as an error response on failure
+async fn scan_orphans(
+    state: &AppState,
+) -> Result<Vec<orphans::OrphanDir>, (StatusCode, Json<serde_json::Value>)> {
+    let known_ids = state
+        .upload_repo
+        .all_ids()
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    let upload_dir = std::path::PathBuf::from(&state.upload_config.upload_dir);
+    let scan = move || orphans::find_orphans(&upload_dir, &known_ids);
+    let scanned = match tokio::task::spawn_blocking(scan).await {
+        Ok(scanned) => scanned,
+        Err(e) => Err(std::io::Error::other(e.to_string())),
+    };
+    scanned.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "scan_failed",
+                "message": format!("Failed to scan upload directory: {}", e)
+            })),
+        )
+    })
+}
+
+/// GET /uploads/orphans
+/// Admin: list directories under `upload_dir` with no upload record
+async fn list_orphans(
+    State(state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    caller.require_admin("This endpoint requires the admin token")?;
+    let orphans = scan_orphans(&state).await?;
+    let total_bytes: i64 = orphans.iter().map(|o| o.size_bytes).sum();
+
+    Ok(Json(serde_json::json!({
+        "orphans": orphans,
+        "total_bytes": total_bytes
+    })))
+}
+
+#[derive(serde::Deserialize)]
+struct RemoveOrphansQuery {
+    older_than_minutes: Option<i64>,
+}
+
+/// DELETE /uploads/orphans
+/// Admin: delete orphaned directories untouched for `older_than_minutes`
+/// (default: the uploading TTL). Younger ones may be rsyncs in progress,
+/// whose record is only created at finalize.
+async fn remove_orphans(
+    State(state): State<AppState>,
+    caller: Caller,
+    axum::extract::Query(query): axum::extract::Query<RemoveOrphansQuery>,
+) -> impl IntoResponse {
+    caller.require_admin("This endpoint requires the admin token")?;
+    let older_than_minutes = query
+        .older_than_minutes
+        .unwrap_or(state.upload_config.ttl_uploading_minutes as i64);
+    if older_than_minutes < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_age",
+                "message": "older_than_minutes must not be negative"
+            })),
+        ));
+    }
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::minutes(older_than_minutes);
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+    let mut freed_bytes = 0;
+    for orphan in scan_orphans(&state).await? {
+        if orphan.last_modified > cutoff {
+            kept.push(orphan.upload_id);
+            continue;
+        }
+        let dir = std::path::Path::new(&state.upload_config.upload_dir).join(&orphan.upload_id);
+        let remove = move || std::fs::remove_dir_all(&dir);
+        let removal = match tokio::task::spawn_blocking(remove).await {
+            Ok(removal) => removal,
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        };
+        match removal {
+            Ok(()) => {
+                tracing::info!("Removed orphaned upload directory {}", orphan.upload_id);
+                freed_bytes += orphan.size_bytes;
+                removed.push(orphan.upload_id);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to remove orphaned upload directory {}: {}", orphan.upload_id, e);
+                kept.push(orphan.upload_id);
+            }
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "removed": removed,
+        "kept": kept,
+        "freed_bytes": freed_bytes
+    })))
+}
+
+/// GET /uploads/:id/progress
+/// Report how much of an in-flight rsync has landed, against the totals in
+/// the upload's progress marker if the client wrote one
+async fn get_upload_progress(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if id.is_empty() || id.contains('/') || id.contains("..") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_upload_id",
+                "message": format!("Invalid upload ID {:?}", id)
+            })),
+        ));
+    }
+
+    // Directories without a record haven't been claimed by a finalize yet
+    let upload = state
+        .upload_repo
+        .get(&id)
+        .await
+        .map_err(|e| crate::db::database_error_response(&e, e.to_string()))?;
+    if let Some(upload) = upload {
+        if upload.user_id != caller.user_id && !caller.is_admin {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": "upload_forbidden",
+                    "message": format!("Upload {} belongs to another user", id)
+                })),
+            ));
+        }
+    }
+
+    let upload_dir = std::path::Path::new(&state.upload_config.upload_dir).join(&id);
+
+    if !upload_dir.exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "upload_not_found",
+                "message": format!("Upload directory {} does not exist", id)
+            })),
+        ));
+    }
+
+    let cache = state.upload_progress.clone();
+    let walk_id = id.clone();
+    let walked = match tokio::task::spawn_blocking(move || {
+        let stats = cache.get_or_walk(&walk_id, || upload_stats(&upload_dir))?;
+        Ok((stats, ExpectedTotals::read(&upload_dir)))
+    })
+    .await
+    {
+        Ok(walked) => walked,
+        Err(e) => Err(std::io::Error::other(e.to_string())),
+    };
+    let ((size_bytes, file_count), expected) = walked.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "stat_failed",
+                "message": format!("Failed to calculate upload stats: {}", e)
+            })),
+        )
+    })?;
+
+    let progress = compute_progress(size_bytes, file_count, expected);
+    Ok(Json(serde_json::json!({
+        "upload_id": id,
+        "size_bytes": progress.size_bytes,
+        "file_count": progress.file_count,
+        "expected_size_bytes": progress.expected_size_bytes,
+        "expected_file_count": progress.expected_file_count,
+        "percent": progress.percent
+    })))
+}
+
 /// GET /uploads/:id
 /// Get upload status
 async fn get_upload(
@@ -131,13 +525,7 @@ async fn get_upload(
                 "message": format!("Upload {} not found", id)
             })),
         )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "database_error",
-                "message": e.to_string()
-            })),
-        )),
+        Err(e) => Err(crate::db::database_error_response(&e, e.to_string())),
     }
 }
 
@@ -165,13 +553,7 @@ async fn delete_upload(
                 "message": format!("Upload {} not found or already in terminal state", id)
             })),
         )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "database_error",
-                "message": e.to_string()
-            })),
-        )),
+        Err(e) => Err(crate::db::database_error_response(&e, e.to_string())),
     }
 }
 
@@ -226,6 +608,261 @@ mod tests {
         assert_eq!(size, 0);
     }
 
+    #[tokio::test]
+    async fn test_verify_reports_stats_and_limits() {
+        use tower::ServiceExt;
+
+        let upload_root = tempfile::TempDir::new().unwrap();
+        let upload_dir = upload_root.path().join("upload_verify1");
+        std::fs::create_dir(&upload_dir).unwrap();
+        std::fs::write(upload_dir.join("a.txt"), "hello").unwrap();
+        std::fs::write(upload_dir.join("b.txt"), "world!").unwrap();
+
+        let verify = |max_upload_size_bytes: i64| {
+            let upload_root = upload_root.path().display().to_string();
+            async move {
+                let mut state = crate::test_state().await;
+                state.upload_config.upload_dir = upload_root;
+                state.upload_config.max_upload_size_bytes = max_upload_size_bytes;
+
+                let response = routes()
+                    .with_state(state)
+                    .oneshot(
+                        axum::http::Request::builder()
+                            .method("POST")
+                            .uri("/upload_verify1/verify")
+                            .body(axum::body::Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+
+        let body = verify(1024).await;
+        assert_eq!(body["size_bytes"], 11);
+        assert_eq!(body["file_count"], 2);
+        assert_eq!(body["within_limits"], true);
+        assert_eq!(body["violations"], serde_json::json!([]));
+
+        let body = verify(10).await;
+        assert_eq!(body["within_limits"], false);
+        assert_eq!(body["violations"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_finalize_records_caller_user() {
+        use tower::ServiceExt;
+
+        let upload_root = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(upload_root.path().join("upload_owner1")).unwrap();
+        let mut state = crate::test_state().await;
+        state.upload_config.upload_dir = upload_root.path().display().to_string();
+
+        let mut request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload_owner1/finalize")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(Caller {
+            user_id: "team-a".to_string(),
+            is_admin: false,
+        });
+
+        let response = routes()
+            .with_state(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let upload = state.upload_repo.get("upload_owner1").await.unwrap().unwrap();
+        assert_eq!(upload.user_id, "team-a");
+    }
+
+    #[test]
+    fn test_parse_consumed_finalize_policy() {
+        assert_eq!("strict".parse(), Ok(ConsumedFinalizePolicy::Strict));
+        assert_eq!("idempotent".parse(), Ok(ConsumedFinalizePolicy::Idempotent));
+        assert!("lenient".parse::<ConsumedFinalizePolicy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refinalize_of_consumed_upload_follows_policy() {
+        use tower::ServiceExt;
+
+        let upload_root = tempfile::TempDir::new().unwrap();
+        let upload_dir = upload_root.path().join("upload_again1");
+        std::fs::create_dir(&upload_dir).unwrap();
+        std::fs::write(upload_dir.join("a.txt"), "hello").unwrap();
+
+        let finalize = |policy: ConsumedFinalizePolicy, state: &AppState| {
+            let mut state = state.clone();
+            state.job_config.consumed_finalize = policy;
+            async move {
+                let mut request = axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/upload_again1/finalize")
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                request.extensions_mut().insert(Caller {
+                    user_id: "default".to_string(),
+                    is_admin: false,
+                });
+                let response = routes().with_state(state).oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+
+        let mut state = crate::test_state().await;
+        state.upload_config.upload_dir = upload_root.path().display().to_string();
+        let (status, _) = finalize(ConsumedFinalizePolicy::Strict, &state).await;
+        assert_eq!(status, StatusCode::OK);
+        // As a job's start would
+        sqlx::query("UPDATE uploads SET state = 'consumed' WHERE id = 'upload_again1'")
+            .execute(state.db.inner())
+            .await
+            .unwrap();
+
+        // Re-finalizing adds nothing, so a full disk doesn't get in the way
+        state.upload_config.max_total_disk_bytes = 4;
+
+        // Strict, the default: any re-finalize is a conflict
+        let (status, body) = finalize(ConsumedFinalizePolicy::default(), &state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "upload_already_consumed");
+
+        // Idempotent: the unchanged upload comes back as it is
+        let (status, body) = finalize(ConsumedFinalizePolicy::Idempotent, &state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["state"], "consumed");
+        assert_eq!((body["size_bytes"].as_i64(), body["file_count"].as_i64()), (Some(5), Some(1)));
+
+        // ...but not once the files differ from what was finalized
+        std::fs::write(upload_dir.join("b.txt"), "more").unwrap();
+        let (status, body) = finalize(ConsumedFinalizePolicy::Idempotent, &state).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "upload_already_consumed");
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_directories_are_listed_and_removed() {
+        use tower::ServiceExt;
+
+        let upload_root = tempfile::TempDir::new().unwrap();
+        for id in ["upload_recorded", "upload_orphan1", "upload_orphan2"] {
+            std::fs::create_dir(upload_root.path().join(id)).unwrap();
+            std::fs::write(upload_root.path().join(id).join("data"), "12345678").unwrap();
+        }
+        let mut state = crate::test_state().await;
+        state.upload_config.upload_dir = upload_root.path().display().to_string();
+        state.upload_repo.create("upload_recorded", "default").await.unwrap();
+
+        let send = |method: &str, uri: &str, is_admin: bool| {
+            let mut request = axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(Caller {
+                user_id: "ops".to_string(),
+                is_admin,
+            });
+            let app = routes().with_state(state.clone());
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+
+        let (status, body) = send("GET", "/orphans", false).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "admin_required");
+
+        let (status, body) = send("GET", "/orphans", true).await;
+        assert_eq!(status, StatusCode::OK);
+        let ids: Vec<&str> = body["orphans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["upload_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["upload_orphan1", "upload_orphan2"]);
+        assert_eq!(body["total_bytes"], 16);
+
+        // Fresh directories may be rsyncs in progress, so the default age keeps them
+        let (_, body) = send("DELETE", "/orphans", true).await;
+        assert_eq!(body["removed"], serde_json::json!([]));
+        assert_eq!(body["kept"], serde_json::json!(["upload_orphan1", "upload_orphan2"]));
+
+        let (status, body) = send("DELETE", "/orphans?older_than_minutes=0", true).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["removed"], serde_json::json!(["upload_orphan1", "upload_orphan2"]));
+        assert_eq!(body["freed_bytes"], 16);
+        assert!(!upload_root.path().join("upload_orphan1").exists());
+        assert!(upload_root.path().join("upload_recorded").exists());
+    }
+
+    #[tokio::test]
+    async fn test_progress_checks_owner_and_id() {
+        use tower::ServiceExt;
+
+        let upload_root = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(upload_root.path().join("upload_mine1")).unwrap();
+        std::fs::write(upload_root.path().join("upload_mine1").join("data"), "1234").unwrap();
+        let mut state = crate::test_state().await;
+        state.upload_config.upload_dir = upload_root.path().display().to_string();
+        state.upload_repo.create("upload_mine1", "team-a").await.unwrap();
+
+        let send = |uri: &str, user_id: &str, is_admin: bool| {
+            let mut request = axum::http::Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(Caller {
+                user_id: user_id.to_string(),
+                is_admin,
+            });
+            let app = routes().with_state(state.clone());
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+
+        let (status, body) = send("/upload_mine1/progress", "team-a", false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["size_bytes"], 4);
+
+        let (status, body) = send("/upload_mine1/progress", "team-b", false).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "upload_forbidden");
+
+        let (status, _) = send("/upload_mine1/progress", "ops", true).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send("/..%2F..%2Fetc/progress", "team-a", false).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_upload_id");
+    }
+
     #[tokio::test]
     async fn test_upload_repository() {
         let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A directory under `upload_dir` that no `uploads` row refers to, e.g. left
+/// by a crashed finalize or a manual rsync
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrphanDir {
+    pub upload_id: String,
+    pub size_bytes: i64,
+    pub file_count: i64,
+    /// Newest modification anywhere in the directory; an rsync still
+    /// writing into it keeps this recent
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Directories directly under `upload_dir` whose name isn't in `known_ids`.
+/// A missing `upload_dir` has no orphans.
+pub fn find_orphans(upload_dir: &Path, known_ids: &HashSet<String>) -> std::io::Result<Vec<OrphanDir>> {
+    let entries = match std::fs::read_dir(upload_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut orphans = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(upload_id) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if known_ids.contains(&upload_id) {
+            continue;
+        }
+
+        let path = entry.path();
+        let (size_bytes, file_count) = super::calculate_dir_stats(&path)?;
+        orphans.push(OrphanDir {
+            upload_id,
+            size_bytes,
+            file_count,
+            last_modified: newest_mtime(&path)?.into(),
+        });
+    }
+    orphans.sort_by(|a, b| a.upload_id.cmp(&b.upload_id));
+    Ok(orphans)
+}
+
+/// Latest modification time of `path` or anything below it
+fn newest_mtime(path: &Path) -> std::io::Result<SystemTime> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    let mut newest = metadata.modified()?;
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            newest = newest.max(newest_mtime(&entry?.path())?);
+        }
+    }
+    Ok(newest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_orphans_skips_known_ids_and_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for id in ["upload_known", "upload_orphan", "upload_nested"] {
+            std::fs::create_dir(dir.path().join(id)).unwrap();
+        }
+        std::fs::write(dir.path().join("upload_orphan/a.txt"), "hello").unwrap();
+        std::fs::create_dir(dir.path().join("upload_nested/src")).unwrap();
+        std::fs::write(dir.path().join("upload_nested/src/b.rs"), "fn main() {}").unwrap();
+        // Stray files aren't uploads
+        std::fs::write(dir.path().join("stray.txt"), "x").unwrap();
+
+        let known: HashSet<String> = ["upload_known".to_string()].into();
+        let orphans = find_orphans(dir.path(), &known).unwrap();
+
+        let ids: Vec<&str> = orphans.iter().map(|o| o.upload_id.as_str()).collect();
+        assert_eq!(ids, ["upload_nested", "upload_orphan"]);
+        assert_eq!((orphans[0].size_bytes, orphans[0].file_count), (12, 1));
+        assert_eq!((orphans[1].size_bytes, orphans[1].file_count), (5, 1));
+        assert!(orphans[1].last_modified <= Utc::now());
+
+        assert!(find_orphans(&dir.path().join("missing"), &known).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// File a client may write into its upload directory, before or alongside
+/// the rsync, announcing how much it is about to send
+pub const PROGRESS_MARKER: &str = ".flashpods-progress";
+
+/// How long a directory walk is reused for progress polls
+const STATS_TTL: Duration = Duration::from_secs(2);
+
+/// Size and file count of an upload directory, leaving out the progress
+/// marker
+pub fn upload_stats(upload_dir: &Path) -> std::io::Result<(i64, i64)> {
+    let (size, count) = super::calculate_dir_stats(upload_dir)?;
+    match std::fs::metadata(upload_dir.join(PROGRESS_MARKER)) {
+        Ok(marker) if marker.is_file() => Ok((size - marker.len() as i64, count - 1)),
+        _ => Ok((size, count)),
+    }
+}
+
+/// Totals announced in the progress marker; either may be omitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct ExpectedTotals {
+    pub size_bytes: Option<i64>,
+    pub file_count: Option<i64>,
+}
+
+impl ExpectedTotals {
+    /// Read the marker from an upload directory; a missing or unreadable
+    /// marker means nothing is expected
+    pub fn read(upload_dir: &Path) -> Self {
+        std::fs::read(upload_dir.join(PROGRESS_MARKER))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UploadProgress {
+    pub size_bytes: i64,
+    pub file_count: i64,
+    pub expected_size_bytes: Option<i64>,
+    pub expected_file_count: Option<i64>,
+    /// 0-100, by bytes if the expected size is known, otherwise by files
+    pub percent: Option<f64>,
+}
+
+/// Progress of an upload whose directory currently holds `size_bytes` in
+/// `file_count` files, not counting the marker itself
+pub fn compute_progress(
+    size_bytes: i64,
+    file_count: i64,
+    expected: ExpectedTotals,
+) -> UploadProgress {
+    let ratio = |done: i64, total: i64| {
+        if total <= 0 {
+            100.0
+        } else {
+            (done as f64 / total as f64 * 100.0).min(100.0)
+        }
+    };
+    let percent = match expected {
+        ExpectedTotals {
+            size_bytes: Some(total),
+            ..
+        } => Some(ratio(size_bytes, total)),
+        ExpectedTotals {
+            file_count: Some(total),
+            ..
+        } => Some(ratio(file_count, total)),
+        _ => None,
+    };
+
+    UploadProgress {
+        size_bytes,
+        file_count,
+        expected_size_bytes: expected.size_bytes,
+        expected_file_count: expected.file_count,
+        percent,
+    }
+}
+
+/// Recent directory walks by upload id, so clients polling progress during
+/// an rsync don't each walk the tree
+#[derive(Default)]
+pub struct DirStatsCache {
+    entries: Mutex<HashMap<String, CachedStats>>,
+}
+
+/// When a walk finished, and its `(size_bytes, file_count)`
+type CachedStats = (Instant, (i64, i64));
+
+impl DirStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `(size_bytes, file_count)` of the directory, walking it with `walk`
+    /// unless a walk finished within the last few seconds
+    pub fn get_or_walk(
+        &self,
+        id: &str,
+        walk: impl FnOnce() -> std::io::Result<(i64, i64)>,
+    ) -> std::io::Result<(i64, i64)> {
+        let now = Instant::now();
+        if let Some((at, stats)) = self.entries.lock().unwrap().get(id) {
+            if now.duration_since(*at) < STATS_TTL {
+                return Ok(*stats);
+            }
+        }
+
+        let stats = walk()?;
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (at, _)| now.duration_since(*at) < STATS_TTL);
+        entries.insert(id.to_string(), (now, stats));
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_of_partial_upload() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(PROGRESS_MARKER),
+            r#"{"size_bytes": 40, "file_count": 4}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("a.txt"), "0123456789").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/b.txt"), "0123456789").unwrap();
+
+        let expected = ExpectedTotals::read(dir.path());
+        assert_eq!(expected.size_bytes, Some(40));
+        let (size, count) = upload_stats(dir.path()).unwrap();
+        assert_eq!((size, count), (20, 2));
+
+        let progress = compute_progress(size, count, expected);
+        assert_eq!(progress.percent, Some(50.0));
+
+        // Without a byte total, files are counted instead
+        let by_files = ExpectedTotals {
+            size_bytes: None,
+            file_count: Some(8),
+        };
+        assert_eq!(compute_progress(size, count, by_files).percent, Some(25.0));
+
+        // Overshooting the estimate caps at 100, and no estimate gives none
+        let small = ExpectedTotals {
+            size_bytes: Some(10),
+            file_count: None,
+        };
+        assert_eq!(compute_progress(size, count, small).percent, Some(100.0));
+        let unknown = ExpectedTotals::default();
+        assert_eq!(compute_progress(size, count, unknown).percent, None);
+    }
+
+    #[test]
+    fn test_walks_are_cached_briefly() {
+        let cache = DirStatsCache::new();
+        assert_eq!(cache.get_or_walk("upload_a", || Ok((1, 1))).unwrap(), (1, 1));
+        // Served from the cache rather than walking again
+        assert_eq!(cache.get_or_walk("upload_a", || Ok((2, 2))).unwrap(), (1, 1));
+        assert_eq!(cache.get_or_walk("upload_b", || Ok((3, 3))).unwrap(), (3, 3));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::middleware::Caller;
+use crate::models::JobStatus;
+use crate::AppState;
+
+pub fn routes() -> axum::Router<AppState> {
+    axum::Router::new().route("/:id/stats", axum::routing::get(get_stats))
+}
+
+/// Aggregates over one user's jobs and uploads, for dashboards
+#[derive(Debug, Serialize)]
+pub struct UserStats {
+    pub user_id: String,
+    /// Jobs per status; statuses with no jobs are omitted
+    pub jobs_by_status: BTreeMap<String, i64>,
+    pub total_jobs: i64,
+    /// Jobs `starting` or `running`
+    pub active_jobs: i64,
+    pub cpu_hours: f64,
+    /// Bytes held by the user's uploads that still have their files
+    pub upload_disk_bytes: i64,
+}
+
+/// GET /users/:id/stats - The user themselves or an admin
+async fn get_stats(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(user_id): Path<String>,
+) -> Result<Json<UserStats>, (StatusCode, Json<serde_json::Value>)> {
+    if user_id != caller.user_id && !caller.is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "stats_forbidden",
+                "message": "Only admins can see other users' stats"
+            })),
+        ));
+    }
+
+    let db_error = |e: sqlx::Error| crate::db::database_error_response(&e, e.to_string());
+    let jobs_by_status = state
+        .job_repo
+        .status_counts_for_user(&user_id)
+        .await
+        .map_err(db_error)?;
+    let cpu_hours = state
+        .job_repo
+        .cpu_hours_for_user(&user_id, Utc::now())
+        .await
+        .map_err(db_error)?;
+    let upload_disk_bytes = state
+        .upload_repo
+        .get_user_disk_usage(&user_id)
+        .await
+        .map_err(db_error)?;
+
+    let count = |status: JobStatus| jobs_by_status.get(&status.to_string()).copied().unwrap_or(0);
+    Ok(Json(UserStats {
+        total_jobs: jobs_by_status.values().sum(),
+        active_jobs: count(JobStatus::Starting) + count(JobStatus::Running),
+        jobs_by_status,
+        user_id,
+        cpu_hours,
+        upload_disk_bytes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JobRepository;
+    use crate::models::Job;
+    use axum::body::Body;
+    use axum::http::Request;
+    use chrono::Duration;
+    use tower::ServiceExt;
+
+    fn job(user_id: &str, status: JobStatus, cpus: i32) -> Job {
+        Job {
+            cpus,
+            timeout_minutes: 120,
+            ..Job::sample(&JobRepository::generate_id(), user_id, status)
+        }
+    }
+
+    /// Store `job` as having run from `started_minutes_ago`, finishing
+    /// `ran_minutes` later unless it's still running, after its earlier
+    /// attempts' `runtime_seconds`
+    async fn seed(state: &AppState, job: &Job, started_minutes_ago: i64, ran_minutes: Option<i64>) {
+        state.job_repo.create(job, None).await.unwrap();
+        let started = Utc::now() - Duration::minutes(started_minutes_ago);
+        let completed = ran_minutes.map(|m| (started + Duration::minutes(m)).to_rfc3339());
+        sqlx::query("UPDATE jobs SET started_at = ?, completed_at = ?, runtime_seconds = ? WHERE id = ?")
+            .bind(started.to_rfc3339())
+            .bind(completed)
+            .bind(job.runtime_seconds)
+            .bind(&job.id)
+            .execute(state.db.inner())
+            .await
+            .unwrap();
+    }
+
+    async fn get_stats_as(state: AppState, user_id: &str, caller: Caller) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder()
+            .uri(format!("/{}/stats", user_id))
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(caller);
+        let response = routes().with_state(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_user_stats_aggregate_jobs_and_uploads() {
+        let state = crate::test_state().await;
+
+        // 2 CPUs for 30 minutes, plus an earlier 30 minute attempt
+        let retried = Job {
+            runtime_seconds: 1800,
+            ..job("alice", JobStatus::Completed, 2)
+        };
+        seed(&state, &retried, 60, Some(30)).await;
+        // 4 CPUs, running for 15 minutes so far
+        seed(&state, &job("alice", JobStatus::Running, 4), 15, None).await;
+        seed(&state, &job("alice", JobStatus::Failed, 1), 10, Some(0)).await;
+        state.job_repo.create(&job("alice", JobStatus::Pending, 8), None).await.unwrap();
+        // Someone else's job counts for nobody here
+        seed(&state, &job("bob", JobStatus::Running, 8), 60, None).await;
+
+        for (id, user, size) in [("upload_a1", "alice", 1000), ("upload_a2", "alice", 500), ("upload_b", "bob", 99)] {
+            state.upload_repo.create(id, user).await.unwrap();
+            state.upload_repo.finalize(id, size, 1).await.unwrap();
+        }
+
+        let alice = Caller {
+            user_id: "alice".to_string(),
+            is_admin: false,
+        };
+        let (status, body) = get_stats_as(state.clone(), "alice", alice).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user_id"], "alice");
+        assert_eq!(
+            body["jobs_by_status"],
+            serde_json::json!({"completed": 1, "failed": 1, "pending": 1, "running": 1})
+        );
+        assert_eq!(body["total_jobs"], 4);
+        assert_eq!(body["active_jobs"], 1);
+        assert_eq!(body["upload_disk_bytes"], 1500);
+        // 2 × 1h + 4 × 0.25h
+        let cpu_hours = body["cpu_hours"].as_f64().unwrap();
+        assert!((cpu_hours - 3.0).abs() < 0.01, "{}", cpu_hours);
+    }
+
+    #[tokio::test]
+    async fn test_user_stats_are_for_self_or_admins() {
+        let state = crate::test_state().await;
+        let bob = Caller {
+            user_id: "bob".to_string(),
+            is_admin: false,
+        };
+        let (status, body) = get_stats_as(state.clone(), "alice", bob).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "stats_forbidden");
+
+        let admin = Caller {
+            user_id: "ops".to_string(),
+            is_admin: true,
+        };
+        let (status, body) = get_stats_as(state, "alice", admin).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_jobs"], 0);
+        assert_eq!(body["cpu_hours"], 0.0);
+    }
+}